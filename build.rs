@@ -0,0 +1,35 @@
+//! 构建时把 definitions/ 下的 TOML 测试定义嵌入二进制
+//!
+//! 生成 `$OUT_DIR/embedded_definitions.rs`，由 `src/definitions.rs` include。
+//! 新增或修改定义文件后重新构建即可，不需要改动 Rust 代码。
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn main() {
+    let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("definitions");
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+
+    let mut out = String::from("pub(crate) const EMBEDDED_DEFINITIONS: &[(&str, &str)] = &[\n");
+    for path in &files {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let name = format!("definitions/{}", path.file_name().unwrap().to_string_lossy());
+        out.push_str(&format!("    ({:?}, include_str!({:?})),\n", name, path.display().to_string()));
+    }
+    out.push_str("];\n");
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("embedded_definitions.rs"), out).unwrap();
+}
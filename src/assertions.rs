@@ -1,21 +1,61 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use regex::Regex;
 use tester_utils::{Logger, TesterError};
+use crate::model::{CacheModel, EvictionPolicy};
+use crate::response::Response;
+use crate::stats::{Stats, STATS_FIELDS};
 
 /// Assertion trait for verifying command responses
 /// 
 /// This trait abstracts the verification logic, allowing different
 /// validation strategies to be composed and reused.
+/// 
+/// Responses are structured per command: a single line, or the lines of a
+/// framed multi-line reply (see `response::Framing`).
 pub trait Assertion {
     /// Verify the actual responses against expected criteria
-    fn verify(&self, actual: &[String], logger: &Logger) -> Result<(), TesterError>;
+    fn verify(&self, actual: &[Response], logger: &Logger) -> Result<(), TesterError>;
+
+    /// Verify with the commands that produced the responses
+    /// 
+    /// Assertions that only look at responses don't need to override this;
+    /// rules about command/response pairs (see `PredicateAssertion`) do.
+    fn verify_transcript(&self, transcript: &Transcript, logger: &Logger) -> Result<(), TesterError> {
+        self.verify(&transcript.responses, logger)
+    }
+}
+
+/// The commands sent and the responses received, paired by index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcript {
+    pub commands: Vec<String>,
+    pub responses: Vec<Response>,
+}
+
+impl Transcript {
+    pub fn new(commands: Vec<String>, responses: Vec<Response>) -> Self {
+        Self { commands, responses }
+    }
+
+    /// (command, response) pairs; commands that got no response are left out
+    pub fn pairs(&self) -> impl Iterator<Item = (&str, &Response)> {
+        self.commands.iter().map(|s| s.as_str()).zip(self.responses.iter())
+    }
 }
 
 /// ExactMatchAssertion verifies that responses match exactly line-by-line
 /// 
 /// This is the default assertion used for most cache test cases.
 /// It provides friendly output showing each line's verification status.
+/// A multi-line response is compared against an expected string whose
+/// lines are joined with `\n` (e.g. `"c\nb\na"`).
 pub struct ExactMatchAssertion {
     expected: Vec<String>,
     command_hints: Option<Vec<String>>,
+    stderr_block: Option<String>,
+    all_mismatches: bool,
 }
 
 impl ExactMatchAssertion {
@@ -24,6 +64,8 @@ impl ExactMatchAssertion {
         Self {
             expected,
             command_hints: None,
+            stderr_block: None,
+            all_mismatches: false,
         }
     }
 
@@ -35,90 +77,829 @@ impl ExactMatchAssertion {
         self.command_hints = Some(commands);
         self
     }
+
+    /// Attach the program's stderr (already labelled and truncated)
+    /// 
+    /// On failure the block is printed to the log and appended to the
+    /// returned error, so students can line up their own debug prints
+    /// with the failing command.
+    pub fn with_stderr(mut self, stderr_block: String) -> Self {
+        self.stderr_block = Some(stderr_block);
+        self
+    }
+
+    /// Report every mismatch instead of stopping at the first one
+    /// 
+    /// The failure then contains an aligned table of index / command /
+    /// expected / actual around each divergence, which shows at a glance
+    /// whether a long run has one bug or a systematic shift.
+    pub fn with_all_mismatches(mut self) -> Self {
+        self.all_mismatches = true;
+        self
+    }
 }
 
 impl Assertion for ExactMatchAssertion {
-    fn verify(&self, actual: &[String], logger: &Logger) -> Result<(), TesterError> {
-        let mut success_logs: Vec<String> = Vec::new();
-
-        // Check each expected line
-        for (i, expected_line) in self.expected.iter().enumerate() {
-            if i >= actual.len() {
-                // Log all successful lines before showing the error
-                for log in &success_logs {
-                    logger.successf(log, &[]);
-                }
-                
-                let hint = self.command_hints.as_ref()
-                    .and_then(|cmds| cmds.get(i))
-                    .map(|cmd| format!(" ({})", cmd))
-                    .unwrap_or_default();
-                
-                logger.errorf(&format!("? {}{}", expected_line, hint), &[]);
-                return Err(TesterError::User(
-                    format!(
-                        "Expected response #{} to be '{}', but didn't receive enough responses (got {} response(s))",
-                        i + 1,
-                        expected_line,
-                        actual.len()
-                    ).into()
-                ));
+    fn verify(&self, actual: &[Response], logger: &Logger) -> Result<(), TesterError> {
+        let expected: Vec<ExpectedLine> = self.expected.iter().cloned().map(ExpectedLine::Exact).collect();
+        let verify = if self.all_mismatches { verify_all_lines } else { verify_lines };
+        verify(&expected, actual, self.command_hints.as_deref(), self.stderr_block.as_deref(), logger)
+    }
+}
+
+/// One expected response
+/// 
+/// Besides exact strings, a line can state only what is actually guaranteed
+/// for a nondeterministic run: a pattern, an integer range, a set of
+/// acceptable responses or a number within a tolerance.
+#[derive(Debug, Clone)]
+pub enum ExpectedLine {
+    /// The response text must equal this string
+    Exact(String),
+
+    /// The whole response text must match this pattern
+    Pattern(Regex),
+
+    /// The response must be a well-formed, consistent STATS line
+    Stats(StatsAssertion),
+
+    /// An integer within this range, e.g. SIZE in `0..=capacity`
+    Range(RangeInclusive<i64>),
+
+    /// Any one of these responses, e.g. `["1", "NULL"]` for a key that may have been evicted
+    OneOf(Vec<String>),
+
+    /// A number within `tolerance` of `value`
+    Approx { value: f64, tolerance: f64 },
+}
+
+impl ExpectedLine {
+    pub fn exact(line: impl Into<String>) -> Self {
+        Self::Exact(line.into())
+    }
+
+    /// Compile a pattern; it is anchored so it must match the whole response
+    pub fn pattern(pattern: &str) -> Result<Self, TesterError> {
+        Regex::new(&format!("^(?:{})$", pattern))
+            .map(Self::Pattern)
+            .map_err(|e| {
+                TesterError::Configuration(format!("Invalid regex pattern '{}': {}", pattern, e))
+            })
+    }
+
+    pub fn range(range: RangeInclusive<i64>) -> Self {
+        Self::Range(range)
+    }
+
+    pub fn one_of(responses: &[&str]) -> Self {
+        Self::OneOf(responses.iter().map(|s| s.to_string()).collect())
+    }
+
+    pub fn approx(value: f64, tolerance: f64) -> Self {
+        Self::Approx { value, tolerance }
+    }
+
+    pub fn matches(&self, actual: &str) -> bool {
+        self.check(actual).is_ok()
+    }
+
+    /// Check a response; on mismatch returns per-field details (empty for exact / regex lines)
+    pub fn check(&self, actual: &str) -> Result<(), Vec<String>> {
+        match self {
+            Self::Exact(expected) if actual == expected => Ok(()),
+            Self::Pattern(re) if re.is_match(actual) => Ok(()),
+            Self::Exact(_) | Self::Pattern(_) => Err(Vec::new()),
+            Self::Stats(stats) => stats.check(actual).map(|_| ()),
+            Self::Range(range) => match actual.parse::<i64>() {
+                Ok(value) if range.contains(&value) => Ok(()),
+                Ok(_) => Err(Vec::new()),
+                Err(_) => Err(vec!["the response is not an integer".to_string()]),
+            },
+            Self::OneOf(options) if options.iter().any(|option| option == actual) => Ok(()),
+            Self::OneOf(_) => Err(Vec::new()),
+            Self::Approx { value, tolerance } => match actual.parse::<f64>() {
+                Ok(actual) if (actual - value).abs() <= *tolerance => Ok(()),
+                Ok(actual) => Err(vec![format!("off by {}", format_number((actual - value).abs()))]),
+                Err(_) => Err(vec!["the response is not a number".to_string()]),
+            },
+        }
+    }
+
+    /// How the expectation is shown in error messages
+    fn describe(&self) -> String {
+        match self {
+            Self::Exact(expected) => quote(expected),
+            Self::Stats(stats) => quote(&stats.to_string()),
+            Self::Pattern(_) => format!("a match for {}", self),
+            Self::Range(_) => format!("an integer in {}", self),
+            Self::OneOf(options) => format!(
+                "one of {}",
+                options.iter().map(|option| quote(option)).collect::<Vec<_>>().join(", ")
+            ),
+            Self::Approx { .. } => format!("a number in {}", self),
+        }
+    }
+}
+
+/// Short form used in the per-line log, e.g. `0..=10`, `1|NULL`, `66.67±0.01`
+impl fmt::Display for ExpectedLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact(expected) => write!(f, "{}", expected),
+            Self::Stats(stats) => write!(f, "{}", stats),
+            Self::Pattern(re) => {
+                let pattern = re.as_str();
+                let pattern = pattern.strip_prefix("^(?:").and_then(|p| p.strip_suffix(")$")).unwrap_or(pattern);
+                write!(f, "/{}/", pattern)
+            }
+            Self::Range(range) => write!(f, "{}..={}", range.start(), range.end()),
+            Self::OneOf(options) => write!(f, "{}", options.join("|")),
+            Self::Approx { value, tolerance } => {
+                write!(f, "{}±{}", format_number(*value), format_number(*tolerance))
             }
+        }
+    }
+}
+
+/// Format a float without trailing zeros (66.67, 0.5, 10)
+fn format_number(value: f64) -> String {
+    let text = format!("{:.6}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// RegexAssertion verifies responses line-by-line against exact strings or patterns
+/// 
+/// Used where output is not fully deterministic (e.g. STATS after a
+/// concurrent workload). Exact, regex and STATS lines can be mixed in one test,
+/// and the output is the same as ExactMatchAssertion's. Patterns are
+/// compiled once, when the assertion is built.
+pub struct RegexAssertion {
+    expected: Vec<ExpectedLine>,
+    command_hints: Option<Vec<String>>,
+    stderr_block: Option<String>,
+    all_mismatches: bool,
+}
+
+impl RegexAssertion {
+    /// Create a new RegexAssertion from mixed exact / regex expectations
+    pub fn new(expected: Vec<ExpectedLine>) -> Self {
+        Self {
+            expected,
+            command_hints: None,
+            stderr_block: None,
+            all_mismatches: false,
+        }
+    }
 
-            let actual_line = &actual[i];
+    /// Create a RegexAssertion where every expected response is a pattern
+    pub fn from_patterns(patterns: &[&str]) -> Result<Self, TesterError> {
+        let expected = patterns
+            .iter()
+            .map(|pattern| ExpectedLine::pattern(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(expected))
+    }
 
-            if actual_line != expected_line {
-                // Log all successful lines before showing the error
-                for log in &success_logs {
-                    logger.successf(log, &[]);
+    /// Add command hints for better error messages (see ExactMatchAssertion)
+    pub fn with_commands(mut self, commands: Vec<String>) -> Self {
+        self.command_hints = Some(commands);
+        self
+    }
+
+    /// Attach the program's stderr (see ExactMatchAssertion)
+    pub fn with_stderr(mut self, stderr_block: String) -> Self {
+        self.stderr_block = Some(stderr_block);
+        self
+    }
+
+    /// Report every mismatch in a table (see ExactMatchAssertion)
+    pub fn with_all_mismatches(mut self) -> Self {
+        self.all_mismatches = true;
+        self
+    }
+
+    /// Replace the expectation for response #`index` (0-based)
+    pub fn with_override(mut self, index: usize, expected: ExpectedLine) -> Self {
+        if let Some(line) = self.expected.get_mut(index) {
+            *line = expected;
+        }
+        self
+    }
+}
+
+impl Assertion for RegexAssertion {
+    fn verify(&self, actual: &[Response], logger: &Logger) -> Result<(), TesterError> {
+        let verify = if self.all_mismatches { verify_all_lines } else { verify_lines };
+        verify(&self.expected, actual, self.command_hints.as_deref(), self.stderr_block.as_deref(), logger)
+    }
+}
+
+/// Expected value of one STATS field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldExpectation {
+    /// Any value (only the invariants apply)
+    Any,
+
+    /// Exactly this text, e.g. "2" or "66.67"
+    Exact(String),
+
+    /// A count of at least this much
+    AtLeast(u64),
+}
+
+impl fmt::Display for FieldExpectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => write!(f, "*"),
+            Self::Exact(value) => write!(f, "{}", value),
+            Self::AtLeast(min) => write!(f, ">={}", min),
+        }
+    }
+}
+
+/// StatsAssertion verifies a STATS line field by field
+/// 
+/// The line is parsed into typed fields (see `stats::Stats`), so a failure
+/// reports each wrong field ("evictions: expected 1, got 0") instead of one
+/// long string diff. Cross-field invariants are always checked, even for
+/// fields whose exact value is unknown:
+/// - fields appear in order: hits misses hit_rate evictions expirations size capacity
+/// - hit_rate equals hits/(hits+misses) rounded to two decimals
+/// - size <= capacity
+/// 
+/// As an `Assertion` it expects exactly one response (the STATS reply).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatsAssertion {
+    fields: Vec<(&'static str, FieldExpectation)>,
+}
+
+impl StatsAssertion {
+    /// Create an assertion that only checks format and invariants
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse an expected STATS line
+    /// 
+    /// Each field is `name:value`, `name:*` (any value) or `name:>=N` (at least N);
+    /// omitted fields accept any value. For example:
+    /// `hits:* misses:* hit_rate:* evictions:>=1 expirations:0 size:* capacity:20`
+    pub fn from_expected(line: &str) -> Result<Self, TesterError> {
+        let mut assertion = Self::new();
+        for part in line.split_whitespace() {
+            let parsed = part.split_once(':').and_then(|(name, value)| {
+                let name = STATS_FIELDS.into_iter().find(|field| *field == name)?;
+                let expectation = match value {
+                    "*" => FieldExpectation::Any,
+                    _ => match value.strip_prefix(">=") {
+                        Some(min) => FieldExpectation::AtLeast(min.parse().ok()?),
+                        None => FieldExpectation::Exact(value.to_string()),
+                    },
+                };
+                Some((name, expectation))
+            });
+            let (name, expectation) = parsed.ok_or_else(|| {
+                TesterError::Configuration(format!("Invalid expected STATS field '{}' in '{}'", part, line))
+            })?;
+            assertion = assertion.with_field(name, expectation);
+        }
+        Ok(assertion)
+    }
+
+    /// Set the expectation for one field (`name` is one of `stats::STATS_FIELDS`)
+    pub fn with_field(mut self, name: &'static str, expectation: FieldExpectation) -> Self {
+        self.fields.retain(|(field, _)| *field != name);
+        self.fields.push((name, expectation));
+        self
+    }
+
+    /// Expect an exact value for one field
+    pub fn expect_field(self, name: &'static str, value: impl ToString) -> Self {
+        self.with_field(name, FieldExpectation::Exact(value.to_string()))
+    }
+
+    /// Expect a count of at least `min` for one field
+    pub fn expect_at_least(self, name: &'static str, min: u64) -> Self {
+        self.with_field(name, FieldExpectation::AtLeast(min))
+    }
+
+    /// Parse and check a STATS line, returning one message per problem
+    pub fn check(&self, line: &str) -> Result<Stats, Vec<String>> {
+        let stats = Stats::parse(line).map_err(|e| vec![format!("STATS format: {}", e)])?;
+
+        let mut problems = Vec::new();
+        for name in STATS_FIELDS {
+            let Some((_, expectation)) = self.fields.iter().find(|(field, _)| *field == name) else {
+                continue;
+            };
+            let actual = stats.field(name).unwrap_or_default();
+            let ok = match expectation {
+                FieldExpectation::Any => true,
+                FieldExpectation::Exact(expected) => actual == *expected,
+                FieldExpectation::AtLeast(min) => actual.parse::<u64>().is_ok_and(|value| value >= *min),
+            };
+            if !ok {
+                let expected = match expectation {
+                    FieldExpectation::AtLeast(min) => format!("at least {}", min),
+                    other => other.to_string(),
+                };
+                problems.push(format!("{}: expected {}, got {}", name, expected, actual));
+            }
+        }
+        problems.extend(stats.invariant_violations());
+
+        if problems.is_empty() {
+            Ok(stats)
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+/// The expected line, e.g. `hits:2 misses:* hit_rate:* ...`
+impl fmt::Display for StatsAssertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = STATS_FIELDS
+            .iter()
+            .map(|name| {
+                let expectation = self.fields.iter().find(|(field, _)| field == name);
+                format!("{}:{}", name, expectation.map_or(FieldExpectation::Any, |(_, e)| e.clone()))
+            })
+            .collect();
+        write!(f, "{}", fields.join(" "))
+    }
+}
+
+impl Assertion for StatsAssertion {
+    fn verify(&self, actual: &[Response], logger: &Logger) -> Result<(), TesterError> {
+        verify_lines(&[ExpectedLine::Stats(self.clone())], actual, None, None, logger)
+    }
+}
+
+/// AllOf passes when every inner assertion passes
+/// 
+/// Inner assertions run in order and the first failure is returned.
+pub struct AllOf(pub Vec<Box<dyn Assertion>>);
+
+impl Assertion for AllOf {
+    fn verify(&self, actual: &[Response], logger: &Logger) -> Result<(), TesterError> {
+        self.0.iter().try_for_each(|assertion| assertion.verify(actual, logger))
+    }
+
+    fn verify_transcript(&self, transcript: &Transcript, logger: &Logger) -> Result<(), TesterError> {
+        self.0.iter().try_for_each(|assertion| assertion.verify_transcript(transcript, logger))
+    }
+}
+
+/// AnyOf passes when at least one inner assertion passes
+/// 
+/// Alternatives are tried quietly; the first one that passes is run again
+/// with the real logger so its output is shown. If none pass, every
+/// alternative's failure is reported.
+pub struct AnyOf(pub Vec<Box<dyn Assertion>>);
+
+impl AnyOf {
+    fn check(
+        &self,
+        logger: &Logger,
+        verify: impl Fn(&dyn Assertion, &Logger) -> Result<(), TesterError>,
+    ) -> Result<(), TesterError> {
+        let quiet = Logger::get_quiet_logger("");
+        let mut failures = Vec::new();
+        for assertion in &self.0 {
+            match verify(assertion.as_ref(), &quiet) {
+                Ok(()) => return verify(assertion.as_ref(), logger),
+                Err(err) => failures.push(err.to_string()),
+            }
+        }
+
+        logger.errorf(&format!("𐄂 none of {} alternatives matched", self.0.len()), &[]);
+        let mut message = format!("None of the {} acceptable outcomes matched:", self.0.len());
+        for (i, failure) in failures.iter().enumerate() {
+            message.push_str(&format!("\n{}. {}", i + 1, failure.replace('\n', "\n   ")));
+        }
+        Err(TesterError::User(message.into()))
+    }
+}
+
+impl Assertion for AnyOf {
+    fn verify(&self, actual: &[Response], logger: &Logger) -> Result<(), TesterError> {
+        self.check(logger, |assertion, logger| assertion.verify(actual, logger))
+    }
+
+    fn verify_transcript(&self, transcript: &Transcript, logger: &Logger) -> Result<(), TesterError> {
+        self.check(logger, |assertion, logger| assertion.verify_transcript(transcript, logger))
+    }
+}
+
+/// AtIndex applies an assertion to the single response #`index` (0-based)
+/// 
+/// For example `AtIndex::new(6, StatsAssertion::new())` checks only the STATS reply.
+pub struct AtIndex {
+    index: usize,
+    assertion: Box<dyn Assertion>,
+}
+
+impl AtIndex {
+    pub fn new(index: usize, assertion: impl Assertion + 'static) -> Self {
+        Self {
+            index,
+            assertion: Box::new(assertion),
+        }
+    }
+
+    fn missing(&self, received: usize) -> TesterError {
+        TesterError::User(format!(
+            "Expected a response #{}, but only got {} response(s)",
+            self.index + 1,
+            received
+        ).into())
+    }
+}
+
+impl Assertion for AtIndex {
+    fn verify(&self, actual: &[Response], logger: &Logger) -> Result<(), TesterError> {
+        let response = actual.get(self.index).ok_or_else(|| self.missing(actual.len()))?;
+        self.assertion.verify(std::slice::from_ref(response), logger)
+    }
+
+    fn verify_transcript(&self, transcript: &Transcript, logger: &Logger) -> Result<(), TesterError> {
+        let response = transcript.responses.get(self.index).ok_or_else(|| self.missing(transcript.responses.len()))?;
+        let single = Transcript::new(
+            transcript.commands.get(self.index).cloned().into_iter().collect(),
+            vec![response.clone()],
+        );
+        self.assertion.verify_transcript(&single, logger)
+    }
+}
+
+/// Rule checked against the whole transcript; `Err` explains the violation
+pub type TranscriptRule = dyn Fn(&Transcript) -> Result<(), String>;
+
+/// PredicateAssertion checks a rule over the paired command/response transcript
+/// 
+/// Useful for properties that hold across a whole run rather than for one
+/// response, e.g. "a GET never returns a value that was not the last PUT".
+pub struct PredicateAssertion {
+    description: String,
+    predicate: Box<TranscriptRule>,
+}
+
+impl PredicateAssertion {
+    pub fn new(
+        description: impl Into<String>,
+        predicate: impl Fn(&Transcript) -> Result<(), String> + 'static,
+    ) -> Self {
+        Self {
+            description: description.into(),
+            predicate: Box::new(predicate),
+        }
+    }
+
+    /// Every GET of k returns NULL or the value of the last PUT of k
+    /// 
+    /// NULL is allowed only where the reference model (`CacheModel` with the
+    /// given eviction policy) says k may have been evicted or expired; a stale
+    /// or foreign value is never allowed. INIT forgets all keys; the values of
+    /// keys not PUT since the last CONCURRENT are not checked (they are unknown).
+    pub fn get_returns_last_put(policy: EvictionPolicy) -> Self {
+        Self::new("GET returns NULL or the last value PUT for the key", move |transcript| {
+            let expected = CacheModel::expectations(policy, &transcript.commands)
+                .map_err(|err| format!("the reference model cannot follow the commands: {}", err))?;
+            let mut values: HashMap<&str, &str> = HashMap::new();
+            let mut unknown_since_concurrent = false;
+
+            for (i, (command, response)) in transcript.pairs().enumerate() {
+                let parts: Vec<&str> = command.split_whitespace().collect();
+                match parts.as_slice() {
+                    ["INIT", ..] => {
+                        values.clear();
+                        unknown_since_concurrent = false;
+                    }
+                    ["CONCURRENT", ..] => {
+                        values.clear();
+                        unknown_since_concurrent = true;
+                    }
+                    ["PUT", key, value, ..] if *response == "OK" => {
+                        values.insert(key, value);
+                    }
+                    ["GET", key] => {
+                        let actual = response.text();
+                        if actual == "NULL" {
+                            match expected.get(i) {
+                                Some(line) if !line.matches("NULL") => {
+                                    return Err(format!(
+                                        "command #{} {} returned 'NULL', but '{}' cannot have been evicted or expired (expected {})",
+                                        i + 1, command, key, line
+                                    ));
+                                }
+                                _ => continue,
+                            }
+                        }
+                        match values.get(key) {
+                            Some(expected) if actual == *expected => {}
+                            Some(expected) => {
+                                return Err(format!(
+                                    "command #{} {} returned {}, but the last PUT of '{}' stored {}",
+                                    i + 1, command, quote(&actual), key, quote(expected)
+                                ));
+                            }
+                            None if unknown_since_concurrent => {}
+                            None => {
+                                return Err(format!(
+                                    "command #{} {} returned {}, but '{}' was never PUT",
+                                    i + 1, command, quote(&actual), key
+                                ));
+                            }
+                        }
+                    }
+                    _ => {}
                 }
-                
-                let hint = self.command_hints.as_ref()
-                    .and_then(|cmds| cmds.get(i))
-                    .map(|cmd| format!(" ({})", cmd))
-                    .unwrap_or_default();
-                
-                logger.errorf(&format!("𐄂 {}{}", actual_line, hint), &[]);
-                return Err(TesterError::User(
-                    format!(
-                        "Response #{} mismatch: expected '{}', got '{}'",
-                        i + 1,
-                        expected_line,
-                        actual_line
-                    ).into()
-                ));
-            } else {
-                let hint = self.command_hints.as_ref()
-                    .and_then(|cmds| cmds.get(i))
-                    .map(|cmd| format!(" ({})", cmd))
-                    .unwrap_or_default();
-                
-                success_logs.push(format!("✓ {}{}", actual_line, hint));
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Assertion for PredicateAssertion {
+    fn verify(&self, actual: &[Response], logger: &Logger) -> Result<(), TesterError> {
+        self.verify_transcript(&Transcript::new(Vec::new(), actual.to_vec()), logger)
+    }
+
+    fn verify_transcript(&self, transcript: &Transcript, logger: &Logger) -> Result<(), TesterError> {
+        match (self.predicate)(transcript) {
+            Ok(()) => {
+                logger.successf(&format!("✓ {}", self.description), &[]);
+                Ok(())
+            }
+            Err(violation) => {
+                logger.errorf(&format!("𐄂 {}", self.description), &[]);
+                Err(TesterError::User(format!("Rule violated: {}\n{}", self.description, violation).into()))
             }
         }
+    }
+}
+
+/// Line-by-line verification shared by ExactMatchAssertion and RegexAssertion
+fn verify_lines(
+    expected: &[ExpectedLine],
+    actual: &[Response],
+    command_hints: Option<&[String]>,
+    stderr_block: Option<&str>,
+    logger: &Logger,
+) -> Result<(), TesterError> {
+    let mut success_logs: Vec<String> = Vec::new();
+    let hint = |i: usize| {
+        command_hints
+            .and_then(|cmds| cmds.get(i))
+            .map(|cmd| format!(" ({})", cmd))
+            .unwrap_or_default()
+    };
 
-        // Check for extra responses
-        if actual.len() > self.expected.len() {
+    // Check each expected line
+    for (i, expected_line) in expected.iter().enumerate() {
+        if i >= actual.len() {
             // Log all successful lines before showing the error
             for log in &success_logs {
                 logger.successf(log, &[]);
             }
-            
-            logger.errorf(&format!("! {}", actual[self.expected.len()]), &[]);
-            return Err(TesterError::User(
+
+            logger.errorf(&format!("? {}{}", expected_line, hint(i)), &[]);
+            return Err(failure(
                 format!(
-                    "Expected {} response(s), but got {} (extra response: '{}')",
-                    self.expected.len(),
-                    actual.len(),
-                    actual[self.expected.len()]
-                ).into()
+                    "Expected response #{} to be {}, but didn't receive enough responses (got {} response(s))",
+                    i + 1,
+                    expected_line.describe(),
+                    actual.len()
+                ),
+                stderr_block,
+                logger,
             ));
         }
 
-        // All lines match - show summary instead of repeating all lines
-        logger.successf(&format!("✓ {} response(s) match", self.expected.len()), &[]);
+        let actual_line = actual[i].text();
+
+        if let Err(details) = expected_line.check(&actual_line) {
+            // Log all successful lines before showing the error
+            for log in &success_logs {
+                logger.successf(log, &[]);
+            }
+
+            logger.errorf(&format!("𐄂 {}{}", actual[i], hint(i)), &[]);
+            let mut message = format!(
+                "Response #{} mismatch: expected {}, got {}",
+                i + 1,
+                expected_line.describe(),
+                quote(&actual_line)
+            );
+            for detail in details {
+                message.push_str(&format!("\n  - {}", detail));
+            }
+            return Err(failure(message, stderr_block, logger));
+        }
+
+        success_logs.push(format!("✓ {}{}", actual[i], hint(i)));
+    }
+
+    // Check for extra responses
+    if actual.len() > expected.len() {
+        // Log all successful lines before showing the error
+        for log in &success_logs {
+            logger.successf(log, &[]);
+        }
 
-        Ok(())
+        logger.errorf(&format!("! {}", actual[expected.len()]), &[]);
+        return Err(failure(
+            format!(
+                "Expected {} response(s), but got {} (extra response: {})",
+                expected.len(),
+                actual.len(),
+                quote(&actual[expected.len()].text())
+            ),
+            stderr_block,
+            logger,
+        ));
+    }
+
+    // All lines match - show summary instead of repeating all lines
+    logger.successf(&format!("✓ {} response(s) match", expected.len()), &[]);
+
+    Ok(())
+}
+
+/// Rows shown before and after each mismatch in the transcript table
+const TABLE_CONTEXT: usize = 2;
+
+/// Longer table cells are cut to this many characters
+const TABLE_CELL_WIDTH: usize = 40;
+
+/// Like `verify_lines`, but checks every line and reports all mismatches
+/// in an aligned index / command / expected / actual table
+fn verify_all_lines(
+    expected: &[ExpectedLine],
+    actual: &[Response],
+    command_hints: Option<&[String]>,
+    stderr_block: Option<&str>,
+    logger: &Logger,
+) -> Result<(), TesterError> {
+    let rows = expected.len().max(actual.len());
+    let mut mismatches: Vec<(usize, Vec<String>)> = Vec::new();
+    let (mut missing, mut extra) = (0, 0);
+
+    for i in 0..rows {
+        match (expected.get(i), actual.get(i)) {
+            (Some(expected_line), Some(response)) => {
+                if let Err(details) = expected_line.check(&response.text()) {
+                    mismatches.push((i, details));
+                }
+            }
+            (Some(_), None) => {
+                missing += 1;
+                mismatches.push((i, Vec::new()));
+            }
+            _ => {
+                extra += 1;
+                mismatches.push((i, Vec::new()));
+            }
+        }
+    }
+
+    if mismatches.is_empty() {
+        logger.successf(&format!("✓ {} response(s) match", expected.len()), &[]);
+        return Ok(());
+    }
+
+    let command = |i: usize| command_hints.and_then(|cmds| cmds.get(i)).map_or("", |cmd| cmd.as_str());
+    for (i, _) in &mismatches {
+        let line = match actual.get(*i) {
+            Some(response) if *i < expected.len() => format!("𐄂 #{} {}", i + 1, response),
+            Some(response) => format!("! #{} {}", i + 1, response),
+            None => format!("? #{} {}", i + 1, expected[*i]),
+        };
+        let hint = command(*i);
+        if hint.is_empty() {
+            logger.errorf(&line, &[]);
+        } else {
+            logger.errorf(&format!("{} ({})", line, hint), &[]);
+        }
+    }
+
+    // Summary: "3 of 101 responses did not match (first at #12; 1 missing)"
+    let mut summary = format!(
+        "{} of {} responses did not match (first at #{}",
+        mismatches.len(),
+        rows,
+        mismatches[0].0 + 1
+    );
+    if missing > 0 {
+        summary.push_str(&format!("; {} missing", missing));
+    }
+    if extra > 0 {
+        summary.push_str(&format!("; {} extra", extra));
+    }
+    summary.push(')');
+
+    // Rows within TABLE_CONTEXT of a mismatch; None marks a gap
+    let mut shown: Vec<Option<usize>> = Vec::new();
+    let mut next = 0;
+    for (i, _) in &mismatches {
+        let start = i.saturating_sub(TABLE_CONTEXT).max(next);
+        let end = (i + TABLE_CONTEXT + 1).min(rows);
+        if start > next {
+            shown.push(None);
+        }
+        shown.extend((start..end).map(Some));
+        next = next.max(end);
+    }
+    if next < rows {
+        shown.push(None);
+    }
+
+    let cell = |text: String| {
+        let text = text.replace('\n', "\\n");
+        if text.chars().count() > TABLE_CELL_WIDTH {
+            let cut: String = text.chars().take(TABLE_CELL_WIDTH - 1).collect();
+            format!("{}…", cut)
+        } else {
+            text
+        }
+    };
+    let header = ["#".to_string(), "command".to_string(), "expected".to_string(), "actual".to_string()];
+    let table: Vec<Option<(bool, [String; 4])>> = shown
+        .iter()
+        .map(|row| {
+            row.map(|i| {
+                let expected_cell = expected.get(i).map_or("(none)".to_string(), |line| line.to_string());
+                let actual_cell = actual.get(i).map_or("(missing)".to_string(), |response| response.text());
+                let mismatch = mismatches.iter().any(|(index, _)| *index == i);
+                (mismatch, [(i + 1).to_string(), cell(command(i).to_string()), cell(expected_cell), cell(actual_cell)])
+            })
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|h| h.chars().count());
+    for (_, cells) in table.iter().flatten() {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let format_row = |marker: &str, cells: &[String; 4]| {
+        format!(
+            "{} {:>w0$}  {:<w1$}  {:<w2$}  {}",
+            marker,
+            cells[0],
+            cells[1],
+            cells[2],
+            cells[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2]
+        )
+        .trim_end()
+        .to_string()
+    };
+
+    let mut message = format!("{}:\n\n{}", summary, format_row(" ", &header));
+    for row in &table {
+        match row {
+            Some((mismatch, cells)) => {
+                message.push('\n');
+                message.push_str(&format_row(if *mismatch { ">" } else { " " }, cells));
+            }
+            None => message.push_str(&format!("\n  {:>w$}", "...", w = widths[0])),
+        }
+    }
+
+    for (i, details) in &mismatches {
+        for detail in details {
+            message.push_str(&format!("\n#{}: {}", i + 1, detail));
+        }
+    }
+
+    Err(failure(message, stderr_block, logger))
+}
+
+/// Build the failure error, logging and attaching stderr if present
+fn failure(message: String, stderr_block: Option<&str>, logger: &Logger) -> TesterError {
+    match stderr_block {
+        Some(block) => {
+            for line in block.lines() {
+                logger.infof(line, &[]);
+            }
+            TesterError::User(format!("{}\n\n{}", message, block).into())
+        }
+        None => TesterError::User(message.into()),
+    }
+}
+
+/// Quote a response for error messages; multi-line text is shown as a list
+fn quote(text: &str) -> String {
+    // Make an otherwise invisible "\r" show up in 'OK\r'
+    let text = &text.replace('\r', "\\r");
+    if text.contains('\n') {
+        format!("[{}]", text.split('\n').map(|line| format!("'{}'", line)).collect::<Vec<_>>().join(", "))
+    } else {
+        format!("'{}'", text)
     }
 }
 
@@ -131,10 +912,14 @@ mod tests {
         Logger::get_quiet_logger("test")
     }
 
+    fn responses(lines: &[&str]) -> Vec<Response> {
+        lines.iter().map(|line| Response::from(*line)).collect()
+    }
+
     #[test]
     fn test_exact_match_success() {
         let assertion = ExactMatchAssertion::new(vec!["OK".to_string(), "1".to_string()]);
-        let actual = vec!["OK".to_string(), "1".to_string()];
+        let actual = responses(&["OK", "1"]);
         let logger = create_test_logger();
 
         let result = assertion.verify(&actual, &logger);
@@ -144,7 +929,7 @@ mod tests {
     #[test]
     fn test_exact_match_mismatch() {
         let assertion = ExactMatchAssertion::new(vec!["OK".to_string(), "1".to_string()]);
-        let actual = vec!["OK".to_string(), "2".to_string()];
+        let actual = responses(&["OK", "2"]);
         let logger = create_test_logger();
 
         let result = assertion.verify(&actual, &logger);
@@ -155,7 +940,7 @@ mod tests {
     #[test]
     fn test_exact_match_missing_response() {
         let assertion = ExactMatchAssertion::new(vec!["OK".to_string(), "1".to_string()]);
-        let actual = vec!["OK".to_string()];
+        let actual = responses(&["OK"]);
         let logger = create_test_logger();
 
         let result = assertion.verify(&actual, &logger);
@@ -166,7 +951,7 @@ mod tests {
     #[test]
     fn test_exact_match_extra_response() {
         let assertion = ExactMatchAssertion::new(vec!["OK".to_string()]);
-        let actual = vec!["OK".to_string(), "EXTRA".to_string()];
+        let actual = responses(&["OK", "EXTRA"]);
         let logger = create_test_logger();
 
         let result = assertion.verify(&actual, &logger);
@@ -174,11 +959,273 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("extra response"));
     }
 
+    #[test]
+    fn test_exact_match_attaches_stderr() {
+        let assertion = ExactMatchAssertion::new(vec!["1".to_string()])
+            .with_stderr("--- stderr ---\ndebug: evicting a\n--- end of stderr ---".to_string());
+        let actual = responses(&["NULL"]);
+        let logger = create_test_logger();
+
+        let message = assertion.verify(&actual, &logger).unwrap_err().to_string();
+        assert!(message.contains("Response #1 mismatch"));
+        assert!(message.contains("debug: evicting a"));
+    }
+
+    #[test]
+    fn test_exact_match_multi_line_response() {
+        let assertion = ExactMatchAssertion::new(vec!["OK".to_string(), "c\nb\na".to_string()]);
+        let logger = create_test_logger();
+
+        let actual = vec![Response::from("OK"), Response::Lines(vec!["c".into(), "b".into(), "a".into()])];
+        assert!(assertion.verify(&actual, &logger).is_ok());
+
+        let actual = vec![Response::from("OK"), Response::Lines(vec!["c".into(), "a".into(), "b".into()])];
+        let message = assertion.verify(&actual, &logger).unwrap_err().to_string();
+        assert!(message.contains("expected ['c', 'b', 'a'], got ['c', 'a', 'b']"));
+    }
+
+    #[test]
+    fn test_regex_mixed_lines() {
+        let assertion = RegexAssertion::new(vec![
+            ExpectedLine::exact("OK"),
+            ExpectedLine::pattern(r"hits:\d+ misses:\d+").unwrap(),
+        ])
+        .with_commands(vec!["INIT 5".to_string(), "STATS".to_string()]);
+        let logger = create_test_logger();
+
+        assert!(assertion.verify(&responses(&["OK", "hits:3 misses:1"]), &logger).is_ok());
+
+        let message = assertion.verify(&responses(&["OK", "hits:3"]), &logger).unwrap_err().to_string();
+        assert!(message.contains(r"Response #2 mismatch: expected a match for /hits:\d+ misses:\d+/, got 'hits:3'"));
+    }
+
+    #[test]
+    fn test_regex_matches_whole_response() {
+        let assertion = RegexAssertion::from_patterns(&["OK", r"\d"]).unwrap();
+        let logger = create_test_logger();
+
+        assert!(assertion.verify(&responses(&["OK", "7"]), &logger).is_ok());
+        assert!(assertion.verify(&responses(&["NOT OK", "7"]), &logger).is_err());
+        assert!(assertion.verify(&responses(&["OK", "17"]), &logger).is_err());
+    }
+
+    #[test]
+    fn test_regex_invalid_pattern() {
+        let err = RegexAssertion::from_patterns(&["OK", "hits:(\\d+"]).err().unwrap();
+        assert!(matches!(err, TesterError::Configuration(_)));
+        assert!(err.to_string().contains("Invalid regex pattern"));
+    }
+
+    #[test]
+    fn test_range_one_of_and_approx() {
+        let assertion = RegexAssertion::new(vec![
+            ExpectedLine::range(0..=10),
+            ExpectedLine::one_of(&["1", "NULL"]),
+            ExpectedLine::approx(66.67, 0.01),
+        ]);
+        let logger = create_test_logger();
+
+        assert!(assertion.verify(&responses(&["0", "NULL", "66.67"]), &logger).is_ok());
+        assert!(assertion.verify(&responses(&["10", "1", "66.666"]), &logger).is_ok());
+
+        let message = assertion.verify(&responses(&["11", "1", "66.67"]), &logger).unwrap_err().to_string();
+        assert!(message.contains("Response #1 mismatch: expected an integer in 0..=10, got '11'"));
+
+        let message = assertion.verify(&responses(&["3", "2", "66.67"]), &logger).unwrap_err().to_string();
+        assert!(message.contains("expected one of '1', 'NULL', got '2'"));
+
+        let message = assertion.verify(&responses(&["3", "1", "66.5"]), &logger).unwrap_err().to_string();
+        assert!(message.contains("expected a number in 66.67±0.01, got '66.5'\n  - off by 0.17"));
+    }
+
+    #[test]
+    fn test_range_rejects_non_integer() {
+        let expected = ExpectedLine::range(0..=5);
+        assert_eq!(expected.check("five"), Err(vec!["the response is not an integer".to_string()]));
+        assert_eq!(expected.to_string(), "0..=5");
+    }
+
+    #[test]
+    fn test_stats_per_field_diff() {
+        let assertion = StatsAssertion::from_expected(
+            "hits:1 misses:1 hit_rate:50.00 evictions:1 expirations:0 size:2 capacity:2",
+        )
+        .unwrap();
+        let logger = create_test_logger();
+
+        let actual = responses(&["hits:1 misses:1 hit_rate:50.00 evictions:0 expirations:0 size:2 capacity:2"]);
+        let message = assertion.verify(&actual, &logger).unwrap_err().to_string();
+        assert!(message.contains("  - evictions: expected 1, got 0"));
+        assert!(!message.contains("hits: expected"));
+    }
+
+    #[test]
+    fn test_stats_invariants_with_unknown_values() {
+        let assertion = StatsAssertion::from_expected("hits:* misses:* evictions:>=1 capacity:20").unwrap();
+        let logger = create_test_logger();
+
+        let ok = responses(&["hits:7 misses:3 hit_rate:70.00 evictions:2 expirations:0 size:20 capacity:20"]);
+        assert!(assertion.verify(&ok, &logger).is_ok());
+
+        let corrupted = "hits:7 misses:3 hit_rate:75.00 evictions:0 expirations:0 size:21 capacity:20";
+        let problems = assertion.check(corrupted).unwrap_err();
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].contains("evictions: expected at least 1, got 0"));
+        assert!(problems[1].contains("hit_rate: 75.00 does not equal"));
+        assert!(problems[2].contains("size: 21 exceeds capacity 20"));
+
+        let reordered = "misses:3 hits:7 hit_rate:70.00 evictions:2 expirations:0 size:20 capacity:20";
+        assert!(assertion.check(reordered).unwrap_err()[0].starts_with("STATS format: field #1"));
+    }
+
+    #[test]
+    fn test_stats_invalid_expected_field() {
+        assert!(StatsAssertion::from_expected("hitz:1").is_err());
+        assert_eq!(
+            StatsAssertion::new().expect_field("size", 3).to_string(),
+            "hits:* misses:* hit_rate:* evictions:* expirations:* size:3 capacity:*"
+        );
+    }
+
+    #[test]
+    fn test_all_of_and_any_of() {
+        let logger = create_test_logger();
+        let actual = responses(&["OK", "1"]);
+        let exact = |lines: &[&str]| -> Box<dyn Assertion> {
+            Box::new(ExactMatchAssertion::new(lines.iter().map(|s| s.to_string()).collect()))
+        };
+
+        let all = AllOf(vec![exact(&["OK", "1"]), Box::new(AtIndex::new(1, RegexAssertion::from_patterns(&[r"\d"]).unwrap()))]);
+        assert!(all.verify(&actual, &logger).is_ok());
+
+        let all = AllOf(vec![exact(&["OK", "1"]), exact(&["OK", "2"])]);
+        assert!(all.verify(&actual, &logger).is_err());
+
+        let any = AnyOf(vec![exact(&["OK", "NULL"]), exact(&["OK", "1"])]);
+        assert!(any.verify(&actual, &logger).is_ok());
+
+        let any = AnyOf(vec![exact(&["OK", "NULL"]), exact(&["OK", "2"])]);
+        let message = any.verify(&actual, &logger).unwrap_err().to_string();
+        assert!(message.contains("None of the 2 acceptable outcomes matched"));
+        assert!(message.contains("\n1. ") && message.contains("\n2. "));
+
+        let missing = AtIndex::new(5, ExactMatchAssertion::new(vec!["OK".to_string()]));
+        assert!(missing.verify(&actual, &logger).unwrap_err().to_string().contains("only got 2 response(s)"));
+    }
+
+    #[test]
+    fn test_regex_assertion_override() {
+        let assertion = RegexAssertion::from_patterns(&["OK", "1"])
+            .unwrap()
+            .with_override(1, ExpectedLine::one_of(&["1", "NULL"]));
+        let logger = create_test_logger();
+
+        assert!(assertion.verify(&responses(&["OK", "NULL"]), &logger).is_ok());
+        assert!(assertion.verify(&responses(&["OK", "2"]), &logger).is_err());
+    }
+
+    #[test]
+    fn test_get_returns_last_put() {
+        let rule = PredicateAssertion::get_returns_last_put(EvictionPolicy::Lru);
+        let logger = create_test_logger();
+        let transcript = |commands: &[&str], lines: &[&str]| {
+            Transcript::new(commands.iter().map(|s| s.to_string()).collect(), responses(lines))
+        };
+
+        let evicted = transcript(
+            &["INIT 1", "PUT a 1", "PUT a 2", "PUT b 3", "GET a", "GET b"],
+            &["OK", "OK", "OK", "OK", "NULL", "3"],
+        );
+        assert!(rule.verify_transcript(&evicted, &logger).is_ok());
+
+        let stale = transcript(&["INIT 2", "PUT a 1", "PUT a 2", "GET a"], &["OK", "OK", "OK", "1"]);
+        let message = rule.verify_transcript(&stale, &logger).unwrap_err().to_string();
+        assert!(message.contains("command #4 GET a returned '1', but the last PUT of 'a' stored '2'"));
+
+        let never_put = transcript(&["INIT 2", "GET x"], &["OK", "7"]);
+        assert!(rule.verify_transcript(&never_put, &logger).is_err());
+
+        let concurrent = transcript(&["INIT 5", "CONCURRENT 10 MIXED", "GET key_3"], &["OK", "OK", "42"]);
+        assert!(rule.verify_transcript(&concurrent, &logger).is_ok());
+
+        // NULL 只在模型允许淘汰或过期时接受
+        let lost = transcript(&["INIT 2", "PUT a 1", "PUT b 2", "GET a"], &["OK", "OK", "OK", "NULL"]);
+        let message = rule.verify_transcript(&lost, &logger).unwrap_err().to_string();
+        assert!(message.contains("command #4 GET a returned 'NULL', but 'a' cannot have been evicted or expired"));
+
+        let maybe_evicted = transcript(&["INIT 2", "PUT a 1", "CONCURRENT 5 MIXED", "GET a"], &["OK", "OK", "OK", "NULL"]);
+        assert!(rule.verify_transcript(&maybe_evicted, &logger).is_ok());
+
+        let expired = transcript(&["INIT 2", "PUT a 1 1", "SLEEP 2", "GET a"], &["OK", "OK", "OK", "NULL"]);
+        assert!(rule.verify_transcript(&expired, &logger).is_ok());
+
+        let fifo = PredicateAssertion::get_returns_last_put(EvictionPolicy::Fifo);
+        let evicted_first = transcript(&["INIT 2", "PUT a 1", "PUT b 2", "GET a", "PUT c 3", "GET a"], &["OK", "OK", "OK", "1", "OK", "NULL"]);
+        assert!(fifo.verify_transcript(&evicted_first, &logger).is_ok());
+        assert!(rule.verify_transcript(&evicted_first, &logger).is_err());
+    }
+
+    #[test]
+    fn test_all_mismatches_table() {
+        let commands: Vec<String> = (0..12).map(|i| format!("GET k{}", i)).collect();
+        let expected: Vec<String> = (0..12).map(|i| i.to_string()).collect();
+        let mut lines: Vec<String> = expected.clone();
+        lines[3] = "4".to_string();
+        lines[10] = "NULL".to_string();
+        lines.truncate(11);
+        let actual = responses(&lines.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+        let logger = create_test_logger();
+
+        let assertion = ExactMatchAssertion::new(expected.clone())
+            .with_commands(commands.clone())
+            .with_all_mismatches();
+        let message = assertion.verify(&actual, &logger).unwrap_err().to_string();
+        let table: Vec<&str> = message.lines().collect();
+
+        assert!(table[0].starts_with("3 of 12 responses did not match (first at #4; 1 missing)"));
+        assert_eq!(table[2].split_whitespace().collect::<Vec<_>>(), ["#", "command", "expected", "actual"]);
+        assert_eq!(table[3].trim(), "...");
+        assert_eq!(table[4].split_whitespace().collect::<Vec<_>>(), ["2", "GET", "k1", "1", "1"]);
+        assert_eq!(table[6].split_whitespace().collect::<Vec<_>>(), [">", "4", "GET", "k3", "3", "4"]);
+        assert_eq!(table[9].trim(), "...");
+        assert_eq!(table[12].split_whitespace().collect::<Vec<_>>(), [">", "11", "GET", "k10", "10", "NULL"]);
+        assert_eq!(table[13].split_whitespace().collect::<Vec<_>>(), [">", "12", "GET", "k11", "11", "(missing)"]);
+        assert_eq!(table.len(), 14);
+
+        // Columns line up
+        let expected_column = table[2].find("expected").unwrap();
+        assert!(table[6][expected_column..].starts_with("3 "));
+        assert!(table[13][expected_column..].starts_with("11 "));
+
+        // Default mode still stops at the first mismatch
+        let message = ExactMatchAssertion::new(expected).verify(&actual, &logger).unwrap_err().to_string();
+        assert!(message.starts_with("Response #4 mismatch: expected '3', got '4'"));
+    }
+
+    #[test]
+    fn test_all_mismatches_details_and_success() {
+        let assertion = RegexAssertion::new(vec![
+            ExpectedLine::exact("OK"),
+            StatsAssertion::from_expected("hits:1 misses:0").map(ExpectedLine::Stats).unwrap(),
+        ])
+        .with_all_mismatches();
+        let logger = create_test_logger();
+
+        let ok = responses(&["OK", "hits:1 misses:0 hit_rate:100.00 evictions:0 expirations:0 size:1 capacity:2"]);
+        assert!(assertion.verify(&ok, &logger).is_ok());
+
+        let wrong = responses(&["OK", "hits:0 misses:1 hit_rate:0.00 evictions:0 expirations:0 size:1 capacity:2", "OK"]);
+        let message = assertion.verify(&wrong, &logger).unwrap_err().to_string();
+        assert!(message.starts_with("2 of 3 responses did not match (first at #2; 1 extra)"));
+        assert!(message.contains("(none)"));
+        assert!(message.contains("\n#2: hits: expected 1, got 0"));
+    }
+
     #[test]
     fn test_exact_match_with_commands() {
         let assertion = ExactMatchAssertion::new(vec!["OK".to_string()])
             .with_commands(vec!["INIT 5".to_string()]);
-        let actual = vec!["OK".to_string()];
+        let actual = responses(&["OK"]);
         let logger = create_test_logger();
 
         let result = assertion.verify(&actual, &logger);
@@ -1,21 +1,35 @@
 use std::collections::HashMap;
 use std::env;
 use std::process;
+use lru_cache_tester::definitions;
 use tester_utils::{register_tests, run_cli, TesterDefinition};
 
+/// 展开为 `register_tests!`，同时生成 `RUST_TEST_SLUGS`（数据驱动测试不能与其重名）
+macro_rules! register_rust_tests {
+    ($(stage $stage:literal, $title:literal => { $($slug:literal => $test:path),* $(,)? }),* $(,)?) => {
+        register_tests! {
+            $(stage $stage, $title => { $($slug => $test,)* },)*
+        }
+        
+        const RUST_TEST_SLUGS: &[&str] = &[$($($slug,)*)*];
+    };
+}
+
 // 声明式测试注册 - 所有测试在一处集中定义
-register_tests! {
+register_rust_tests! {
     stage 1, "Basic Cache Operations" => {
         "jq3" => lru_cache_tester::stage_1::test_basic_cache,
         "jq3-multiple-keys" => lru_cache_tester::stage_1::test_multiple_keys,
         "jq3-update" => lru_cache_tester::stage_1::test_key_update,
         "jq3-size" => lru_cache_tester::stage_1::test_size_command,
+        "jq3-random" => lru_cache_tester::stage_1::test_random_workload,
     },
     
     stage 2, "FIFO Eviction" => {
         "ze6" => lru_cache_tester::stage_2::test_fifo_eviction,
         "ze6-update" => lru_cache_tester::stage_2::test_fifo_update_no_reorder,
         "ze6-size" => lru_cache_tester::stage_2::test_fifo_size,
+        "ze6-random" => lru_cache_tester::stage_2::test_random_workload,
     },
     
     stage 3, "LRU Eviction" => {
@@ -23,6 +37,7 @@ register_tests! {
         "ch7-vs-fifo" => lru_cache_tester::stage_3::test_lru_vs_fifo,
         "ch7-multiple" => lru_cache_tester::stage_3::test_lru_multiple_access,
         "ch7-sequential" => lru_cache_tester::stage_3::test_lru_sequential_evictions,
+        "ch7-random" => lru_cache_tester::stage_3::test_random_workload,
     },
     
     stage 4, "Custom Doubly Linked List" => {
@@ -34,6 +49,7 @@ register_tests! {
         "vh5-empty-cache" => lru_cache_tester::stage_4::test_empty_cache,
         "vh5-repeated-ops" => lru_cache_tester::stage_4::test_repeated_operations,
         "vh5-eviction-cycle" => lru_cache_tester::stage_4::test_full_eviction_cycle,
+        "vh5-random" => lru_cache_tester::stage_4::test_random_workload,
     },
     
     stage 5, "Thread Safety" => {
@@ -46,6 +62,9 @@ register_tests! {
         "ba6-size-consistency" => lru_cache_tester::stage_5::test_size_consistency,
         "ba6-capacity-one" => lru_cache_tester::stage_5::test_concurrent_capacity_one,
         "ba6-after-concurrent" => lru_cache_tester::stage_5::test_operations_after_concurrent,
+        "ba6-clients" => lru_cache_tester::stage_5::test_multi_client_read_heavy,
+        "ba6-clients-eviction" => lru_cache_tester::stage_5::test_multi_client_eviction,
+        "ba6-random" => lru_cache_tester::stage_5::test_random_workload,
     },
     
     stage 6, "TTL Expiration" => {
@@ -58,6 +77,9 @@ register_tests! {
         "xy7-update" => lru_cache_tester::stage_6::test_ttl_update,
         "xy7-size" => lru_cache_tester::stage_6::test_ttl_size_consistency,
         "xy7-concurrent" => lru_cache_tester::stage_6::test_ttl_concurrent,
+        "xy7-random" => lru_cache_tester::stage_6::test_random_workload,
+        "xy7-sleep" => lru_cache_tester::stage_6::test_sleep_command,
+        "xy7-boundary-virtual" => lru_cache_tester::stage_6::test_ttl_expiry_boundary_virtual,
     },
     
     stage 7, "Cache Statistics (Extension)" => {
@@ -72,10 +94,32 @@ register_tests! {
         "st8-precision" => lru_cache_tester::stage_7::test_stats_hit_rate_precision,
         "st8-large" => lru_cache_tester::stage_7::test_stats_large_workload,
         "st8-concurrent" => lru_cache_tester::stage_7::test_stats_concurrent,
+        "st8-clients" => lru_cache_tester::stage_7::test_stats_multi_client,
+        "st8-random" => lru_cache_tester::stage_7::test_random_workload,
+    },
+    
+    stage 8, "RESP Protocol (Extension)" => {
+        "rp9" => lru_cache_tester::stage_8::test_resp_framing,
+        "rp9-binary" => lru_cache_tester::stage_8::test_resp_binary_values,
+        "rp9-errors" => lru_cache_tester::stage_8::test_resp_error_replies,
     },
 }
 
 fn main() {
+    // `--list-definitions [stage]`: 输出数据驱动测试的 SYSTEMQUEST_TEST_CASES_JSON（供 Makefile 使用）
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--list-definitions") {
+        let stage = args.get(2).and_then(|stage| stage.parse().ok());
+        match definitions::load_all() {
+            Ok(all) => println!("{}", definitions::test_cases_json(&all, stage)),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    
     // 获取环境变量
     let env_vars: HashMap<String, String> = env::vars().collect();
     
@@ -85,6 +129,12 @@ fn main() {
     // 使用生成的函数注册所有测试
     register_all_tests(&mut definition);
     
+    // 注册 definitions/*.toml 和 SYSTEMQUEST_DEFINITIONS_DIR 中的数据驱动测试
+    if let Err(err) = definitions::register_definitions(&mut definition, RUST_TEST_SLUGS) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+    
     // 运行 CLI
     let exit_code = run_cli(env_vars, definition);
     process::exit(exit_code);
@@ -0,0 +1,76 @@
+use std::env;
+use std::time::Duration;
+use tester_utils::TesterError;
+use crate::helpers::tester_delay;
+use crate::response::Response;
+
+/// 设置后（非空且不为 "0"），由测试器等待 SLEEP 的 TTL 测试改在虚拟时钟上运行
+pub const VIRTUAL_CLOCK_ENV: &str = "SYSTEMQUEST_VIRTUAL_CLOCK";
+
+/// 冻结时钟（虚拟时钟协议，可选扩展）
+///
+/// | 命令 | 响应 | 说明 |
+/// |------|------|------|
+/// | `CLOCK FREEZE` | `OK` | 停止读取真实时钟，虚拟时间从 0 开始（已冻结时无效果） |
+/// | `CLOCK ADVANCE <n>ms` | `OK` | 虚拟时间前进 n 毫秒 |
+/// | `CLOCK NOW` | `<n>` | 当前虚拟时间（毫秒） |
+///
+/// 冻结后，在虚拟时间 w 写入、TTL 为 t 秒的条目从虚拟时间 w + t*1000 起过期；
+/// SLEEP 仍然真实等待，但不推进虚拟时间。INIT 不会解冻或重置时钟。
+pub const FREEZE: &str = "CLOCK FREEZE";
+
+/// 查询虚拟时间（自 CLOCK FREEZE 以来的毫秒数）
+pub const NOW: &str = "CLOCK NOW";
+
+/// 虚拟时间前进 duration（按毫秒取整）
+///
+/// # 示例
+/// ```rust
+/// # use lru_cache_tester::clock;
+/// # use std::time::Duration;
+/// assert_eq!(clock::advance(Duration::from_millis(1500)), "CLOCK ADVANCE 1500ms");
+/// assert_eq!(clock::replace_sleep("SLEEP 1.5").as_deref(), Some("CLOCK ADVANCE 1500ms"));
+/// assert_eq!(clock::replace_sleep("GET a"), None);
+/// ```
+pub fn advance(duration: Duration) -> String {
+    format!("CLOCK ADVANCE {}ms", duration.as_millis())
+}
+
+/// 把 "SLEEP <seconds>" 换成等长的 CLOCK ADVANCE；其他命令返回 None
+pub fn replace_sleep(command: &str) -> Option<String> {
+    tester_delay(command).map(advance)
+}
+
+/// 是否通过 SYSTEMQUEST_VIRTUAL_CLOCK 选择了虚拟时钟
+pub fn enabled() -> bool {
+    env::var(VIRTUAL_CLOCK_ENV).is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+/// 检查程序对 CLOCK FREEZE 的响应是 OK
+///
+/// 虚拟时钟是可选扩展，但选择了它的测试在程序不支持时失败，而不是当作通过。
+///
+/// # 示例
+/// ```rust
+/// # use lru_cache_tester::clock;
+/// # use lru_cache_tester::response::Response;
+/// let commands = vec!["INIT 1".to_string(), clock::FREEZE.to_string()];
+/// assert!(clock::check_supported(&commands, &[Response::from("OK"), Response::from("OK")]).is_ok());
+/// assert!(clock::check_supported(&commands, &[Response::from("OK"), Response::from("ERROR")]).is_err());
+/// assert!(clock::check_supported(&commands, &[Response::from("OK")]).is_err());
+/// ```
+pub fn check_supported(commands: &[String], responses: &[Response]) -> Result<(), TesterError> {
+    let Some(i) = commands.iter().position(|command| command == FREEZE) else {
+        return Ok(());
+    };
+    match responses.get(i).map(Response::text) {
+        Some(response) if response.trim() == "OK" => Ok(()),
+        response => Err(TesterError::User(format!(
+            "This test runs on the optional virtual clock, but your program answered {} with '{}'. \
+            Implement the CLOCK commands, or leave {} unset to run the TTL tests in real time.",
+            FREEZE,
+            response.unwrap_or_else(|| "(no response)".to_string()),
+            VIRTUAL_CLOCK_ENV
+        ).into())),
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::BTreeSet;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+use tester_utils::{TestCaseHarness, TesterError};
+use crate::assertions::StatsAssertion;
+use crate::helpers::{output_block, CacheClient, ProgramSpec, ServerProcess, Timeouts};
+use crate::random::XorShift;
+
+/// 每隔多少次操作检查一次 SIZE
+const SIZE_CHECK_INTERVAL: usize = 50;
+
+/// 失败时最多列出的违规数量
+const MAX_REPORTED_VIOLATIONS: usize = 5;
+
+/// 并发负载的读写比例（与 CONCURRENT 命令的模式同名）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkloadMode {
+    /// 70% GET, 30% PUT
+    ReadHeavy,
+    /// 30% GET, 70% PUT
+    WriteHeavy,
+    /// 50% GET, 50% PUT
+    Mixed,
+}
+
+impl WorkloadMode {
+    fn read_percent(self) -> u64 {
+        match self {
+            Self::ReadHeavy => 70,
+            Self::WriteHeavy => 30,
+            Self::Mixed => 50,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::ReadHeavy => "READ_HEAVY",
+            Self::WriteHeavy => "WRITE_HEAVY",
+            Self::Mixed => "MIXED",
+        }
+    }
+}
+
+/// ConcurrentWorkload - 由测试器驱动的多客户端负载
+///
+/// 测试器自己打开 `clients` 个连接，每个连接在独立线程中交错发送
+/// `ops_per_client` 条 PUT/GET（键取自 key_0 .. key_{keyspace-1}），
+/// 而不是依赖程序自己实现的 CONCURRENT 命令。
+///
+/// 写入的值形如 `key_3:c2:17`（键、客户端、序号），这样每个 GET 的结果
+/// 都可以验证是否属于被查询的键。
+#[derive(Debug, Clone)]
+pub struct ConcurrentWorkload {
+    pub clients: usize,
+    pub ops_per_client: usize,
+    pub keyspace: usize,
+    pub mode: WorkloadMode,
+    pub seed: u64,
+}
+
+impl ConcurrentWorkload {
+    pub fn new(clients: usize, ops_per_client: usize, mode: WorkloadMode) -> Self {
+        Self {
+            clients,
+            ops_per_client,
+            keyspace: 100,
+            mode,
+            seed: 0x5eed,
+        }
+    }
+
+    /// 设置键空间大小（默认 100）
+    pub fn with_keyspace(mut self, keyspace: usize) -> Self {
+        self.keyspace = keyspace;
+        self
+    }
+
+    /// 设置随机种子（默认固定，保证可复现）
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    fn key(index: usize) -> String {
+        format!("key_{}", index)
+    }
+
+    /// 在 server 上运行负载
+    ///
+    /// 所有客户端先建立连接，再通过 Barrier 同时开始，保证请求真正交错。
+    /// 返回所有客户端的统计，或者观察到的违规描述（最多若干条）。
+    pub fn run(&self, server: &ServerProcess, capacity: usize, timeout: Duration) -> Result<WorkloadReport, Vec<String>> {
+        let mut clients = Vec::new();
+        for id in 0..self.clients {
+            match server.client(timeout) {
+                Ok(client) => clients.push((id, client)),
+                Err(e) => return Err(vec![format!("client #{}: {}", id, e)]),
+            }
+        }
+
+        let barrier = Arc::new(Barrier::new(self.clients));
+        let started_at = Instant::now();
+        let handles: Vec<_> = clients
+            .into_iter()
+            .map(|(id, client)| {
+                let workload = self.clone();
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    workload.run_client(id, client, capacity)
+                })
+            })
+            .collect();
+
+        let mut report = WorkloadReport::default();
+        let mut violations = Vec::new();
+        for handle in handles {
+            match handle.join() {
+                Ok(Ok(client_report)) => report.merge(client_report),
+                Ok(Err(violation)) => violations.push(violation),
+                Err(_) => violations.push("client thread panicked".to_string()),
+            }
+        }
+        report.elapsed = started_at.elapsed();
+
+        if violations.is_empty() {
+            Ok(report)
+        } else {
+            violations.truncate(MAX_REPORTED_VIOLATIONS);
+            Err(violations)
+        }
+    }
+
+    /// 单个客户端的操作序列；遇到第一个违规即停止
+    fn run_client(&self, id: usize, mut client: CacheClient, capacity: usize) -> Result<WorkloadReport, String> {
+        let mut rng = XorShift::new(self.seed ^ ((id as u64 + 1) << 32));
+        let mut report = WorkloadReport::default();
+
+        for seq in 0..self.ops_per_client {
+            let key = Self::key(rng.below(self.keyspace as u64) as usize);
+            let is_read = rng.below(100) < self.mode.read_percent();
+
+            let command = match is_read {
+                true => format!("GET {}", key),
+                false => format!("PUT {} {}:c{}:{}", key, key, id, seq),
+            };
+            let response = send(&mut client, id, seq, &command)?;
+
+            if is_read {
+                if response == "NULL" {
+                    report.misses += 1;
+                } else if value_belongs_to(&response, &key) {
+                    report.hits += 1;
+                } else {
+                    return Err(violation(id, seq, &command, &response, "expected NULL or a value written to this key"));
+                }
+            } else if response == "OK" {
+                report.puts += 1;
+                report.keys_written.insert(key);
+            } else {
+                return Err(violation(id, seq, &command, &response, "expected OK"));
+            }
+
+            if (seq + 1) % SIZE_CHECK_INTERVAL == 0 {
+                let response = send(&mut client, id, seq, "SIZE")?;
+                match response.parse::<usize>() {
+                    Ok(size) if size <= capacity => {}
+                    Ok(_) => {
+                        return Err(violation(id, seq, "SIZE", &response, &format!("exceeds capacity {}", capacity)));
+                    }
+                    Err(_) => return Err(violation(id, seq, "SIZE", &response, "expected a number")),
+                }
+            }
+
+            report.operations += 1;
+        }
+
+        Ok(report)
+    }
+}
+
+/// 多客户端负载的统计（由测试器观察，而不是程序自报）
+#[derive(Debug, Clone, Default)]
+pub struct WorkloadReport {
+    pub operations: usize,
+    pub puts: usize,
+    pub hits: usize,
+    pub misses: usize,
+    pub keys_written: BTreeSet<String>,
+    pub elapsed: Duration,
+}
+
+impl WorkloadReport {
+    fn merge(&mut self, other: WorkloadReport) {
+        self.operations += other.operations;
+        self.puts += other.puts;
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.keys_written.extend(other.keys_written);
+    }
+
+    /// 负载结束后缓存中应有的条目数
+    ///
+    /// 条目只会因容量淘汰而减少，所以写入过的不同键数达到容量后 SIZE 必须等于容量。
+    pub fn expected_size(&self, capacity: usize) -> usize {
+        self.keys_written.len().min(capacity)
+    }
+}
+
+/// MultiClientTestCase - 服务器模式下的多客户端并发测试
+///
+/// 流程:
+/// 1. 启动服务器，用控制连接发送 `INIT <capacity>`
+/// 2. 运行 ConcurrentWorkload（每个响应都在客户端线程中即时验证）
+/// 3. 可选: 检查 STATS 与测试器观察到的命中/未命中一致
+/// 4. 检查 SIZE，并逐个 GET 所有键，命中数必须等于 SIZE
+///
+/// stdin 传输方式下无法建立多个连接，返回配置错误（不会当作通过）。
+/// 这些测试不在默认的 _ALL 列表中，需要用服务器传输方式单独运行。
+pub struct MultiClientTestCase {
+    pub description: String,
+    pub workload: ConcurrentWorkload,
+    pub capacity: usize,
+    pub check_stats: bool,
+    pub hint: Option<&'static str>,
+    pub timeouts: Timeouts,
+}
+
+impl MultiClientTestCase {
+    pub fn new(description: impl Into<String>, capacity: usize, workload: ConcurrentWorkload) -> Self {
+        Self {
+            description: description.into(),
+            workload,
+            capacity,
+            check_stats: false,
+            hint: None,
+            timeouts: Timeouts::default(),
+        }
+    }
+
+    /// 添加提示信息（用于失败时的教学性提示）
+    pub fn with_hint(mut self, hint: &'static str) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    /// 负载结束后检查 STATS 计数器（Stage 7）
+    pub fn with_stats_check(mut self) -> Self {
+        self.check_stats = true;
+        self
+    }
+
+    /// 设置超时（per_command 作为每个客户端请求的超时）
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn run(&self, harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+        harness.logger.infof(&self.description, &[]);
+
+        let spec = ProgramSpec::from_env()?;
+        if !spec.transport.is_server() {
+            return Err(TesterError::Configuration(
+                "Multi-client tests need a server transport: set SYSTEMQUEST_TRANSPORT to tcp, resp or http"
+                    .to_string(),
+            ));
+        }
+
+        let (server, stream) = ServerProcess::start(&spec, &self.timeouts)?;
+        drop(stream);
+        let timeout = self.timeouts.per_command.unwrap_or(Duration::from_secs(10));
+        let mut control = server.client(timeout)?;
+
+        self.expect(&server, &mut control, &format!("INIT {}", self.capacity), "OK")?;
+
+        let workload = &self.workload;
+        harness.logger.infof(
+            &format!(
+                "Starting {} clients × {} operations ({}, {} keys)",
+                workload.clients,
+                workload.ops_per_client,
+                workload.mode.name(),
+                workload.keyspace
+            ),
+            &[],
+        );
+        let report = workload.run(&server, self.capacity, timeout).map_err(|violations| {
+            for violation in &violations {
+                harness.logger.errorf(&format!("𐄂 {}", violation), &[]);
+            }
+            self.failure(&server, &format!("Concurrent clients observed invalid responses:\n{}", violations.join("\n")))
+        })?;
+        harness.logger.successf(
+            &format!(
+                "✓ {} operations completed in {} ms ({} hits, {} misses)",
+                report.operations,
+                report.elapsed.as_millis(),
+                report.hits,
+                report.misses
+            ),
+            &[],
+        );
+
+        let expected_size = report.expected_size(self.capacity);
+
+        if self.check_stats {
+            let stats = self.send(&server, &mut control, "STATS")?;
+            self.check_stats_line(&server, &stats, &report, expected_size)?;
+            harness.logger.successf(&format!("✓ STATS consistent with observed traffic: {}", stats), &[]);
+        }
+
+        self.expect(&server, &mut control, "SIZE", &expected_size.to_string())?;
+        harness.logger.successf(
+            &format!(
+                "✓ SIZE {} ({} distinct keys written, capacity {})",
+                expected_size,
+                report.keys_written.len(),
+                self.capacity
+            ),
+            &[],
+        );
+
+        let mut present = 0;
+        for index in 0..workload.keyspace {
+            let key = ConcurrentWorkload::key(index);
+            let response = self.send(&server, &mut control, &format!("GET {}", key))?;
+            if response == "NULL" {
+                continue;
+            }
+            if !value_belongs_to(&response, &key) {
+                return Err(self.failure(&server, &format!("GET {} returned {:?}, which was never written to this key", key, response)));
+            }
+            present += 1;
+        }
+        if present != expected_size {
+            return Err(self.failure(
+                &server,
+                &format!("SIZE reported {} entries, but GET found {} of the keys", expected_size, present),
+            ));
+        }
+        harness.logger.successf(&format!("✓ {} entries readable after concurrent traffic", present), &[]);
+
+        harness.logger.successf(&format!("✓ {}", self.description), &[]);
+        Ok(())
+    }
+
+    /// 检查 STATS: 命中/未命中与测试器观察一致，size/capacity 正确，淘汰数不少于下限，
+    /// 并且各字段之间一致（见 StatsAssertion）
+    fn check_stats_line(&self, server: &ServerProcess, stats: &str, report: &WorkloadReport, expected_size: usize) -> Result<(), TesterError> {
+        let min_evictions = report.keys_written.len().saturating_sub(self.capacity);
+        let assertion = StatsAssertion::new()
+            .expect_field("hits", report.hits)
+            .expect_field("misses", report.misses)
+            .expect_at_least("evictions", min_evictions as u64)
+            .expect_field("size", expected_size)
+            .expect_field("capacity", self.capacity);
+
+        assertion.check(stats).map(|_| ()).map_err(|problems| {
+            self.failure(
+                server,
+                &format!(
+                    "STATS does not match what the tester's clients observed:\n  - {}\nSTATS: {}",
+                    problems.join("\n  - "),
+                    stats
+                ),
+            )
+        })
+    }
+
+    fn send(&self, server: &ServerProcess, client: &mut CacheClient, command: &str) -> Result<String, TesterError> {
+        client
+            .send_command(command)
+            .map_err(|e| self.failure(server, &format!("{} failed: {}", command, e)))
+    }
+
+    fn expect(&self, server: &ServerProcess, client: &mut CacheClient, command: &str, expected: &str) -> Result<(), TesterError> {
+        let response = self.send(server, client, command)?;
+        if response != expected {
+            return Err(self.failure(server, &format!("{}\nExpected: {}\nActual:   {}", command, expected, response)));
+        }
+        Ok(())
+    }
+
+    /// 构造失败信息，附带提示和服务器日志
+    fn failure(&self, server: &ServerProcess, message: &str) -> TesterError {
+        let hint = self.hint.map(|hint| format!("\n\nHint: {}", hint)).unwrap_or_default();
+        TesterError::User(format!("{}{}\n\n{}", message, hint, output_block("stderr", &server.stderr())).into())
+    }
+}
+
+fn send(client: &mut CacheClient, id: usize, seq: usize, command: &str) -> Result<String, String> {
+    client
+        .send_command(command)
+        .map_err(|e| format!("client #{}, operation #{}: {} failed: {}", id, seq + 1, command, e))
+}
+
+fn violation(id: usize, seq: usize, command: &str, response: &str, reason: &str) -> String {
+    format!("client #{}, operation #{}: {} → {:?} ({})", id, seq + 1, command, response, reason)
+}
+
+/// 值是否是为该键写入的（形如 `key_3:c2:17`）
+fn value_belongs_to(value: &str, key: &str) -> bool {
+    let Some(rest) = value.strip_prefix(key).and_then(|rest| rest.strip_prefix(":c")) else {
+        return false;
+    };
+    match rest.split_once(':') {
+        Some((client, seq)) => client.parse::<usize>().is_ok() && seq.parse::<usize>().is_ok(),
+        None => false,
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试值与键的归属校验
+    #[test]
+    fn test_value_belongs_to() {
+        assert!(value_belongs_to("key_3:c2:17", "key_3"));
+        assert!(!value_belongs_to("key_31:c2:17", "key_3"));
+        assert!(!value_belongs_to("key_3:c2", "key_3"));
+        assert!(!value_belongs_to("garbage", "key_3"));
+    }
+
+    /// 测试期望的最终条目数
+    #[test]
+    fn test_expected_size() {
+        let mut report = WorkloadReport::default();
+        report.keys_written.extend(["a", "b", "c"].map(String::from));
+        assert_eq!(report.expected_size(10), 3);
+        assert_eq!(report.expected_size(2), 2);
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use serde_json::json;
+use tester_utils::{TestCase, TestCaseHarness, TesterDefinition, TesterError};
+use crate::assertions::ExpectedLine;
+use crate::normalize::Normalization;
+use crate::test_case::{CacheTestCase, RunMode};
+
+/// 额外加载测试定义的目录（可选，与内置定义一起注册）
+pub const DEFINITIONS_DIR_ENV: &str = "SYSTEMQUEST_DEFINITIONS_DIR";
+
+// build.rs 生成: definitions/*.toml 的 (文件名, 内容) 列表
+include!(concat!(env!("OUT_DIR"), "/embedded_definitions.rs"));
+
+/// TestDefinition - 声明式测试定义
+///
+/// 课程作者不需要写 Rust 代码就能添加测试: 在 `definitions/` 下的 TOML 文件中
+/// 添加一个 `[[test]]` 表（构建时嵌入），或放在 `SYSTEMQUEST_DEFINITIONS_DIR`
+/// 指向的目录中（运行时读取）。每个定义对应一个 `CacheTestCase`。
+///
+/// ```toml
+/// [[test]]
+/// slug = "jq3-overwrite"
+/// stage = 1
+/// description = "Testing repeated overwrites of one key"
+/// hint = "PUT on an existing key replaces its value"
+/// commands = ["INIT 5", "PUT a 1", "PUT a 2", "GET a"]
+/// expect = ["OK", "OK", "OK", "2"]
+/// match = "exact"            # 可选: exact（默认）/ regex（所有行按正则匹配）
+/// regex_lines = []           # 可选: 按正则匹配的行（从 0 开始）
+/// mode = "batch"             # 可选: batch（默认）/ interactive
+/// normalization = "default"  # 可选: default / strict / lenient
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestDefinition {
+    pub slug: String,
+    pub stage: u32,
+    pub description: String,
+
+    #[serde(default)]
+    pub hint: Option<String>,
+
+    pub commands: Vec<String>,
+    pub expect: Vec<String>,
+
+    #[serde(default, rename = "match")]
+    pub matching: Matching,
+
+    #[serde(default)]
+    pub regex_lines: Vec<usize>,
+
+    #[serde(default)]
+    pub mode: Mode,
+
+    #[serde(default)]
+    pub normalization: NormalizationPolicy,
+}
+
+/// 期望响应的匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Matching {
+    #[default]
+    Exact,
+    Regex,
+}
+
+/// 命令执行模式（对应 `RunMode`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    #[default]
+    Batch,
+    Interactive,
+}
+
+/// 规范化策略（对应 `Normalization` 的预设）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NormalizationPolicy {
+    #[default]
+    Default,
+    Strict,
+    Lenient,
+}
+
+/// 一个定义文件: 若干 `[[test]]` 表
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DefinitionFile {
+    #[serde(default)]
+    test: Vec<TestDefinition>,
+}
+
+impl TestDefinition {
+    /// 检查定义本身是否一致（命令与期望数量、正则是否合法等）
+    pub fn validate(&self) -> Result<(), String> {
+        if self.slug.trim().is_empty() {
+            return Err("slug must not be empty".to_string());
+        }
+        if self.stage == 0 {
+            return Err("stage numbers start at 1".to_string());
+        }
+        if self.commands.is_empty() {
+            return Err("at least one command is required".to_string());
+        }
+        if self.commands.len() != self.expect.len() {
+            return Err(format!(
+                "{} command(s) but {} expected response(s)",
+                self.commands.len(),
+                self.expect.len()
+            ));
+        }
+        if let Some(index) = self.regex_lines.iter().find(|i| **i >= self.expect.len()) {
+            return Err(format!(
+                "regex_lines contains {} but there are only {} expected response(s)",
+                index,
+                self.expect.len()
+            ));
+        }
+
+        for (i, expected) in self.expect.iter().enumerate() {
+            if self.matching == Matching::Regex || self.regex_lines.contains(&i) {
+                ExpectedLine::pattern(expected).map_err(|err| err.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 转换为可运行的测试用例
+    pub fn to_test_case(&self) -> CacheTestCase {
+        let mut test_case = CacheTestCase::new(self.description.clone(), self.commands.clone(), self.expect.clone())
+            .with_regex_match(self.matching == Matching::Regex)
+            .with_regex_lines(&self.regex_lines)
+            .with_run_mode(match self.mode {
+                Mode::Batch => RunMode::Batch,
+                Mode::Interactive => RunMode::Interactive,
+            })
+            .with_normalization(match self.normalization {
+                NormalizationPolicy::Default => Normalization::default(),
+                NormalizationPolicy::Strict => Normalization::strict(),
+                NormalizationPolicy::Lenient => Normalization::lenient(),
+            });
+        if let Some(hint) = &self.hint {
+            test_case = test_case.with_hint(hint.clone());
+        }
+        test_case
+    }
+}
+
+/// 解析一个 TOML 定义文件；`name` 只用于错误信息
+pub fn parse(name: &str, source: &str) -> Result<Vec<TestDefinition>, TesterError> {
+    let file: DefinitionFile = toml::from_str(source)
+        .map_err(|e| TesterError::Configuration(format!("{}: {}", name, e)))?;
+
+    for definition in &file.test {
+        definition.validate().map_err(|e| {
+            TesterError::Configuration(format!("{}: test '{}': {}", name, definition.slug, e))
+        })?;
+    }
+    Ok(file.test)
+}
+
+/// 构建时嵌入的定义（definitions/*.toml）
+pub fn embedded() -> Result<Vec<TestDefinition>, TesterError> {
+    let mut definitions = Vec::new();
+    for (name, source) in EMBEDDED_DEFINITIONS {
+        definitions.extend(parse(name, source)?);
+    }
+    Ok(definitions)
+}
+
+/// 读取目录中的所有 *.toml 定义文件（按文件名排序）
+pub fn load_dir(dir: &Path) -> Result<Vec<TestDefinition>, TesterError> {
+    let entries = fs::read_dir(dir).map_err(|e| {
+        TesterError::Configuration(format!("Cannot read {} ({}): {}", DEFINITIONS_DIR_ENV, dir.display(), e))
+    })?;
+
+    let mut files: Vec<_> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    files.sort();
+
+    let mut definitions = Vec::new();
+    for path in files {
+        let source = fs::read_to_string(&path)
+            .map_err(|e| TesterError::Configuration(format!("{}: {}", path.display(), e)))?;
+        definitions.extend(parse(&path.display().to_string(), &source)?);
+    }
+    Ok(definitions)
+}
+
+/// 内置定义加上 SYSTEMQUEST_DEFINITIONS_DIR 中的定义；slug 不能重复
+pub fn load_all() -> Result<Vec<TestDefinition>, TesterError> {
+    let mut definitions = embedded()?;
+    if let Ok(dir) = env::var(DEFINITIONS_DIR_ENV) {
+        definitions.extend(load_dir(Path::new(&dir))?);
+    }
+
+    let mut slugs = HashSet::new();
+    if let Some(duplicate) = definitions.iter().find(|d| !slugs.insert(d.slug.as_str())) {
+        return Err(TesterError::Configuration(format!(
+            "Test slug '{}' is defined more than once",
+            duplicate.slug
+        )));
+    }
+    Ok(definitions)
+}
+
+/// 把所有定义注册为测试用例（与 `register_tests!` 中的 Rust 测试并列）
+///
+/// `rust_slugs` 是已注册的 Rust 测试的 slug: 与它们重名的定义会报错，而不是悄悄覆盖。
+pub fn register_definitions(definition: &mut TesterDefinition, rust_slugs: &[&str]) -> Result<(), TesterError> {
+    register(definition, load_all()?, rust_slugs)
+}
+
+fn register(definition: &mut TesterDefinition, tests: Vec<TestDefinition>, rust_slugs: &[&str]) -> Result<(), TesterError> {
+    let registered: HashSet<&str> = rust_slugs.iter().copied().collect();
+    if let Some(duplicate) = tests.iter().find(|test| registered.contains(test.slug.as_str())) {
+        return Err(TesterError::Configuration(format!(
+            "Test slug '{}' is already registered by a Rust test",
+            duplicate.slug
+        )));
+    }
+
+    // 与 register_tests! 展开后的注册方式相同，只是测试函数换成了闭包
+    for test in tests {
+        let slug = test.slug.clone();
+        definition.add_test_case(TestCase::new(slug, move |harness: &mut TestCaseHarness| {
+            test.to_test_case().run(harness)
+        }));
+    }
+    Ok(())
+}
+
+/// 生成 SYSTEMQUEST_TEST_CASES_JSON，运行所有（或某个 Stage 的）定义
+///
+/// 日志前缀为 `stage-<n>.<slug>`，标题为 `Stage #<n>: <description>`。
+pub fn test_cases_json(definitions: &[TestDefinition], stage: Option<u32>) -> String {
+    let cases: Vec<serde_json::Value> = definitions
+        .iter()
+        .filter(|d| stage.is_none_or(|stage| d.stage == stage))
+        .map(|d| {
+            json!({
+                "slug": d.slug,
+                "tester_log_prefix": format!("stage-{}.{}", d.stage, d.slug),
+                "title": format!("Stage #{}: {}", d.stage, d.description),
+            })
+        })
+        .collect();
+    serde_json::Value::Array(cases).to_string()
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::EvictionPolicy;
+
+    const SAMPLE: &str = r#"
+[[test]]
+slug = "sample"
+stage = 1
+description = "Sample"
+commands = ["INIT 5", "SIZE"]
+expect = ["OK", "\\d+"]
+regex_lines = [1]
+normalization = "strict"
+"#;
+
+    /// 测试解析定义文件并转换为测试用例
+    #[test]
+    fn test_parse_definition() {
+        let definitions = parse("sample.toml", SAMPLE).unwrap();
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0].matching, Matching::Exact);
+        assert_eq!(definitions[0].mode, Mode::Batch);
+
+        let test_case = definitions[0].to_test_case();
+        assert_eq!(test_case.commands, vec!["INIT 5", "SIZE"]);
+        assert_eq!(test_case.regex_lines, vec![1]);
+        assert_eq!(test_case.normalization, Normalization::strict());
+        assert_eq!(test_case.hint, None);
+    }
+
+    /// 测试定义错误时报告文件名和 slug
+    #[test]
+    fn test_invalid_definitions() {
+        let mismatch = SAMPLE.replace(r#"expect = ["OK", "\\d+"]"#, r#"expect = ["OK"]"#);
+        let err = parse("sample.toml", &mismatch).unwrap_err().to_string();
+        assert!(err.contains("sample.toml: test 'sample': 2 command(s) but 1 expected response(s)"));
+
+        let bad_regex = SAMPLE.replace(r#""\\d+""#, r#""(\\d""#);
+        assert!(parse("sample.toml", &bad_regex).unwrap_err().to_string().contains("Invalid regex pattern"));
+
+        let unknown_field = SAMPLE.replace("stage = 1", "stage = 1\nexpected = []");
+        assert!(parse("sample.toml", &unknown_field).is_err());
+    }
+
+    /// 测试内置定义都能解析，且 slug 不与其他定义重复
+    #[test]
+    fn test_embedded_definitions() {
+        let definitions = embedded().unwrap();
+        assert!(!definitions.is_empty());
+
+        let json: serde_json::Value = serde_json::from_str(&test_cases_json(&definitions, Some(1))).unwrap();
+        let cases = json.as_array().unwrap();
+        assert!(cases.iter().all(|case| case["tester_log_prefix"].as_str().unwrap().starts_with("stage-1.")));
+    }
+
+    /// 测试定义不能与已注册的 Rust 测试重名
+    #[test]
+    fn test_register_rejects_registered_slug() {
+        let mut definition = TesterDefinition::new("your_program.sh".to_string());
+
+        let err = register(&mut definition, parse("sample.toml", SAMPLE).unwrap(), &["jq3", "sample"]).unwrap_err();
+        assert!(err.to_string().contains("'sample' is already registered"));
+
+        let renamed = SAMPLE.replace(r#"slug = "sample""#, r#"slug = "sample-2""#);
+        assert!(register(&mut definition, parse("sample.toml", &renamed).unwrap(), &["jq3", "sample"]).is_ok());
+    }
+
+    /// 测试内置定义的期望与参考模型一致
+    #[test]
+    fn test_embedded_definitions_match_model() {
+        for definition in embedded().unwrap() {
+            let policy = EvictionPolicy::for_stage(definition.stage);
+            let conflicts = definition.to_test_case().model_conflicts(policy).unwrap();
+            assert!(conflicts.is_empty(), "{}:\n{}", definition.slug, conflicts.join("\n"));
+        }
+    }
+}
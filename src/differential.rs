@@ -0,0 +1,171 @@
+use std::env;
+use std::path::Path;
+use tester_utils::TesterError;
+use crate::assertions::ExpectedLine;
+use crate::helpers::{CommandRunner, ProgramSpec, Timeouts};
+use crate::normalize::Normalization;
+use crate::response::FramingRules;
+use crate::transport::Transport;
+
+/// 已知正确的参考实现所在目录（可选，包含 `your_program.sh`）
+///
+/// 设置后，每个 `CacheTestCase` 先在参考实现上运行同一组命令:
+/// 手写的期望必须接受参考实现的输出，`CacheTestCase::from_reference` 的测试
+/// 直接以参考实现的输出作为期望。
+pub const REFERENCE_DIR_ENV: &str = "SYSTEMQUEST_REFERENCE_DIR";
+
+/// Reference - 参考实现
+///
+/// 与学生程序使用相同的传输方式（`SYSTEMQUEST_TRANSPORT`）和帧格式，
+/// 按批量模式运行（见 `CommandRunner`）。
+///
+/// # 示例
+/// ```rust,no_run
+/// # use lru_cache_tester::differential::Reference;
+/// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// if let Some(reference) = Reference::from_env()? {
+///     let responses = reference.responses(&["INIT 2".to_string(), "GET a".to_string()])?;
+///     assert_eq!(responses, vec!["OK", "NULL"]);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Reference {
+    program: ProgramSpec,
+    timeouts: Timeouts,
+    normalization: Normalization,
+}
+
+impl Reference {
+    pub fn new(program: ProgramSpec) -> Self {
+        Self {
+            program,
+            timeouts: Timeouts::default(),
+            normalization: Normalization::default(),
+        }
+    }
+
+    /// 根据 SYSTEMQUEST_REFERENCE_DIR 定位参考实现；未设置时返回 None
+    pub fn from_env() -> Result<Option<Self>, TesterError> {
+        match env::var(REFERENCE_DIR_ENV) {
+            Ok(dir) => Self::from_dir(&dir).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// 使用目录中的 `your_program.sh`（传输方式来自 SYSTEMQUEST_TRANSPORT）
+    pub fn from_dir(dir: &str) -> Result<Self, TesterError> {
+        let path = Path::new(dir).join("your_program.sh");
+        if !path.is_file() {
+            return Err(TesterError::Configuration(format!(
+                "{} is set to '{}', but {} does not exist",
+                REFERENCE_DIR_ENV,
+                dir,
+                path.display()
+            )));
+        }
+        Ok(Self::new(ProgramSpec::new(path).with_transport(Transport::from_env()?)))
+    }
+
+    /// 设置多行响应的帧格式（与测试用例一致）
+    pub fn with_framing(mut self, framing: FramingRules) -> Self {
+        self.program = self.program.with_framing(framing);
+        self
+    }
+
+    /// 设置超时（与测试用例一致）
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// 设置规范化策略（与测试用例一致）
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// 在参考实现上运行命令，返回（规范化后的）每条响应
+    ///
+    /// 参考实现出错或响应数量不对都是测试器的配置问题，不是学生程序的问题。
+    pub fn responses(&self, commands: &[String]) -> Result<Vec<String>, TesterError> {
+        let commands: Vec<&str> = commands.iter().map(|s| s.as_str()).collect();
+        let output = CommandRunner::new(self.program.clone())
+            .with_timeouts(self.timeouts)
+            .run(&commands)
+            .map_err(|e| TesterError::Configuration(format!("The reference implementation failed: {}", e)))?;
+
+        if output.responses.len() != commands.len() {
+            return Err(TesterError::Configuration(format!(
+                "The reference implementation printed {} response(s) for {} command(s)",
+                output.responses.len(),
+                commands.len()
+            )));
+        }
+        Ok(output.responses.iter().map(|response| self.normalization.apply(&response.text())).collect())
+    }
+}
+
+/// 比较手写的期望和参考实现的输出，返回每处不一致的说明
+pub fn disagreements(
+    commands: &[impl AsRef<str>],
+    expected: &[ExpectedLine],
+    reference: &[String],
+) -> Vec<String> {
+    commands
+        .iter()
+        .zip(expected)
+        .zip(reference)
+        .enumerate()
+        .filter(|(_, ((_, expected), actual))| !expected.matches(actual))
+        .map(|(i, ((command, expected), actual))| {
+            format!(
+                "#{} {}: expected '{}', but the reference printed '{}'",
+                i + 1,
+                command.as_ref(),
+                expected,
+                actual.replace('\n', "\\n")
+            )
+        })
+        .collect()
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试只报告参考实现输出不被接受的行
+    #[test]
+    fn test_disagreements() {
+        let commands = ["INIT 2", "PUT a 1", "GET a", "SIZE"];
+        let expected = vec![
+            ExpectedLine::exact("OK"),
+            ExpectedLine::exact("OK"),
+            ExpectedLine::one_of(&["1", "NULL"]),
+            ExpectedLine::exact("2"),
+        ];
+        let reference: Vec<String> = ["OK", "OK", "1", "1"].iter().map(|s| s.to_string()).collect();
+
+        assert_eq!(
+            disagreements(&commands, &expected, &reference),
+            vec!["#4 SIZE: expected '2', but the reference printed '1'"]
+        );
+    }
+
+    /// 测试参考实现目录中没有 your_program.sh 时报告配置错误
+    #[test]
+    fn test_missing_reference_is_configuration_error() {
+        match Reference::from_dir("/nonexistent/reference") {
+            Err(TesterError::Configuration(message)) => {
+                assert!(message.contains("/nonexistent/reference/your_program.sh"), "{}", message);
+            }
+            Err(other) => panic!("expected a configuration error, got {}", other),
+            Ok(_) => panic!("expected a configuration error"),
+        }
+    }
+}
@@ -1,25 +1,99 @@
-use tester_utils::{Executable, TesterError};
+use std::env;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, TcpStream};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tester_utils::TesterError;
+use crate::http::{spawn_http_worker, HttpClient};
+use crate::resp::{RespCommandWriter, RespConnection, RespValue};
+use crate::normalize::Normalization;
+use crate::response::{FramingRules, Response, ResponseFramer};
+use crate::transport::{self, Transport, PORT_ENV, SERVER_STARTUP_TIMEOUT};
+use crate::usage::{self, ResourceUsage};
+
+/// 超时配置
+/// 
+/// - per_command: 单条命令从发送到收到响应的最长时间（仅交互模式生效，
+///   批量模式下程序可能缓冲 stdout，无法可靠地按命令计时）
+/// - per_test: 整个测试（启动 → 退出）的最长时间
+/// 
+/// 任一超时触发时，Runner 会杀掉整个进程组，并报告最后一条得到响应的命令、
+/// 卡住的命令以及已捕获的 stdout/stderr。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    pub per_command: Option<Duration>,
+    pub per_test: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            per_command: Some(Duration::from_secs(10)),
+            per_test: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl Timeouts {
+    /// 不设置任何超时
+    pub fn none() -> Self {
+        Self {
+            per_command: None,
+            per_test: None,
+        }
+    }
+    
+    /// 设置单条命令超时
+    pub fn with_per_command(mut self, timeout: Duration) -> Self {
+        self.per_command = Some(timeout);
+        self
+    }
+    
+    /// 设置整个测试的超时
+    pub fn with_per_test(mut self, timeout: Duration) -> Self {
+        self.per_test = Some(timeout);
+        self
+    }
+}
 
 /// CommandRunner - Batch stdin/stdout 模式
 /// 
 /// 设计理念（基于 CodeCrafters 测试架构研究）:
 /// - Week 1 使用简单的 batch 模式: 一次性发送所有命令，等待所有响应
-/// - Week 2-3 可选升级到 PTY 交互模式（参考 Shell-Tester）
+/// - 需要逐条交互时使用 InteractiveCommandRunner（见下文）
 /// 
 /// 工作流程:
 /// 1. Start program once
 /// 2. Write all commands to stdin
 /// 3. Close stdin (EOF)
-/// 4. Wait for program to exit
-/// 5. Read all responses from stdout
+/// 4. Wait for program to exit (受 per_test 超时限制)
+/// 5. Read all responses from stdout (按 ProgramSpec::framing 切分多行响应)
 pub struct CommandRunner {
-    executable: Executable,
+    program: ProgramSpec,
+    timeouts: Timeouts,
 }
 
 impl CommandRunner {
-    /// 创建 CommandRunner
-    pub fn new(executable: Executable) -> Self {
-        Self { executable }
+    /// 创建 CommandRunner（使用默认超时）
+    /// 
+    /// 参数是 `ProgramSpec` 而不是 `Executable`: Runner 自己启动进程，
+    /// 用 `ProgramSpec::from_env()` 取得学生程序。
+    pub fn new(program: ProgramSpec) -> Self {
+        Self {
+            program,
+            timeouts: Timeouts::default(),
+        }
+    }
+    
+    /// 设置超时
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
     }
     
     /// 批量发送命令并读取所有响应
@@ -28,10 +102,9 @@ impl CommandRunner {
     /// 
     /// # 示例
     /// ```rust,no_run
-    /// # use lru_cache_tester::helpers::CommandRunner;
-    /// # use tester_utils::Executable;
-    /// # fn example(executable: Executable) -> Result<(), Box<dyn std::error::Error>> {
-    /// let mut runner = CommandRunner::new(executable);
+    /// # use lru_cache_tester::helpers::{CommandRunner, ProgramSpec};
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut runner = CommandRunner::new(ProgramSpec::from_env()?);
     /// let responses = runner.send_commands(&[
     ///     "INIT 10",
     ///     "PUT name Alice",
@@ -42,36 +115,118 @@ impl CommandRunner {
     /// # }
     /// ```
     pub fn send_commands(&mut self, commands: &[&str]) -> Result<Vec<String>, TesterError> {
-        // 使用 run_with_stdin 一次性发送所有命令
-        let stdin_data = commands.join("\n") + "\n";
-        
-        let result = self.executable.run_with_stdin(stdin_data.as_bytes(), &[])
-            .map_err(|e| TesterError::Configuration(format!("Execution failed: {:?}", e)))?;
+        let output = self.run(commands)?;
         
-        // 4. 检查退出码
-        if result.exit_code != 0 {
+        // 验证响应数量匹配
+        if output.responses.len() != commands.len() {
             return Err(TesterError::User(format!(
-                "Program exited with code {}: {}",
-                result.exit_code,
-                String::from_utf8_lossy(&result.stderr)
+                "Expected {} responses, got {}. Output: {:?}\n{}",
+                commands.len(),
+                output.responses.len(),
+                output.responses,
+                output_block("stderr", &output.stderr)
             ).into()));
         }
         
-        // 5. 解析输出（按行分割）
-        let output = String::from_utf8_lossy(&result.stdout);
-        let responses: Vec<String> = output.lines().map(|s| s.to_string()).collect();
+        let normalization = Normalization::default();
+        Ok(output.responses.iter().map(|response| normalization.apply(&response.text())).collect())
+    }
+    
+    /// 批量发送命令，返回响应、stderr 和退出状态
+    /// 
+    /// 与 send_commands 不同，这里不检查响应数量（交给 Assertion 逐行报告），
+    /// 并且无论成功与否都保留 stderr，方便在断言失败时展示学生的调试输出。
+    /// 程序以非零状态退出或超时仍然直接返回错误。
+    pub fn run(&mut self, commands: &[&str]) -> Result<RunOutput, TesterError> {
+        let mut program = RunningProgram::spawn(&self.program, &self.timeouts)?;
+        let deadline = self.timeouts.per_test.map(|t| program.process.started_at + t);
+        let server = self.program.transport.is_server();
         
-        // 6. 验证响应数量匹配
-        if responses.len() != commands.len() {
-            return Err(TesterError::User(format!(
-                "Expected {} responses, got {}. Output: {:?}",
-                commands.len(),
-                responses.len(),
-                responses
-            ).into()));
+        // 1-3. 在独立线程中写入所有命令（stdin 模式下写完即关闭 stdin），
+        //      避免程序输出过多时双方互相阻塞
+        let input_data = commands.join("\n") + "\n";
+        let mut input = program.input.take().expect("input is open");
+        let writer = thread::spawn(move || {
+            let _ = input.write_all(input_data.as_bytes());
+            let _ = input.flush();
+        });
+        
+        // 4. 按帧格式收集每条命令的响应，直到 EOF 或超时（服务器不会关闭连接，收齐响应即停止）
+        let mut responses: Vec<Response> = Vec::new();
+        for command in commands {
+            match program.recv_response(command, deadline) {
+                ResponseEvent::Response(response) => responses.push(response),
+                ResponseEvent::Eof(partial) => {
+                    if !partial.is_empty() {
+                        responses.push(Response::Lines(partial));
+                    }
+                    // 服务器关闭连接通常意味着它崩溃了，等它退出以便报告退出状态
+                    if server {
+                        program.process.wait(Some(Instant::now() + EXIT_GRACE));
+                    }
+                    break;
+                }
+                ResponseEvent::TimedOut(mut unframed) => {
+                    program.kill();
+                    unframed.extend(program.drain_lines());
+                    return Err(hang_error(
+                        "test",
+                        self.timeouts.per_test.unwrap_or_default(),
+                        commands,
+                        &responses,
+                        &unframed,
+                        &program.stderr(),
+                    ));
+                }
+            }
         }
         
-        Ok(responses)
+        // stdin 模式下多余的输出行也作为响应保留，交给 Assertion 报告数量不符
+        while !server && responses.len() >= commands.len() {
+            match program.recv_line(deadline) {
+                LineEvent::Line(line) => responses.push(Response::Line(line)),
+                LineEvent::Eof => break,
+                LineEvent::TimedOut => {
+                    program.kill();
+                    return Err(hang_error(
+                        "test",
+                        self.timeouts.per_test.unwrap_or_default(),
+                        commands,
+                        &responses,
+                        &program.drain_lines(),
+                        &program.stderr(),
+                    ));
+                }
+            }
+        }
+        let _ = writer.join();
+        
+        let status = match program.stop(deadline) {
+            Ok(status) => status,
+            Err(()) => {
+                program.kill();
+                return Err(hang_error(
+                    "test",
+                    self.timeouts.per_test.unwrap_or_default(),
+                    commands,
+                    &responses,
+                    &[],
+                    &program.stderr(),
+                ));
+            }
+        };
+        
+        // 5. 检查退出码
+        let output = RunOutput {
+            responses,
+            stderr: program.stderr(),
+            exit_status: status,
+            usage: program.process.usage(),
+            delays: Vec::new(),
+        };
+        output.check_exit_status(commands, program.crash_hint)?;
+        
+        Ok(output)
     }
     
     /// 发送单条命令并返回响应（便捷方法）
@@ -83,52 +238,1409 @@ impl CommandRunner {
     }
 }
 
+/// 学生程序的启动描述
+/// 
+/// Runner 需要自己持有子进程（交互读写、超时后杀掉进程组），因此直接根据
+/// `SYSTEMQUEST_REPOSITORY_DIR` 定位 `your_program.sh`
+/// （与 `TesterDefinition::new("your_program.sh")` 一致）；
+/// 测试中也可以用 `ProgramSpec::new` 直接指定一个脚本。
+#[derive(Debug, Clone)]
+pub struct ProgramSpec {
+    /// 可执行文件路径
+    pub path: PathBuf,
+    
+    /// 额外传给程序的环境变量
+    pub env: Vec<(String, String)>,
+    
+    /// 命令传输方式（stdin 或 TCP 服务器）
+    pub transport: Transport,
+    
+    /// 各命令响应的帧格式（默认每条命令一行）
+    pub framing: FramingRules,
+    
+    /// 程序异常退出（崩溃或非零退出码）时附加的提示，通常按 Stage 设置
+    pub crash_hint: Option<&'static str>,
+}
+
+impl ProgramSpec {
+    /// 使用指定路径创建 ProgramSpec
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            env: Vec::new(),
+            transport: Transport::Stdin,
+            framing: FramingRules::new(),
+            crash_hint: None,
+        }
+    }
+    
+    /// 根据 SYSTEMQUEST_REPOSITORY_DIR 定位学生的 your_program.sh，
+    /// 并根据 SYSTEMQUEST_TRANSPORT 选择传输方式
+    pub fn from_env() -> Result<Self, TesterError> {
+        let repository_dir = env::var("SYSTEMQUEST_REPOSITORY_DIR").map_err(|_| {
+            TesterError::Configuration(
+                "SYSTEMQUEST_REPOSITORY_DIR is not set, cannot locate your_program.sh".to_string(),
+            )
+        })?;
+        
+        Ok(Self::new(Path::new(&repository_dir).join("your_program.sh"))
+            .with_transport(Transport::from_env()?))
+    }
+    
+    /// 设置传输方式
+    pub fn with_transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+    
+    /// 设置多行响应的帧格式（例如 KEYS 返回 `*<n>` 计数头加 n 行）
+    pub fn with_framing(mut self, framing: FramingRules) -> Self {
+        self.framing = framing;
+        self
+    }
+    
+    /// 设置程序崩溃时的提示（例如 Stage 4: 检查双向链表的指针更新）
+    pub fn with_crash_hint(mut self, hint: &'static str) -> Self {
+        self.crash_hint = Some(hint);
+        self
+    }
+    
+    /// 添加一个环境变量
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+    
+    /// 启动程序，stdout/stderr 通过管道连接
+    /// 
+    /// stdin 模式下 stdin 也通过管道连接；服务器模式下 stdin 为空，命令通过 TCP 发送。
+    /// 程序运行在独立的进程组中，超时后可以连同它启动的子进程一起杀掉
+    fn spawn(&self, extra_env: &[(&str, String)]) -> Result<Child, TesterError> {
+        let mut command = Command::new(&self.path);
+        command
+            .stdin(if self.transport.is_server() { Stdio::null() } else { Stdio::piped() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            // Python 在管道上默认使用块缓冲，交互模式下会导致响应迟迟不到
+            .env("PYTHONUNBUFFERED", "1");
+        
+        if let Some(dir) = self.path.parent() {
+            command.current_dir(dir);
+        }
+        
+        for (key, value) in &self.env {
+            command.env(key, value);
+        }
+        
+        for (key, value) in extra_env {
+            command.env(key, value);
+        }
+        
+        command.spawn().map_err(|e| {
+            TesterError::Configuration(format!(
+                "Failed to start {}: {}",
+                self.path.display(),
+                e
+            ))
+        })
+    }
+}
+
+/// 解析 "SLEEP <seconds>" 命令；使用测试器侧等待时它不会发送给程序
+pub fn tester_delay(command: &str) -> Option<Duration> {
+    let mut parts = command.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("SLEEP"), Some(seconds), None) => seconds
+            .parse::<f64>()
+            .ok()
+            .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+            .map(Duration::from_secs_f64),
+        _ => None,
+    }
+}
+
+/// InteractiveCommandRunner - 交互模式（发一条、读一条）
+/// 
+/// 与 CommandRunner 的区别:
+/// - 程序在整个测试期间保持运行，状态保存在内存中
+/// - 每发送一条命令，立即读取完整的一条响应（通常一行，见 ProgramSpec::framing）后再发送下一条
+/// - 可以在命令之间执行测试器侧的动作（计时、等待等）
+/// 
+/// 实现说明: 使用普通管道而不是 PTY，避免引入额外依赖和 ANSI 转义码问题。
+/// 代价是学生程序必须在每条响应后 flush stdout（Python 通过 PYTHONUNBUFFERED 自动处理）。
+/// 
+/// # 示例
+/// ```rust,no_run
+/// # use lru_cache_tester::helpers::{InteractiveCommandRunner, ProgramSpec, Timeouts};
+/// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let spec = ProgramSpec::from_env()?;
+/// let mut runner = InteractiveCommandRunner::start(&spec, Timeouts::default())?;
+/// assert_eq!(runner.send_command("INIT 10")?, "OK");
+/// assert_eq!(runner.send_command("PUT name Alice")?, "OK");
+/// assert_eq!(runner.send_command("GET name")?, "Alice");
+/// runner.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct InteractiveCommandRunner {
+    program: RunningProgram,
+    timeouts: Timeouts,
+    
+    /// 已发送的命令和已收到的响应（用于超时/崩溃时的诊断信息）
+    commands: Vec<String>,
+    responses: Vec<Response>,
+    
+    /// 测试器侧等待的起点: 上一条响应（或上一次等待结束）的时间
+    idle_since: Instant,
+}
+
+impl InteractiveCommandRunner {
+    /// 启动程序并保持运行
+    /// 
+    /// 超时在启动时就需要（服务器模式等待端口就绪也受 per_test 限制），
+    /// 所以作为参数传入而不是之后再设置；per_test 从程序启动时开始计算。
+    pub fn start(spec: &ProgramSpec, timeouts: Timeouts) -> Result<Self, TesterError> {
+        Ok(Self {
+            program: RunningProgram::spawn(spec, &timeouts)?,
+            timeouts,
+            commands: Vec::new(),
+            responses: Vec::new(),
+            idle_since: Instant::now(),
+        })
+    }
+    
+    /// 发送一条命令并返回响应文本（多行响应用 "\n" 连接，按默认策略规范化）
+    pub fn send_command(&mut self, command: &str) -> Result<String, TesterError> {
+        self.send(command).map(|response| Normalization::default().apply(&response.text()))
+    }
+    
+    /// 发送一条命令并读取一条完整的响应
+    pub fn send(&mut self, command: &str) -> Result<Response, TesterError> {
+        self.commands.push(command.to_string());
+        
+        let input = self.program.input.as_mut().ok_or_else(|| {
+            TesterError::Configuration("input already closed".to_string())
+        })?;
+        
+        if let Err(e) = writeln!(input, "{}", command).and_then(|_| input.flush()) {
+            return Err(self.exited_error(&format!("failed to write command: {}", e)));
+        }
+        
+        // 取 per_command 与 per_test 剩余时间中较早的截止时间
+        let command_deadline = self.timeouts.per_command.map(|t| Instant::now() + t);
+        let test_deadline = self.timeouts.per_test.map(|t| self.program.process.started_at + t);
+        let deadline = match (command_deadline, test_deadline) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        
+        match self.program.recv_response(command, deadline) {
+            ResponseEvent::Response(response) => {
+                self.idle_since = Instant::now();
+                self.responses.push(response.clone());
+                Ok(response)
+            }
+            ResponseEvent::Eof(partial) if partial.is_empty() => {
+                Err(self.exited_error("program closed stdout before responding"))
+            }
+            ResponseEvent::Eof(partial) => Err(self.exited_error(&format!(
+                "program closed stdout in the middle of a multi-line response (got {:?})",
+                partial
+            ))),
+            ResponseEvent::TimedOut(mut unframed) => {
+                let (kind, limit) = if deadline == command_deadline {
+                    ("command", self.timeouts.per_command.unwrap_or_default())
+                } else {
+                    ("test", self.timeouts.per_test.unwrap_or_default())
+                };
+                
+                self.program.kill();
+                unframed.extend(self.program.drain_lines());
+                
+                let commands: Vec<&str> = self.commands.iter().map(|s| s.as_str()).collect();
+                Err(hang_error(kind, limit, &commands, &self.responses, &unframed, &self.program.stderr()))
+            }
+        }
+    }
+    
+    /// 测试器侧等待（不发送任何命令）: 从上一条响应起至少经过 duration
+    /// 
+    /// 返回从上一条响应到下一条命令实际经过的时间。机器过载时它可能明显更长；
+    /// 多出的时间由依赖时间的期望吸收（见 `run_with_tester_delays` 和 `RunOutput::timed_commands`）。
+    pub fn pause(&mut self, duration: Duration) -> Duration {
+        thread::sleep(duration.saturating_sub(self.idle_since.elapsed()));
+        let now = Instant::now();
+        let elapsed = now - self.idle_since;
+        self.idle_since = now;
+        elapsed
+    }
+    
+    /// 运行一组命令，SLEEP 由测试器自己等待（见 `pause`），不发送给程序
+    /// 
+    /// 这样 TTL 测试不依赖学生的 SLEEP 实现；SLEEP 在返回的响应中记为 "OK"，
+    /// 与模型和期望响应对齐，实际等待的时间记录在 `RunOutput::delays` 中（按 SLEEP 的下标）。
+    pub fn run_with_tester_delays(
+        spec: &ProgramSpec,
+        timeouts: Timeouts,
+        commands: &[&str],
+    ) -> Result<RunOutput, TesterError> {
+        let mut runner = Self::start(spec, timeouts)?;
+        let mut delays = Vec::new();
+        for (i, command) in commands.iter().enumerate() {
+            match tester_delay(command) {
+                Some(duration) => delays.push((i, runner.pause(duration))),
+                None => {
+                    runner.send(command)?;
+                }
+            }
+        }
+        
+        let mut output = runner.finish()?;
+        for &(i, _) in &delays {
+            output.responses.insert(i.min(output.responses.len()), Response::Line("OK".to_string()));
+        }
+        output.delays = delays;
+        Ok(output)
+    }
+    
+    /// 依次发送多条命令（每条都等待响应后再发下一条）
+    pub fn send_commands(&mut self, commands: &[&str]) -> Result<Vec<String>, TesterError> {
+        commands.iter().map(|command| self.send_command(command)).collect()
+    }
+    
+    /// 到目前为止捕获的 stderr
+    pub fn stderr(&self) -> String {
+        self.program.stderr()
+    }
+    
+    /// 关闭 stdin (EOF) 并等待程序正常退出，返回整个会话的响应、stderr 和退出状态
+    pub fn finish(mut self) -> Result<RunOutput, TesterError> {
+        let deadline = self.timeouts.per_test.map(|t| self.program.process.started_at + t);
+        let status = match self.program.stop(deadline) {
+            Ok(status) => status,
+            Err(()) => {
+                self.program.kill();
+                return Err(TesterError::User(format!(
+                    "Program did not exit within {:.1}s after stdin was closed (EOF).\n{}",
+                    self.timeouts.per_test.unwrap_or_default().as_secs_f64(),
+                    output_block("stderr", &self.program.stderr())
+                ).into()));
+            }
+        };
+        
+        let output = self.output(status);
+        let commands: Vec<&str> = self.commands.iter().map(|s| s.as_str()).collect();
+        output.check_exit_status(&commands, self.program.crash_hint)?;
+        
+        Ok(output)
+    }
+    
+    /// 向程序的进程组发送信号（例如 SIGTERM，测试优雅退出），不等待程序处理
+    pub fn signal(&mut self, signal: i32) -> Result<(), TesterError> {
+        if self.program.process.try_wait().is_some() {
+            return Err(TesterError::User(format!(
+                "Cannot send signal {}: the program has already exited.\n{}",
+                signal,
+                output_block("stderr", &self.program.stderr())
+            ).into()));
+        }
+        // process_group(0) 使进程组 ID 等于子进程 PID
+        let result = unsafe { libc::kill(-(self.program.process.child.id() as libc::pid_t), signal) };
+        if result != 0 {
+            return Err(TesterError::Configuration(format!(
+                "Failed to send signal {}: {}",
+                signal,
+                io::Error::last_os_error()
+            )));
+        }
+        Ok(())
+    }
+    
+    /// 等待程序自行退出（不关闭 stdin，例如收到 SIGTERM 之后），返回整个会话的输出
+    /// 
+    /// 不检查退出状态（由调用者判断）；超时后杀掉程序并返回错误。
+    pub fn wait_for_exit(mut self, timeout: Duration) -> Result<RunOutput, TesterError> {
+        match self.program.process.wait(Some(Instant::now() + timeout)) {
+            Some(status) => Ok(self.output(Some(status))),
+            None => {
+                self.program.kill();
+                Err(TesterError::User(format!(
+                    "Program did not exit within {:.1}s.\n{}",
+                    timeout.as_secs_f64(),
+                    output_block("stderr", &self.program.stderr())
+                ).into()))
+            }
+        }
+    }
+    
+    /// 杀掉程序（SIGKILL，整个进程组），返回整个会话的输出（不检查退出状态）
+    pub fn kill(mut self) -> RunOutput {
+        self.program.kill();
+        self.program.process.join_log_readers();
+        let status = self.program.process.try_wait();
+        self.output(status)
+    }
+    
+    /// 会话结束后的输出
+    fn output(&mut self, exit_status: Option<ExitStatus>) -> RunOutput {
+        RunOutput {
+            responses: std::mem::take(&mut self.responses),
+            stderr: self.program.stderr(),
+            exit_status,
+            usage: self.program.process.usage(),
+            delays: Vec::new(),
+        }
+    }
+    
+    /// 构造"程序未响应"的错误，附带已捕获的 stderr
+    /// 
+    /// 程序已经异常退出时报告退出状态（信号名称）和崩溃时正在处理的命令。
+    fn exited_error(&mut self, reason: &str) -> TesterError {
+        // 关闭输出后程序通常马上退出；退出后等 stderr 读完，保证错误信息完整
+        if let Some(status) = self.program.process.wait(Some(Instant::now() + EXIT_GRACE)) {
+            if !status.success() {
+                let commands: Vec<&str> = self.commands.iter().map(|s| s.as_str()).collect();
+                return crash_error(
+                    &status,
+                    &commands,
+                    &self.responses,
+                    &self.program.stderr(),
+                    self.program.crash_hint,
+                );
+            }
+        }
+        
+        TesterError::User(format!(
+            "No response to command #{} ({}): {}. \
+            Make sure your program flushes stdout after every response.\n{}",
+            self.commands.len(),
+            self.commands.last().map(|s| s.as_str()).unwrap_or_default(),
+            reason,
+            output_block("stderr", &self.program.stderr())
+        ).into())
+    }
+}
+
+/// 一次运行的完整结果
+/// 
+/// stderr 和退出状态总是被保留（即使程序"成功"退出），
+/// 断言失败时可以和响应一起展示给学生。
+#[derive(Debug, Clone)]
+pub struct RunOutput {
+    /// 每条命令的响应（按帧格式切分）
+    pub responses: Vec<Response>,
+    
+    /// 捕获的全部 stderr（服务器模式下还包括 stdout 日志）
+    pub stderr: String,
+    
+    /// 程序的退出状态（服务器模式下由测试器停止时为 None）
+    pub exit_status: Option<ExitStatus>,
+    
+    /// 程序的资源使用情况（wait4 回收子进程时取得）
+    pub usage: Option<ResourceUsage>,
+    
+    /// 测试器侧等待实际经过的时间（SLEEP 的下标，从上一条响应到下一条命令）
+    pub delays: Vec<(usize, Duration)>,
+}
+
+impl RunOutput {
+    /// 把测试器等待的 SLEEP 换成实际经过的时间（毫秒精度），供模型重新计算依赖时间的期望
+    pub fn timed_commands(&self, commands: &[String]) -> Vec<String> {
+        let mut commands = commands.to_vec();
+        for &(i, elapsed) in &self.delays {
+            if let Some(command) = commands.get_mut(i) {
+                *command = format!("SLEEP {:.3}", elapsed.as_secs_f64());
+            }
+        }
+        commands
+    }
+    
+    /// 退出状态的简短描述，例如 "code 0"
+    pub fn describe_exit_status(&self) -> String {
+        match &self.exit_status {
+            Some(status) => describe_exit_status(status),
+            None => "no exit (server stopped by the tester)".to_string(),
+        }
+    }
+    
+    /// 带标签、截断后的 stderr 块（附带退出状态）
+    pub fn stderr_block(&self) -> String {
+        format!(
+            "Program exited with {}\n{}",
+            self.describe_exit_status(),
+            output_block("stderr", &self.stderr)
+        )
+    }
+    
+    /// 非零退出或被信号终止视为失败，报告崩溃时正在处理的命令
+    fn check_exit_status(&self, commands: &[&str], crash_hint: Option<&str>) -> Result<(), TesterError> {
+        match &self.exit_status {
+            Some(status) if !status.success() => {
+                Err(crash_error(status, commands, &self.responses, &self.stderr, crash_hint))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 // ============================================================================
-// 未来改进（Week 2-3 可选）
+// 内部实现: 进程管理
 // ============================================================================
-// 
-// 如果需要真正的交互式测试（发一条读一条），参考 Shell-Tester 的 PTY 模式:
-// 
-// ```rust
-// use pty_process::Pty;
-// 
-// pub struct InteractiveCommandRunner {
-//     pty: Pty,
-//     buffer: String,
-// }
-// 
-// impl InteractiveCommandRunner {
-//     pub fn send_command(&mut self, cmd: &str) -> Result<String, TesterError> {
-//         // 1. Write command to PTY
-//         writeln!(self.pty, "{}", cmd)?;
-//         
-//         // 2. Read until newline (real-time response)
-//         let mut line = String::new();
-//         self.pty.read_line(&mut line)?;
-//         
-//         Ok(line.trim().to_string())
-//     }
-// }
-// ```
-// 
-// 优点:
-// - 真正的实时交互
-// - 程序保持运行，状态保持在内存
-// - 更接近真实使用场景
-// 
-// 缺点:
-// - 需要添加 pty-process crate
-// - 实现复杂度增加约 100 行代码
-// - 需要处理 PTY 特有的问题（ANSI 转义码等）
-// 
-// 决策: Week 1 不实现，Week 2-3 根据需要评估
+
+/// 读取一行 stdout 的结果
+enum LineEvent {
+    Line(String),
+    Eof,
+    TimedOut,
+}
+
+/// 读取一条完整响应的结果（不完整时携带已读到的行）
+enum ResponseEvent {
+    Response(Response),
+    Eof(Vec<String>),
+    TimedOut(Vec<String>),
+}
+
+/// ServerProcess - 以服务器模式运行的学生程序
+/// 
+/// 负责分配端口（通过 `SYSTEMQUEST_CACHE_PORT` 传给程序）、等待程序开始监听，
+/// 并允许建立额外的连接。Runner 在 TCP / RESP 传输方式下使用它；
+/// 需要直接操作连接的测试（例如 RESP 协议一致性测试）也可以单独使用。
+/// 
+/// 被 drop 时会杀掉整个进程组。
+pub struct ServerProcess {
+    process: ProcessHandle,
+    port: u16,
+    transport: Transport,
+}
+
+impl ServerProcess {
+    /// 启动服务器并建立第一个连接
+    /// 
+    /// 程序在监听之前退出、或在启动超时（不超过 per_test）内仍未监听时返回错误，
+    /// 错误中附带程序已输出的日志。
+    pub fn start(spec: &ProgramSpec, timeouts: &Timeouts) -> Result<(Self, TcpStream), TesterError> {
+        let port = transport::free_port()?;
+        let mut process = ProcessHandle::spawn(spec, &[(PORT_ENV, port.to_string())])?;
+        
+        // 服务器的 stdout 只是日志，与 stderr 一起收集
+        let stdout = process.child.stdout.take().expect("stdout is piped");
+        process.log_readers.push(spawn_capture(stdout, &process.stderr));
+        
+        let startup = timeouts.per_test.map_or(SERVER_STARTUP_TIMEOUT, |t| t.min(SERVER_STARTUP_TIMEOUT));
+        let deadline = process.started_at + startup;
+        match transport::connect(port, || process.try_wait(), deadline) {
+            Ok(stream) => Ok((Self { process, port, transport: spec.transport }, stream)),
+            Err(err) => {
+                process.kill();
+                process.join_log_readers();
+                Err(match err {
+                    TesterError::User(e) => {
+                        TesterError::User(format!("{}\n{}", e, output_block("stderr", &process.stderr())).into())
+                    }
+                    other => other,
+                })
+            }
+        }
+    }
+    
+    /// 服务器监听的端口
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+    
+    /// 建立一个新的连接（用于多客户端测试）
+    pub fn connect(&self) -> Result<TcpStream, TesterError> {
+        let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, self.port)).map_err(|e| {
+            TesterError::User(format!("Failed to connect to 127.0.0.1:{}: {}", self.port, e).into())
+        })?;
+        let _ = stream.set_nodelay(true);
+        Ok(stream)
+    }
+    
+    /// 按服务器的传输方式建立一个新的客户端（用于多客户端并发测试）
+    pub fn client(&self, timeout: Duration) -> Result<CacheClient, TesterError> {
+        let connection = match self.transport {
+            Transport::Http => ClientConnection::Http(HttpClient::new(self.port).with_timeout(timeout)),
+            transport => {
+                let stream = self.connect()?;
+                let configured = stream.set_read_timeout(Some(timeout)).and_then(|_| {
+                    Ok(match transport {
+                        Transport::Resp => ClientConnection::Resp(RespConnection::new(stream, timeout)?),
+                        _ => ClientConnection::Line {
+                            reader: BufReader::new(stream.try_clone()?),
+                            writer: stream,
+                        },
+                    })
+                });
+                configured.map_err(|e| {
+                    TesterError::Configuration(format!("Failed to configure client connection: {}", e))
+                })?
+            }
+        };
+        Ok(CacheClient { connection })
+    }
+    
+    /// 服务器是否已经自行退出（例如崩溃）
+    pub fn try_exit_status(&mut self) -> Option<ExitStatus> {
+        self.process.try_wait()
+    }
+    
+    /// 到目前为止捕获的服务器输出（stderr 和 stdout 日志）
+    pub fn stderr(&self) -> String {
+        self.process.stderr()
+    }
+}
+
+/// CacheClient - 服务器模式下的一个客户端连接
+/// 
+/// 每次发送一行命令并等待一条响应，响应按传输方式规范化为文本
+/// （与 Runner 看到的相同）。通过 `ServerProcess::client` 创建，
+/// 每个客户端可以在自己的线程中使用。
+pub struct CacheClient {
+    connection: ClientConnection,
+}
+
+enum ClientConnection {
+    Line {
+        writer: TcpStream,
+        reader: BufReader<TcpStream>,
+    },
+    Resp(RespConnection),
+    Http(HttpClient),
+}
+
+impl CacheClient {
+    /// 发送一条命令并读取响应
+    pub fn send_command(&mut self, command: &str) -> io::Result<String> {
+        match &mut self.connection {
+            ClientConnection::Line { writer, reader } => {
+                writer.write_all(format!("{}\n", command).as_bytes())?;
+                writer.flush()?;
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 {
+                    return Err(io::Error::new(ErrorKind::UnexpectedEof, "server closed the connection"));
+                }
+                Ok(trim_line_ending(&line).to_string())
+            }
+            ClientConnection::Resp(connection) => {
+                connection.send(&RespValue::from_command_line(command))?;
+                Ok(connection.receive()?.to_response_line())
+            }
+            ClientConnection::Http(client) => client.execute(command),
+        }
+    }
+}
+
+/// 子进程及其日志收集（stdin 和服务器模式共用）
+/// 
+/// stderr（服务器模式下还包括 stdout 日志）由后台线程持续收集，避免管道写满后程序阻塞。
+struct ProcessHandle {
+    child: Child,
+    stderr: Arc<Mutex<Vec<u8>>>,
+    log_readers: Vec<JoinHandle<()>>,
+    started_at: Instant,
+    
+    /// 由 wait4 回收后的退出状态和资源使用情况
+    exit: Option<(ExitStatus, ResourceUsage)>,
+}
+
+impl ProcessHandle {
+    fn spawn(spec: &ProgramSpec, extra_env: &[(&str, String)]) -> Result<Self, TesterError> {
+        let mut child = spec.spawn(extra_env)?;
+        let started_at = Instant::now();
+        
+        let stderr = Arc::new(Mutex::new(Vec::new()));
+        let log_readers = vec![spawn_capture(child.stderr.take().expect("stderr is piped"), &stderr)];
+        
+        Ok(Self {
+            child,
+            stderr,
+            log_readers,
+            started_at,
+            exit: None,
+        })
+    }
+    
+    /// 程序已退出时回收它并返回退出状态（不阻塞）
+    /// 
+    /// 通过 wait4 回收以取得资源使用情况；回收后不能再调用 `Child::try_wait` / `wait`，
+    /// 所以所有退出检查都经过这里。
+    fn try_wait(&mut self) -> Option<ExitStatus> {
+        if self.exit.is_none() {
+            self.exit = usage::wait4(self.child.id(), false, self.started_at.elapsed()).ok().flatten();
+        }
+        self.exit.map(|(status, _)| status)
+    }
+    
+    /// 程序被回收后的资源使用情况
+    fn usage(&self) -> Option<ResourceUsage> {
+        self.exit.map(|(_, usage)| usage)
+    }
+    
+    /// 等待程序退出；超过 deadline 返回 None
+    fn wait(&mut self, deadline: Option<Instant>) -> Option<ExitStatus> {
+        loop {
+            if let Some(status) = self.try_wait() {
+                self.join_log_readers();
+                return Some(status);
+            }
+            
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return None;
+            }
+            
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    
+    /// 杀掉整个进程组（包括 your_program.sh 启动的子进程）并回收子进程
+    fn kill(&mut self) {
+        // process_group(0) 使进程组 ID 等于子进程 PID
+        unsafe {
+            libc::kill(-(self.child.id() as libc::pid_t), libc::SIGKILL);
+        }
+        if self.exit.is_none() {
+            self.exit = usage::wait4(self.child.id(), true, self.started_at.elapsed()).ok().flatten();
+        }
+    }
+    
+    /// 等待 stderr / 日志读取线程结束
+    fn join_log_readers(&mut self) {
+        for handle in self.log_readers.drain(..) {
+            let _ = handle.join();
+        }
+    }
+    
+    fn stderr(&self) -> String {
+        String::from_utf8_lossy(&self.stderr.lock().unwrap()).into_owned()
+    }
+}
+
+impl Drop for ProcessHandle {
+    fn drop(&mut self) {
+        // 测试中途失败时不留下僵尸进程
+        if self.try_wait().is_none() {
+            self.kill();
+        }
+    }
+}
+
+/// 程序关闭输出（stdout 或连接）后等待它退出的时间，用于报告崩溃的退出状态
+const EXIT_GRACE: Duration = Duration::from_millis(500);
+
+/// 已启动的学生程序（两种 Runner 共用）
+/// 
+/// 无论哪种传输方式，Runner 看到的都是"按行写命令、按条读响应":
+/// - Stdin: 命令写入 stdin，响应为 stdout 的每一行
+/// - Tcp: 命令和响应都是 TCP 连接上的行
+/// - Resp: 命令行被编码为 RESP 数组，RESP 回复被规范化为一行文本
+/// - Http: 每行命令由工作线程翻译为 HTTP 请求，响应被规范化为一行文本
+/// 
+/// 响应由后台线程读取并通过 channel 传回，这样读取可以带超时。
+struct RunningProgram {
+    process: ProcessHandle,
+    
+    /// 命令写入端: stdin 或 TCP 连接
+    input: Option<Box<dyn Write + Send>>,
+    
+    /// 响应读取端
+    lines: Receiver<String>,
+    
+    transport: Transport,
+    framing: FramingRules,
+    crash_hint: Option<&'static str>,
+}
+
+impl RunningProgram {
+    fn spawn(spec: &ProgramSpec, timeouts: &Timeouts) -> Result<Self, TesterError> {
+        if !spec.transport.is_server() {
+            let mut process = ProcessHandle::spawn(spec, &[])?;
+            let input = process.child.stdin.take().expect("stdin is piped");
+            let lines = spawn_line_reader(process.child.stdout.take().expect("stdout is piped"));
+            
+            return Ok(Self {
+                process,
+                input: Some(Box::new(input)),
+                lines,
+                transport: spec.transport,
+                framing: spec.framing.clone(),
+                crash_hint: spec.crash_hint,
+            });
+        }
+        
+        let (server, stream) = ServerProcess::start(spec, timeouts)?;
+        
+        let (input, lines): (Box<dyn Write + Send>, Receiver<String>) = if spec.transport == Transport::Http {
+            // HTTP 每个请求使用独立连接，启动探测用的连接不再需要
+            drop(stream);
+            let (writer, lines) = spawn_http_worker(HttpClient::new(server.port()));
+            (Box::new(writer), lines)
+        } else {
+            let reader = stream.try_clone().map_err(|e| {
+                TesterError::Configuration(format!("Failed to clone TCP stream: {}", e))
+            })?;
+            match spec.transport {
+                Transport::Resp => (Box::new(RespCommandWriter::new(stream)), spawn_resp_reader(reader)),
+                _ => (Box::new(stream), spawn_line_reader(reader)),
+            }
+        };
+        
+        Ok(Self {
+            process: server.process,
+            input: Some(input),
+            lines,
+            transport: spec.transport,
+            framing: spec.framing.clone(),
+            crash_hint: spec.crash_hint,
+        })
+    }
+    
+    /// 读取下一条响应（deadline 为 None 时无限等待）
+    fn recv_line(&self, deadline: Option<Instant>) -> LineEvent {
+        let result = match deadline {
+            Some(deadline) => self
+                .lines
+                .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => self.lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        
+        match result {
+            Ok(line) => LineEvent::Line(line),
+            Err(RecvTimeoutError::Disconnected) => LineEvent::Eof,
+            Err(RecvTimeoutError::Timeout) => LineEvent::TimedOut,
+        }
+    }
+    
+    /// 按该命令的帧格式读取一条完整响应
+    fn recv_response(&self, command: &str, deadline: Option<Instant>) -> ResponseEvent {
+        let mut framer = ResponseFramer::new(self.framing.framing_for(command));
+        loop {
+            match self.recv_line(deadline) {
+                LineEvent::Line(line) => {
+                    if let Some(response) = framer.push(line) {
+                        return ResponseEvent::Response(response);
+                    }
+                }
+                LineEvent::Eof => return ResponseEvent::Eof(framer.partial()),
+                LineEvent::TimedOut => return ResponseEvent::TimedOut(framer.partial()),
+            }
+        }
+    }
+    
+    /// 杀掉进程后取出剩余的响应（最多等待片刻）
+    fn drain_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        while let Ok(line) = self.lines.recv_timeout(Duration::from_millis(100)) {
+            lines.push(line);
+        }
+        lines
+    }
+    
+    /// 结束会话
+    /// 
+    /// - stdin 模式: 关闭 stdin (EOF) 并等待程序退出；超过 deadline 返回 Err(())
+    /// - 服务器模式: 服务器不会自己退出，直接停止它；返回 Ok(None)，
+    ///   除非它在此之前已经自行退出（例如崩溃）
+    fn stop(&mut self, deadline: Option<Instant>) -> Result<Option<ExitStatus>, ()> {
+        drop(self.input.take());
+        
+        if self.transport.is_server() {
+            let status = self.process.try_wait();
+            if status.is_none() {
+                self.process.kill();
+            }
+            self.process.join_log_readers();
+            return Ok(status);
+        }
+        
+        self.process.wait(deadline).map(Some).ok_or(())
+    }
+    
+    /// 杀掉整个进程组（包括 your_program.sh 启动的子进程）
+    fn kill(&mut self) {
+        self.process.kill();
+    }
+    
+    fn stderr(&self) -> String {
+        self.process.stderr()
+    }
+}
+
+/// 在后台线程中逐行读取，每行（去掉 "\n"）通过 channel 发送
+/// 
+/// "\r" 保留在行中，由 `Normalization` 决定是否忽略，并在不匹配时给出说明。
+fn spawn_line_reader<R: Read + Send + 'static>(reader: R) -> Receiver<String> {
+    let (sender, lines) = mpsc::channel();
+    let mut reader = BufReader::new(reader);
+    thread::spawn(move || {
+        loop {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if sender.send(trim_newline(&line).to_string()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    lines
+}
+
+/// 在后台线程中逐个解码 RESP 回复，规范化为文本行后通过 channel 发送
+/// 
+/// 数组回复按 `RespValue::to_response_lines` 展开为计数头 `*<n>` 加每个元素一行，
+/// 与 `Framing::Counted` 的多行响应一致。
+/// 回复格式错误时发送一条描述错误的响应（便于在断言中展示）并停止读取。
+fn spawn_resp_reader<R: Read + Send + 'static>(reader: R) -> Receiver<String> {
+    let (sender, lines) = mpsc::channel();
+    let mut reader = BufReader::new(reader);
+    thread::spawn(move || {
+        loop {
+            match RespValue::read(&mut reader) {
+                Ok(value) => {
+                    if value.to_response_lines().into_iter().any(|line| sender.send(line).is_err()) {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::InvalidData => {
+                    let _ = sender.send(format!("<invalid RESP reply: {}>", e));
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+    });
+    lines
+}
+
+/// 在后台线程中持续读取，全部追加到 sink
+fn spawn_capture<R: Read + Send + 'static>(mut reader: R, sink: &Arc<Mutex<Vec<u8>>>) -> JoinHandle<()> {
+    let sink = Arc::clone(sink);
+    thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        while let Ok(n) = reader.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            sink.lock().unwrap().extend_from_slice(&buf[..n]);
+        }
+    })
+}
+
+/// 构造超时错误: 最后一条得到响应的命令、卡住的命令、已捕获的输出
+fn hang_error(
+    kind: &str,
+    limit: Duration,
+    commands: &[&str],
+    responses: &[Response],
+    unframed: &[String],
+    stderr: &str,
+) -> TesterError {
+    let mut message = format!(
+        "Timed out after {:.1}s ({} timeout), the program was killed.\n",
+        limit.as_secs_f64(),
+        kind
+    );
+    
+    let answered = responses.len().min(commands.len());
+    if answered == 0 {
+        message.push_str("No command was answered.\n");
+    } else {
+        message.push_str(&format!(
+            "Last answered: command #{}: {} → {}\n",
+            answered,
+            commands[answered - 1],
+            responses[answered - 1]
+        ));
+    }
+    
+    match commands.get(answered) {
+        Some(command) => message.push_str(&format!("Hung on command #{}: {}\n", answered + 1, command)),
+        None => message.push_str("All commands were answered, but the program did not exit after EOF.\n"),
+    }
+    
+    // stdout 块按原样展示: 已切分的响应各行 + 未组成完整响应的行
+    let stdout: Vec<&str> = responses
+        .iter()
+        .flat_map(|response| response.lines())
+        .chain(unframed.iter().map(|s| s.as_str()))
+        .collect();
+    message.push_str(&output_block("stdout", &stdout.join("\n")));
+    message.push('\n');
+    message.push_str(&output_block("stderr", stderr));
+    
+    TesterError::User(message.into())
+}
+
+/// 输出块最多显示的行数（超出部分只保留末尾）
+const OUTPUT_BLOCK_MAX_LINES: usize = 20;
+
+/// 将程序输出格式化为带标签的块，过长时只保留最后若干行
+pub(crate) fn output_block(label: &str, text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() {
+        return format!("--- {} (empty) ---", label);
+    }
+    
+    let mut block = format!("--- {} ---\n", label);
+    if lines.len() > OUTPUT_BLOCK_MAX_LINES {
+        block.push_str(&format!(
+            "... ({} earlier line(s) omitted)\n",
+            lines.len() - OUTPUT_BLOCK_MAX_LINES
+        ));
+    }
+    for line in &lines[lines.len().saturating_sub(OUTPUT_BLOCK_MAX_LINES)..] {
+        block.push_str(line);
+        block.push('\n');
+    }
+    block.push_str(&format!("--- end of {} ---", label));
+    block
+}
+
+/// 描述退出状态: "code N" 或 "signal N"
+fn describe_exit_status(status: &ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+    
+    match (status.code(), status.signal()) {
+        // your_program.sh 没有 exec 时，shell 把子进程被信号 N 终止报告为退出码 128 + N
+        (Some(code), _) if code > 128 && signal_name(code - 128).is_some() => {
+            format!("code {} (a child process was killed by {})", code, describe_signal(code - 128))
+        }
+        (Some(code), _) => format!("code {}", code),
+        (None, Some(signal)) if status.core_dumped() => {
+            format!("signal {} (core dumped)", describe_signal(signal))
+        }
+        (None, Some(signal)) => format!("signal {}", describe_signal(signal)),
+        (None, None) => status.to_string(),
+    }
+}
+
+/// 信号的描述，例如 "11 (SIGSEGV: segmentation fault, invalid memory access)"
+pub(crate) fn describe_signal(signal: i32) -> String {
+    match signal_name(signal) {
+        Some((name, meaning)) => format!("{} ({}: {})", signal, name, meaning),
+        None => signal.to_string(),
+    }
+}
+
+/// 常见终止信号的名称和含义
+fn signal_name(signal: i32) -> Option<(&'static str, &'static str)> {
+    Some(match signal {
+        libc::SIGSEGV => ("SIGSEGV", "segmentation fault, invalid memory access"),
+        libc::SIGABRT => ("SIGABRT", "aborted, e.g. a failed assertion or double free"),
+        libc::SIGKILL => ("SIGKILL", "killed, often by the OOM killer when memory runs out"),
+        libc::SIGBUS => ("SIGBUS", "bus error, invalid memory access"),
+        libc::SIGFPE => ("SIGFPE", "arithmetic error, e.g. integer division by zero"),
+        libc::SIGILL => ("SIGILL", "illegal instruction"),
+        libc::SIGPIPE => ("SIGPIPE", "wrote to a closed pipe or socket"),
+        libc::SIGTERM => ("SIGTERM", "terminated"),
+        libc::SIGINT => ("SIGINT", "interrupted, e.g. Ctrl-C"),
+        _ => return None,
+    })
+}
+
+/// 程序异常退出（崩溃或非零退出码）的错误
+/// 
+/// 根据已收到的响应数推断崩溃时正在处理的命令。
+fn crash_error(
+    status: &ExitStatus,
+    commands: &[&str],
+    responses: &[Response],
+    stderr: &str,
+    crash_hint: Option<&str>,
+) -> TesterError {
+    let mut message = format!("Program exited with {}\n", describe_exit_status(status));
+    
+    let answered = responses.len().min(commands.len());
+    match commands.get(answered) {
+        Some(command) => message.push_str(&format!(
+            "Crashed on command #{}: {} ({} of {} commands were answered)\n",
+            answered + 1,
+            command,
+            answered,
+            commands.len()
+        )),
+        None => message.push_str(&format!(
+            "All {} commands were answered; the program failed while shutting down.\n",
+            commands.len()
+        )),
+    }
+    if answered > 0 {
+        message.push_str(&format!(
+            "Last answered: command #{}: {} → {}\n",
+            answered,
+            commands[answered - 1],
+            responses[answered - 1]
+        ));
+    }
+    message.push_str(&output_block("stderr", stderr));
+    
+    if let Some(hint) = crash_hint {
+        message.push_str(&format!("\n\nHint: {}", hint));
+    }
+    
+    TesterError::User(message.into())
+}
+
+/// 只去掉行尾的 "\n"，保留 "\r"
+fn trim_newline(line: &str) -> &str {
+    line.strip_suffix('\n').unwrap_or(line)
+}
+
+/// 去掉行尾的 "\n" / "\r\n"（与 `str::lines` 的行为一致）
+fn trim_line_ending(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
 
 // ============================================================================
 // 单元测试
 // ============================================================================
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use crate::response::Framing;
+    
+    /// write_script 写出的"学生程序"，离开作用域时删除它所在的临时目录
+    /// （包括脚本运行时在 `dir` 中写下的文件）
+    pub(crate) struct Script {
+        pub(crate) spec: ProgramSpec,
+        pub(crate) dir: PathBuf,
+    }
+    
+    impl Script {
+        /// 在启动描述上应用 builder，例如 `|spec| spec.with_transport(Transport::Tcp)`
+        pub(crate) fn with(mut self, builder: impl FnOnce(ProgramSpec) -> ProgramSpec) -> Self {
+            self.spec = builder(self.spec.clone());
+            self
+        }
+    }
+    
+    impl Drop for Script {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+    
+    /// 在单独的临时目录中写一个可执行的 shell 脚本作为"学生程序"
+    pub(crate) fn write_script(name: &str, body: &str) -> Script {
+        let dir = env::temp_dir().join(format!("lru-cache-tester-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("your_program.sh");
+        fs::write(&path, format!("#!/bin/sh\n{}", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        Script { spec: ProgramSpec::new(path), dir }
+    }
+    
+    /// 测试交互模式逐条发送、逐条读取
+    #[test]
+    fn test_interactive_round_trip() {
+        let script = write_script("echo", "while read line; do echo \"got $line\"; done\n");
+        let mut runner = InteractiveCommandRunner::start(&script.spec, Timeouts::default()).unwrap();
+        
+        assert_eq!(runner.send_command("INIT 10").unwrap(), "got INIT 10");
+        assert_eq!(runner.send_command("GET a").unwrap(), "got GET a");
+        
+        let output = runner.finish().unwrap();
+        assert_eq!(output.responses, vec!["got INIT 10", "got GET a"]);
+        assert_eq!(output.describe_exit_status(), "code 0");
+    }
+    
+    /// 测试程序提前退出时报告未响应的命令
+    #[test]
+    fn test_interactive_program_exits_early() {
+        let script = write_script("exit", "read line; echo OK; echo 'boom' >&2; exit 0\n");
+        let mut runner = InteractiveCommandRunner::start(&script.spec, Timeouts::default()).unwrap();
+        
+        assert_eq!(runner.send_command("INIT 10").unwrap(), "OK");
+        let err = runner.send_command("PUT a 1").unwrap_err().to_string();
+        assert!(err.contains("command #2 (PUT a 1)"));
+        assert!(err.contains("--- stderr ---\nboom\n--- end of stderr ---"));
+    }
+    
+    /// 测试解析测试器侧等待的 SLEEP 命令
+    #[test]
+    fn test_tester_delay() {
+        assert_eq!(tester_delay("SLEEP 1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(tester_delay("SLEEP 2"), Some(Duration::from_secs(2)));
+        assert_eq!(tester_delay("SLEEP"), None);
+        assert_eq!(tester_delay("SLEEP -1"), None);
+        assert_eq!(tester_delay("SLEEP 1 2"), None);
+        assert_eq!(tester_delay("GET SLEEP"), None);
+    }
+    
+    /// 测试 SLEEP 由测试器等待、不发送给程序，并在响应中记为 OK
+    #[test]
+    fn test_run_with_tester_delays() {
+        let script = write_script("delays", "while read line; do echo \"got $line\"; done\n");
+        let started = Instant::now();
+        let output = InteractiveCommandRunner::run_with_tester_delays(
+            &script.spec,
+            Timeouts::default(),
+            &["INIT 1", "SLEEP 0.2", "GET a"],
+        ).unwrap();
+        
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(output.responses, vec!["got INIT 1", "OK", "got GET a"]);
+        assert_eq!(output.delays.len(), 1);
+        assert_eq!(output.delays[0].0, 1);
+        assert!(output.delays[0].1 >= Duration::from_millis(200));
+        
+        let commands: Vec<String> = ["INIT 1", "SLEEP 0.2", "GET a"].iter().map(|s| s.to_string()).collect();
+        let timed = output.timed_commands(&commands);
+        assert!(timed[1].starts_with("SLEEP 0.2"), "{}", timed[1]);
+        assert_eq!(timed[1].len(), "SLEEP 0.200".len());
+    }
+    
+    /// 测试等待从上一条响应算起: 响应之后已经过去的时间计入等待
+    #[test]
+    fn test_pause_measures_from_last_response() {
+        let script = write_script("pause", "while read line; do echo OK; done\n");
+        let mut runner = InteractiveCommandRunner::start(&script.spec, Timeouts::default()).unwrap();
+        runner.send("INIT 1").unwrap();
+        
+        thread::sleep(Duration::from_millis(150));
+        let started = Instant::now();
+        let elapsed = runner.pause(Duration::from_millis(200));
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(started.elapsed() < Duration::from_millis(150));
+        
+        // 连续的等待各自从上一次等待结束算起
+        assert!(runner.pause(Duration::from_millis(50)) >= Duration::from_millis(50));
+        runner.finish().unwrap();
+    }
+    
+    /// 测试批量模式逐行收集响应
+    #[test]
+    fn test_batch_round_trip() {
+        let script = write_script("batch", "while read line; do echo OK; done\n");
+        let mut runner = CommandRunner::new(script.spec.clone());
+        
+        assert_eq!(runner.send_commands(&["INIT 10", "PUT a 1"]).unwrap(), vec!["OK", "OK"]);
+    }
+    
+    /// 测试批量模式按帧格式切分多行响应
+    #[test]
+    fn test_batch_multi_line_framing() {
+        let script = write_script(
+            "batch-framing",
+            "while read cmd rest; do\n\
+             case $cmd in\n\
+               KEYS) echo '*2'; echo b; echo a ;;\n\
+               DUMP) echo a=1; echo END ;;\n\
+               *) echo OK ;;\n\
+             esac\n\
+             done\n",
+        )
+        .with(|spec| spec.with_framing(FramingRules::new().with("KEYS", Framing::Counted).with("DUMP", Framing::Terminated("END"))));
+        
+        let output = CommandRunner::new(script.spec.clone()).run(&["PUT a 1", "KEYS", "DUMP", "SIZE"]).unwrap();
+        assert_eq!(
+            output.responses,
+            vec![
+                Response::from("OK"),
+                Response::Lines(vec!["b".into(), "a".into()]),
+                Response::Lines(vec!["a=1".into()]),
+                Response::from("OK"),
+            ]
+        );
+    }
+    
+    /// 测试成功退出时也保留 stderr
+    #[test]
+    fn test_batch_keeps_stderr_on_success() {
+        let script = write_script(
+            "batch-stderr",
+            "while read line; do echo \"debug: $line\" >&2; echo OK; done\n",
+        );
+        let output = CommandRunner::new(script.spec.clone()).run(&["INIT 10", "GET a"]).unwrap();
+        
+        assert_eq!(output.responses, vec!["OK", "OK"]);
+        assert!(output.stderr.contains("debug: GET a"));
+        assert!(output.stderr_block().starts_with("Program exited with code 0\n--- stderr ---"));
+    }
+    
+    /// 测试回收程序时记录资源使用情况
+    #[test]
+    fn test_batch_reports_resource_usage() {
+        let script = write_script("batch-usage", "while read line; do echo OK; done\n");
+        let output = CommandRunner::new(script.spec.clone()).run(&["INIT 10"]).unwrap();
+        
+        let usage = output.usage.expect("usage is recorded after wait4");
+        assert!(usage.max_rss_kb > 0);
+        assert!(usage.wall_time > Duration::ZERO);
+    }
+    
+    /// 测试 "\r" 保留在原始响应中，send_commands 按默认策略去掉
+    #[test]
+    fn test_batch_keeps_carriage_return() {
+        let script = write_script("batch-crlf", "while read line; do printf 'OK\\r\\n'; done\n");
+        let output = CommandRunner::new(script.spec.clone()).run(&["INIT 10"]).unwrap();
+        assert_eq!(output.responses, vec![Response::from("OK\r")]);
+        
+        let responses = CommandRunner::new(script.spec.clone()).send_commands(&["INIT 10", "GET a"]).unwrap();
+        assert_eq!(responses, vec!["OK", "OK"]);
+    }
+    
+    /// 测试程序被信号终止时报告信号名称、崩溃的命令和崩溃提示
+    #[test]
+    fn test_batch_crash_reports_signal_and_command() {
+        let script = write_script(
+            "batch-crash",
+            "read line; echo OK; read line; echo OK; read line; echo 'unlink failed' >&2; kill -SEGV $$\n",
+        )
+        .with(|spec| spec.with_crash_hint("check your DLL unlink"));
+        
+        let err = CommandRunner::new(script.spec.clone())
+            .run(&["INIT 2", "PUT a 1", "PUT b 2", "GET a"])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("signal 11 (SIGSEGV"));
+        assert!(err.contains("Crashed on command #3: PUT b 2 (2 of 4 commands were answered)"));
+        assert!(err.contains("Last answered: command #2: PUT a 1 → OK"));
+        assert!(err.contains("unlink failed"));
+        assert!(err.ends_with("Hint: check your DLL unlink"));
+    }
+    
+    /// 测试交互模式下程序崩溃时报告退出状态
+    #[test]
+    fn test_interactive_crash_reports_exit_status() {
+        let script = write_script("interactive-crash", "read line; echo OK; read line; exit 1\n");
+        let mut runner = InteractiveCommandRunner::start(&script.spec, Timeouts::default()).unwrap();
+        
+        assert_eq!(runner.send_command("INIT 2").unwrap(), "OK");
+        let err = runner.send_command("GET a").unwrap_err().to_string();
+        assert!(err.starts_with("Program exited with code 1\n"));
+        assert!(err.contains("Crashed on command #2: GET a"));
+    }
+    
+    /// 测试退出状态描述（信号名称、shell 报告的 128 + N）
+    #[test]
+    fn test_describe_exit_status() {
+        use std::os::unix::process::ExitStatusExt;
+        
+        assert_eq!(describe_exit_status(&ExitStatus::from_raw(3 << 8)), "code 3");
+        assert!(describe_exit_status(&ExitStatus::from_raw(libc::SIGABRT)).starts_with("signal 6 (SIGABRT"));
+        assert!(describe_exit_status(&ExitStatus::from_raw(137 << 8))
+            .starts_with("code 137 (a child process was killed by 9 (SIGKILL"));
+        assert_eq!(describe_exit_status(&ExitStatus::from_raw(64)), "signal 64");
+    }
+    
+    /// 测试批量模式下程序卡住时报告卡住的命令
+    #[test]
+    fn test_batch_hang_reports_command() {
+        let script = write_script(
+            "batch-hang",
+            "read line; echo OK; read line; echo 'locking' >&2; sleep 30\n",
+        );
+        let mut runner = CommandRunner::new(script.spec.clone())
+            .with_timeouts(Timeouts::none().with_per_test(Duration::from_millis(500)));
+        
+        let err = runner.send_commands(&["INIT 2", "CONCURRENT 50 MIXED", "SIZE"])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Last answered: command #1: INIT 2 → OK"));
+        assert!(err.contains("Hung on command #2: CONCURRENT 50 MIXED"));
+        assert!(err.contains("locking"));
+    }
+    
+    /// 测试交互模式下单条命令超时
+    #[test]
+    fn test_interactive_command_timeout() {
+        let script = write_script("interactive-hang", "read line; echo OK; read line; sleep 30\n");
+        let timeouts = Timeouts::none().with_per_command(Duration::from_millis(300));
+        let mut runner = InteractiveCommandRunner::start(&script.spec, timeouts).unwrap();
+        
+        assert_eq!(runner.send_command("INIT 2").unwrap(), "OK");
+        let err = runner.send_command("CONCURRENT 50 MIXED").unwrap_err().to_string();
+        assert!(err.contains("command timeout"));
+        assert!(err.contains("Hung on command #2: CONCURRENT 50 MIXED"));
+    }
+    
+    /// 一个按行回显的 TCP 服务器（需要 python3）
+    fn tcp_echo_server(name: &str) -> Option<Script> {
+        Command::new("python3").arg("--version").output().ok()?;
+        let script = write_script(name, r#"exec python3 -c '
+import os, socket
+s = socket.socket()
+s.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
+s.bind(("127.0.0.1", int(os.environ["SYSTEMQUEST_CACHE_PORT"])))
+s.listen()
+c, _ = s.accept()
+r, w = c.makefile("r"), c.makefile("w")
+for line in iter(r.readline, ""):
+    w.write("got " + line)
+    w.flush()
+'
+"#);
+        Some(script.with(|spec| spec.with_transport(Transport::Tcp)))
+    }
+    
+    /// 测试 TCP 模式下的批量和交互运行
+    #[test]
+    fn test_tcp_transport_round_trip() {
+        let Some(script) = tcp_echo_server("tcp") else { return };
+        
+        let output = CommandRunner::new(script.spec.clone()).run(&["INIT 10", "GET a"]).unwrap();
+        assert_eq!(output.responses, vec!["got INIT 10", "got GET a"]);
+        assert!(output.exit_status.is_none());
+        assert!(output.usage.is_some());
+        
+        let mut runner = InteractiveCommandRunner::start(&script.spec, Timeouts::default()).unwrap();
+        assert_eq!(runner.send_command("SIZE").unwrap(), "got SIZE");
+        assert!(runner.finish().is_ok());
+    }
+    
+    /// 测试服务器未监听端口就退出时的错误
+    #[test]
+    fn test_tcp_server_exits_before_listening() {
+        let script = write_script("tcp-exit", "echo 'no port for me' >&2; exit 3\n")
+            .with(|spec| spec.with_transport(Transport::Tcp));
+        
+        let err = CommandRunner::new(script.spec.clone()).run(&["INIT 10"]).unwrap_err().to_string();
+        assert!(err.contains("before listening on port"));
+        assert!(err.contains("no port for me"));
+    }
+    
+    /// 测试交互模式启动服务器时使用传入的 per_test 超时
+    #[test]
+    fn test_interactive_start_uses_timeouts() {
+        let script = write_script("tcp-silent", "sleep 30\n").with(|spec| spec.with_transport(Transport::Tcp));
+        let timeouts = Timeouts::none().with_per_test(Duration::from_millis(300));
+    
+        let started = Instant::now();
+        let err = InteractiveCommandRunner::start(&script.spec, timeouts).err().unwrap().to_string();
+        assert!(err.contains("did not start listening"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+    
+    /// 测试输出块截断
+    #[test]
+    fn test_output_block_truncates() {
+        let text: Vec<String> = (1..=25).map(|i| format!("line {}", i)).collect();
+        let block = output_block("stderr", &text.join("\n"));
+        
+        assert!(block.starts_with("--- stderr ---"));
+        assert!(block.contains("5 earlier line(s) omitted"));
+        assert!(!block.contains("line 5\n"));
+        assert!(block.contains("line 25"));
+        assert_eq!(output_block("stderr", ""), "--- stderr (empty) ---");
+    }
+    
+    /// 测试行尾处理与 str::lines 一致
+    #[test]
+    fn test_trim_line_ending() {
+        assert_eq!(trim_line_ending("OK\n"), "OK");
+        assert_eq!(trim_line_ending("OK\r\n"), "OK");
+        assert_eq!(trim_line_ending("OK"), "OK");
+    }
     
     /// 测试命令拼接逻辑
     #[test]
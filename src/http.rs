@@ -0,0 +1,482 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{Ipv4Addr, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+use serde_json::Value;
+use crate::stats::STATS_FIELDS;
+
+/// HttpCommand - 一条文本命令对应的 HTTP 操作
+///
+/// 命令映射:
+/// - `INIT <capacity>` → `POST /init`，JSON body `{"capacity": N}`
+/// - `PUT <key> <value> [ttl]` → `PUT /cache/{key}[?ttl=<seconds>]`，body 为原始值
+/// - `GET <key>` → `GET /cache/{key}`，404 表示 NULL
+/// - `SIZE` / `STATS` → `GET /stats`（JSON）
+/// - `CONCURRENT <n> <mode>` → `POST /concurrent`，JSON body `{"threads": N, "mode": "..."}`
+/// - `SLEEP <seconds>` → 由测试器在本地等待（Web 服务没有对应的接口）
+#[derive(Debug, Clone, PartialEq)]
+pub enum HttpCommand {
+    Request(HttpRequest, ResponseKind),
+    Sleep(Duration),
+    Unsupported(String),
+}
+
+/// 如何把 HTTP 响应规范化为 ExactMatchAssertion 期望的字符串
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseKind {
+    /// 2xx → "OK"
+    Ok,
+    /// 200 → body，404 → "NULL"
+    Value,
+    /// /stats JSON 的 size 字段
+    Size,
+    /// /stats JSON → "hits:X misses:Y hit_rate:Z.ZZ ..."
+    Stats,
+}
+
+impl HttpCommand {
+    /// 把一行文本命令翻译为 HTTP 操作
+    pub fn from_command_line(line: &str) -> Self {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let unsupported = || Self::Unsupported(line.to_string());
+
+        match parts.as_slice() {
+            ["INIT", capacity] => match capacity.parse::<u64>() {
+                Ok(capacity) => Self::Request(
+                    HttpRequest::json("POST", "/init", &serde_json::json!({ "capacity": capacity })),
+                    ResponseKind::Ok,
+                ),
+                Err(_) => unsupported(),
+            },
+            ["PUT", key, value] => Self::Request(
+                HttpRequest::new("PUT", &cache_path(key)).with_body("text/plain", value.as_bytes()),
+                ResponseKind::Ok,
+            ),
+            ["PUT", key, value, ttl] => Self::Request(
+                HttpRequest::new("PUT", &format!("{}?ttl={}", cache_path(key), percent_encode(ttl)))
+                    .with_body("text/plain", value.as_bytes()),
+                ResponseKind::Ok,
+            ),
+            ["GET", key] => Self::Request(HttpRequest::new("GET", &cache_path(key)), ResponseKind::Value),
+            ["SIZE"] => Self::Request(HttpRequest::new("GET", "/stats"), ResponseKind::Size),
+            ["STATS"] => Self::Request(HttpRequest::new("GET", "/stats"), ResponseKind::Stats),
+            ["CONCURRENT", threads, mode] => match threads.parse::<u64>() {
+                Ok(threads) => Self::Request(
+                    HttpRequest::json("POST", "/concurrent", &serde_json::json!({ "threads": threads, "mode": mode })),
+                    ResponseKind::Ok,
+                ),
+                Err(_) => unsupported(),
+            },
+            ["SLEEP", seconds] => match seconds.parse::<f64>().ok().and_then(|s| Duration::try_from_secs_f64(s).ok()) {
+                Some(duration) => Self::Sleep(duration),
+                None => unsupported(),
+            },
+            _ => unsupported(),
+        }
+    }
+}
+
+impl ResponseKind {
+    /// 把 HTTP 响应规范化为一行文本
+    ///
+    /// 非预期的状态码或格式错误的 JSON 规范化为 "(error) ..."，
+    /// 这样它们会作为普通的不匹配响应展示给学生。
+    pub fn normalize(self, response: &HttpResponse) -> String {
+        if self == Self::Value && response.status == 404 {
+            return "NULL".to_string();
+        }
+        if !(200..300).contains(&response.status) {
+            return format!("(error) HTTP {}: {}", response.status, response.text().trim());
+        }
+
+        match self {
+            Self::Ok => "OK".to_string(),
+            Self::Value => response.text(),
+            Self::Size => match response.json() {
+                Ok(stats) => match stats.get("size") {
+                    Some(size) => json_scalar(size),
+                    None => "(error) /stats JSON has no \"size\" field".to_string(),
+                },
+                Err(e) => format!("(error) {}", e),
+            },
+            Self::Stats => match response.json() {
+                Ok(stats) => format_stats(&stats),
+                Err(e) => format!("(error) {}", e),
+            },
+        }
+    }
+}
+
+/// 单个 HTTP 请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn new(method: &str, path: &str) -> Self {
+        Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            content_type: None,
+            body: Vec::new(),
+        }
+    }
+
+    /// 创建带 JSON body 的请求
+    pub fn json(method: &str, path: &str, body: &Value) -> Self {
+        Self::new(method, path).with_body("application/json", body.to_string().as_bytes())
+    }
+
+    pub fn with_body(mut self, content_type: &str, body: &[u8]) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self.body = body.to_vec();
+        self
+    }
+
+    /// 编码为 HTTP/1.1 请求（每个请求使用独立连接，Connection: close）
+    pub fn encode(&self, port: u16) -> Vec<u8> {
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nConnection: close\r\nContent-Length: {}\r\n",
+            self.method,
+            self.path,
+            port,
+            self.body.len()
+        );
+        if let Some(content_type) = &self.content_type {
+            head.push_str(&format!("Content-Type: {}\r\n", content_type));
+        }
+        head.push_str("\r\n");
+
+        let mut out = head.into_bytes();
+        out.extend_from_slice(&self.body);
+        out
+    }
+
+    /// 用于日志的简短描述，例如 `PUT /cache/a`
+    pub fn describe(&self) -> String {
+        format!("{} {}", self.method, self.path)
+    }
+}
+
+/// 单个 HTTP 响应
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// 读取一个完整的响应（支持 Content-Length、chunked 以及读到连接关闭为止）
+    pub fn read<R: BufRead>(reader: &mut R) -> io::Result<Self> {
+        let status_line = read_header_line(reader)?;
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .filter(|_| status_line.starts_with("HTTP/1."))
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| invalid(&format!("invalid status line {:?}", status_line)))?;
+
+        let mut content_length = None;
+        let mut chunked = false;
+        loop {
+            let line = read_header_line(reader)?;
+            if line.is_empty() {
+                break;
+            }
+            let Some((name, value)) = line.split_once(':') else {
+                return Err(invalid(&format!("invalid header {:?}", line)));
+            };
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = Some(value.parse::<usize>().map_err(|_| {
+                    invalid(&format!("invalid Content-Length {:?}", value))
+                })?);
+            } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                chunked = value.to_ascii_lowercase().contains("chunked");
+            }
+        }
+
+        let body = if chunked {
+            read_chunked_body(reader)?
+        } else if let Some(len) = content_length {
+            let mut body = vec![0u8; len];
+            reader.read_exact(&mut body)?;
+            body
+        } else {
+            let mut body = Vec::new();
+            reader.read_to_end(&mut body)?;
+            body
+        };
+
+        Ok(Self { status, body })
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json(&self) -> Result<Value, String> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| format!("invalid JSON in response body ({}): {}", e, self.text().trim()))
+    }
+}
+
+/// HttpClient - 向本地服务器发送请求（每个请求一个新连接）
+#[derive(Debug, Clone, Copy)]
+pub struct HttpClient {
+    port: u16,
+    timeout: Option<Duration>,
+}
+
+impl HttpClient {
+    pub fn new(port: u16) -> Self {
+        Self { port, timeout: None }
+    }
+
+    /// 设置单个请求的读取超时
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn send(&self, request: &HttpRequest) -> io::Result<HttpResponse> {
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, self.port))?;
+        let _ = stream.set_nodelay(true);
+        stream.set_read_timeout(self.timeout)?;
+        stream.write_all(&request.encode(self.port))?;
+        stream.flush()?;
+        HttpResponse::read(&mut BufReader::new(stream))
+    }
+
+    /// 执行一条文本命令，返回规范化后的响应
+    pub fn execute(&self, line: &str) -> io::Result<String> {
+        match HttpCommand::from_command_line(line) {
+            HttpCommand::Request(request, kind) => {
+                let response = self.send(&request).map_err(|e| {
+                    io::Error::new(e.kind(), format!("{} failed: {}", request.describe(), e))
+                })?;
+                Ok(kind.normalize(&response))
+            }
+            HttpCommand::Sleep(duration) => {
+                thread::sleep(duration);
+                Ok("OK".to_string())
+            }
+            HttpCommand::Unsupported(line) => Ok(format!("(error) command not supported over HTTP: {}", line)),
+        }
+    }
+}
+
+/// HttpCommandWriter - 把按行写入的文本命令交给 HTTP 工作线程
+///
+/// 与 `RespCommandWriter` 相同，Runner 仍然按行写命令；
+/// 每一行由工作线程翻译为 HTTP 请求，规范化后的响应从 channel 读回。
+pub struct HttpCommandWriter {
+    commands: Sender<String>,
+    pending: Vec<u8>,
+}
+
+impl Write for HttpCommandWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']).to_string();
+            self.commands
+                .send(line)
+                .map_err(|_| io::Error::new(ErrorKind::BrokenPipe, "HTTP worker stopped"))?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 启动 HTTP 工作线程，按顺序执行命令
+///
+/// 请求失败（例如服务器崩溃）时发送一条描述错误的响应并停止；
+/// writer 被 drop 后线程退出，响应 channel 随之关闭。
+pub(crate) fn spawn_http_worker(client: HttpClient) -> (HttpCommandWriter, Receiver<String>) {
+    let (commands, command_rx) = mpsc::channel::<String>();
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in command_rx {
+            match client.execute(&line) {
+                Ok(response) => {
+                    if sender.send(response).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    let _ = sender.send(format!("<HTTP request failed: {}>", e));
+                    break;
+                }
+            }
+        }
+    });
+
+    let writer = HttpCommandWriter {
+        commands,
+        pending: Vec::new(),
+    };
+    (writer, lines)
+}
+
+/// `/cache/{key}`，key 做百分号编码
+fn cache_path(key: &str) -> String {
+    format!("/cache/{}", percent_encode(key))
+}
+
+/// 百分号编码（保留 RFC 3986 unreserved 字符）
+fn percent_encode(text: &str) -> String {
+    let mut out = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+/// /stats JSON → "hits:X misses:Y hit_rate:Z.ZZ evictions:E expirations:X size:S capacity:C"
+fn format_stats(stats: &Value) -> String {
+    let mut fields = Vec::new();
+    for name in STATS_FIELDS {
+        let Some(value) = stats.get(name) else {
+            return format!("(error) /stats JSON has no \"{}\" field", name);
+        };
+        let value = match (name, value.as_f64()) {
+            ("hit_rate", Some(rate)) => format!("{:.2}", rate),
+            _ => json_scalar(value),
+        };
+        fields.push(format!("{}:{}", name, value));
+    }
+    fields.join(" ")
+}
+
+/// JSON 标量的文本形式（字符串不带引号）
+fn json_scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// 读取以 \r\n（或 \n）结尾的一行头部
+fn read_header_line<R: BufRead>(reader: &mut R) -> io::Result<String> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Err(io::Error::new(ErrorKind::UnexpectedEof, "connection closed before a complete response"));
+    }
+    let line = String::from_utf8_lossy(&line);
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn read_chunked_body<R: BufRead>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let size_line = read_header_line(reader)?;
+        let size = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| invalid(&format!("invalid chunk size {:?}", size_line)))?;
+        if size == 0 {
+            // 跳过 trailer
+            while !read_header_line(reader)?.is_empty() {}
+            return Ok(body);
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        read_header_line(reader)?;
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn response(raw: &str) -> HttpResponse {
+        HttpResponse::read(&mut Cursor::new(raw.as_bytes().to_vec())).unwrap()
+    }
+
+    fn request(line: &str) -> (HttpRequest, ResponseKind) {
+        match HttpCommand::from_command_line(line) {
+            HttpCommand::Request(request, kind) => (request, kind),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    /// 测试命令到 HTTP 请求的映射
+    #[test]
+    fn test_command_mapping() {
+        let (init, kind) = request("INIT 5");
+        assert_eq!(init.describe(), "POST /init");
+        assert_eq!(init.body, br#"{"capacity":5}"#.to_vec());
+        assert_eq!(kind, ResponseKind::Ok);
+
+        let (put, _) = request("PUT user:1 Alice 2.5");
+        assert_eq!(put.describe(), "PUT /cache/user%3A1?ttl=2.5");
+        assert_eq!(put.body, b"Alice".to_vec());
+
+        assert_eq!(request("GET a"), (HttpRequest::new("GET", "/cache/a"), ResponseKind::Value));
+        assert_eq!(request("SIZE").1, ResponseKind::Size);
+        assert_eq!(request("STATS").1, ResponseKind::Stats);
+        assert_eq!(HttpCommand::from_command_line("SLEEP 1.5"), HttpCommand::Sleep(Duration::from_millis(1500)));
+        assert!(matches!(HttpCommand::from_command_line("FLY away"), HttpCommand::Unsupported(_)));
+    }
+
+    /// 测试响应规范化
+    #[test]
+    fn test_normalize_responses() {
+        let ok = response("HTTP/1.1 204 No Content\r\n\r\n");
+        assert_eq!(ResponseKind::Ok.normalize(&ok), "OK");
+
+        let value = response("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nAlice");
+        assert_eq!(ResponseKind::Value.normalize(&value), "Alice");
+
+        let missing = response("HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\nnot found");
+        assert_eq!(ResponseKind::Value.normalize(&missing), "NULL");
+        assert_eq!(ResponseKind::Ok.normalize(&missing), "(error) HTTP 404: not found");
+
+        let stats = response(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n\
+            {\"hits\":2,\"misses\":1,\"hit_rate\":66.666,\"evictions\":0,\"expirations\":0,\"size\":2,\"capacity\":5}",
+        );
+        assert_eq!(
+            ResponseKind::Stats.normalize(&stats),
+            "hits:2 misses:1 hit_rate:66.67 evictions:0 expirations:0 size:2 capacity:5"
+        );
+        assert_eq!(ResponseKind::Size.normalize(&stats), "2");
+    }
+
+    /// 测试 chunked 响应和格式错误的 JSON
+    #[test]
+    fn test_chunked_and_invalid_json() {
+        let chunked = response("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nAli\r\n2\r\nce\r\n0\r\n\r\n");
+        assert_eq!(chunked.text(), "Alice");
+        assert!(ResponseKind::Stats.normalize(&chunked).starts_with("(error) invalid JSON"));
+
+        let err = HttpResponse::read(&mut Cursor::new(b"garbage\r\n\r\n".to_vec())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
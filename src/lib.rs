@@ -1,6 +1,21 @@
 pub mod assertions;
+pub mod clock;
+pub mod concurrency;
+pub mod definitions;
+pub mod differential;
 pub mod helpers;
+pub mod http;
+pub mod model;
+pub mod normalize;
+pub mod random;
+pub mod resp;
+pub mod response;
+pub mod scenario;
+pub mod shrink;
+pub mod stats;
 pub mod test_case;
+pub mod transport;
+pub mod usage;
 pub mod stage_1;
 pub mod stage_2;
 pub mod stage_3;
@@ -8,3 +23,4 @@ pub mod stage_4;
 pub mod stage_5;
 pub mod stage_6;
 pub mod stage_7;
+pub mod stage_8;
@@ -0,0 +1,674 @@
+use crate::assertions::{ExpectedLine, FieldExpectation, StatsAssertion};
+use crate::stats::Stats;
+
+/// 真实耗时可能比 SLEEP 之和多出的时间（秒，命令处理和进程调度）
+const TIMING_SLACK: f64 = 0.5;
+
+/// 一条 CONCURRENT 命令最多占用的时间（秒）
+const CONCURRENT_SECONDS: f64 = 2.0;
+
+/// CONCURRENT 读写的键: key_0 .. key_99
+const CONCURRENT_KEY_PREFIX: &str = "key_";
+
+/// 可以逐一列出的最大整数范围（用于比较期望）
+const MAX_ENUMERATED_RANGE: i64 = 1000;
+
+/// 淘汰策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// 按插入顺序淘汰，更新已有的键不改变顺序（Stage 2）
+    Fifo,
+
+    /// 淘汰最久未访问的键，GET 和 PUT 都算访问（Stage 3 起）
+    Lru,
+}
+
+impl EvictionPolicy {
+    /// 各 Stage 使用的策略: Stage 2 为 FIFO，其余为 LRU（Stage 1 不会触发淘汰）
+    pub fn for_stage(stage: u32) -> Self {
+        if stage == 2 {
+            Self::Fifo
+        } else {
+            Self::Lru
+        }
+    }
+}
+
+/// 计数器的可能取值（hi 为 None 表示没有上界，例如 CONCURRENT 之后的 hits）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Count {
+    lo: u64,
+    hi: Option<u64>,
+}
+
+impl Count {
+    fn zero() -> Self {
+        Self { lo: 0, hi: Some(0) }
+    }
+
+    fn exact(&self) -> Option<u64> {
+        self.hi.filter(|hi| *hi == self.lo)
+    }
+
+    /// 一定加一
+    fn increment(&mut self) {
+        self.lo += 1;
+        self.hi = self.hi.map(|hi| hi + 1);
+    }
+
+    /// 可能加一
+    fn maybe_increment(&mut self) {
+        self.hi = self.hi.map(|hi| hi + 1);
+    }
+
+    fn unbounded(&mut self) {
+        self.hi = None;
+    }
+
+    fn expectation(&self) -> FieldExpectation {
+        match self.exact() {
+            Some(value) => FieldExpectation::Exact(value.to_string()),
+            None if self.lo == 0 => FieldExpectation::Any,
+            None => FieldExpectation::AtLeast(self.lo),
+        }
+    }
+}
+
+/// TTL: 写入时刻（虚拟时钟区间）和存活时间
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Expiry {
+    written: (f64, f64),
+    ttl: f64,
+
+    /// CLOCK FREEZE 之后写入时的冻结时钟（毫秒），此时过期时间是精确的
+    written_ms: Option<u64>,
+}
+
+/// 条目是否已过期（时间太接近 TTL 时无法确定）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expired {
+    No,
+    Yes,
+    Maybe,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    key: String,
+    value: String,
+    expiry: Option<Expiry>,
+
+    /// false: 可能已被淘汰（CONCURRENT 或无法确定受害者的淘汰之后）
+    present: bool,
+}
+
+/// CacheModel - 协议的参考实现
+///
+/// 根据命令序列计算每条命令的期望响应，覆盖 INIT / PUT（可选 TTL）/ GET /
+/// SIZE / STATS / SLEEP / CONCURRENT / CLOCK:
+/// - FIFO 或 LRU 淘汰（淘汰计入 evictions，即使被淘汰的条目已过期）
+/// - TTL 惰性删除: 过期条目在 GET 访问时才删除（计入 misses 和 expirations），
+///   在此之前仍计入 SIZE
+/// - 时间是虚拟的: SLEEP 推进时钟，真实耗时最多多出 `TIMING_SLACK`；
+///   过期时间离当前时间太近时，GET 的期望是 "值或 NULL"
+/// - CLOCK FREEZE 之后时钟只由 CLOCK ADVANCE 推进（SLEEP 和 CONCURRENT 不再计时），
+///   冻结后写入的条目按毫秒精确过期
+///
+/// CONCURRENT 的结果是不确定的，模型保守处理:
+/// - 它在 key_0..key_99 上随机读写，假定写入的键足以填满缓存（Stage 5 的测试都依赖这一点）
+/// - 之前写入的键都可能已被淘汰，之后对它们的 GET 期望 "值或 NULL"
+/// - hits / misses / evictions 只知道下限，expirations 不变
+///
+/// 因此期望不一定是精确的字符串，也可能是候选集合、范围或 STATS 字段约束（`ExpectedLine`）。
+///
+/// # 示例
+/// ```rust
+/// # use lru_cache_tester::model::{CacheModel, EvictionPolicy};
+/// let expected = CacheModel::expectations(EvictionPolicy::Lru, &["INIT 2", "PUT a 1", "PUT b 2", "GET a", "PUT c 3", "GET b"]).unwrap();
+/// let expected: Vec<String> = expected.iter().map(|line| line.to_string()).collect();
+/// assert_eq!(expected, vec!["OK", "OK", "OK", "1", "OK", "NULL"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CacheModel {
+    policy: EvictionPolicy,
+    capacity: Option<usize>,
+
+    /// 淘汰顺序，队首最先被淘汰
+    entries: Vec<Entry>,
+
+    /// entries 的前 unordered 个条目（以及 fillers）之间顺序未知，但都排在其余条目之前
+    unordered: usize,
+
+    /// CONCURRENT 写入、按键无法跟踪的条目数量范围（都在无序区中）
+    fillers: (usize, usize),
+
+    size: (usize, usize),
+
+    /// 自第一条命令以来的虚拟时间区间（秒）
+    clock: (f64, f64),
+
+    /// CLOCK FREEZE 之后的冻结时钟（毫秒，从 0 开始；INIT 不会重置）
+    frozen_ms: Option<u64>,
+
+    hits: Count,
+    misses: Count,
+    evictions: Count,
+    expirations: Count,
+}
+
+impl CacheModel {
+    pub fn new(policy: EvictionPolicy) -> Self {
+        Self {
+            policy,
+            capacity: None,
+            entries: Vec::new(),
+            unordered: 0,
+            fillers: (0, 0),
+            size: (0, 0),
+            clock: (0.0, 0.0),
+            frozen_ms: None,
+            hits: Count::zero(),
+            misses: Count::zero(),
+            evictions: Count::zero(),
+            expirations: Count::zero(),
+        }
+    }
+
+    /// 依次执行所有命令，返回每条命令的期望响应
+    pub fn expectations(policy: EvictionPolicy, commands: &[impl AsRef<str>]) -> Result<Vec<ExpectedLine>, String> {
+        let mut model = Self::new(policy);
+        commands
+            .iter()
+            .enumerate()
+            .map(|(i, command)| {
+                model.apply(command.as_ref()).map_err(|e| format!("command #{} '{}': {}", i + 1, command.as_ref(), e))
+            })
+            .collect()
+    }
+
+    /// 执行一条命令，返回期望的响应
+    pub fn apply(&mut self, command: &str) -> Result<ExpectedLine, String> {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        let name = parts.first().copied().unwrap_or_default();
+        if name != "INIT" && name != "CLOCK" && self.capacity.is_none() {
+            return Err("the cache must be initialized with INIT first".to_string());
+        }
+
+        match (name, parts.get(1..).unwrap_or_default()) {
+            ("INIT", [capacity]) => {
+                let capacity = capacity.parse().map_err(|_| format!("invalid capacity '{}'", capacity))?;
+                *self = Self {
+                    capacity: Some(capacity),
+                    frozen_ms: self.frozen_ms,
+                    ..Self::new(self.policy)
+                };
+                Ok(ExpectedLine::exact("OK"))
+            }
+            ("PUT", [key, value]) => {
+                self.put(key, value, None);
+                Ok(ExpectedLine::exact("OK"))
+            }
+            ("PUT", [key, value, ttl]) => {
+                let ttl = parse_seconds(ttl)?;
+                self.put(key, value, Some(ttl));
+                Ok(ExpectedLine::exact("OK"))
+            }
+            ("GET", [key]) => self.get(key),
+            ("SIZE", []) => Ok(match self.size_bounds() {
+                (lo, hi) if lo == hi => ExpectedLine::exact(lo.to_string()),
+                (lo, hi) => ExpectedLine::range(lo as i64..=hi as i64),
+            }),
+            ("STATS", []) => Ok(self.stats()),
+            ("SLEEP", [seconds]) => {
+                let seconds = parse_seconds(seconds)?;
+                if self.frozen_ms.is_none() {
+                    self.clock = (self.clock.0 + seconds, self.clock.1 + seconds);
+                }
+                Ok(ExpectedLine::exact("OK"))
+            }
+            ("CLOCK", ["FREEZE"]) => {
+                self.frozen_ms.get_or_insert(0);
+                Ok(ExpectedLine::exact("OK"))
+            }
+            ("CLOCK", ["ADVANCE", amount]) => {
+                let millis = parse_millis(amount)?;
+                let frozen = self.frozen_ms.as_mut().ok_or("CLOCK ADVANCE needs CLOCK FREEZE first")?;
+                *frozen += millis;
+                let seconds = millis as f64 / 1000.0;
+                self.clock = (self.clock.0 + seconds, self.clock.1 + seconds);
+                Ok(ExpectedLine::exact("OK"))
+            }
+            ("CLOCK", ["NOW"]) => {
+                let frozen = self.frozen_ms.ok_or("CLOCK NOW needs CLOCK FREEZE first")?;
+                Ok(ExpectedLine::exact(frozen.to_string()))
+            }
+            ("CONCURRENT", [_, _]) => {
+                self.concurrent();
+                Ok(ExpectedLine::exact("OK"))
+            }
+            _ => Err("the model does not support this command".to_string()),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity.unwrap_or_default()
+    }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.key == key)
+    }
+
+    /// 可能是 CONCURRENT 写入、但模型无法跟踪的键
+    fn maybe_filler(&self, key: &str) -> bool {
+        self.fillers.1 > 0 && key.starts_with(CONCURRENT_KEY_PREFIX)
+    }
+
+    /// 移出第 i 个条目（维护无序区的长度）
+    fn take(&mut self, i: usize) -> Entry {
+        if i < self.unordered {
+            self.unordered -= 1;
+        }
+        self.entries.remove(i)
+    }
+
+    fn put(&mut self, key: &str, value: &str, ttl: Option<f64>) {
+        let expiry = ttl.map(|ttl| Expiry { written: self.clock, ttl, written_ms: self.frozen_ms });
+        match self.position(key) {
+            Some(i) if self.entries[i].present => {
+                // 更新已有的键: 值和 TTL 都替换，LRU 下移到队尾
+                let entry = &mut self.entries[i];
+                entry.value = value.to_string();
+                entry.expiry = expiry;
+                if self.policy == EvictionPolicy::Lru {
+                    let entry = self.take(i);
+                    self.entries.push(entry);
+                }
+            }
+            Some(i) => {
+                // 可能已被淘汰: 要么是更新，要么是插入
+                self.take(i);
+                self.insert(key, value, expiry, true);
+            }
+            None if self.maybe_filler(key) => {
+                self.fillers.0 = self.fillers.0.saturating_sub(1);
+                self.insert(key, value, expiry, true);
+            }
+            None => self.insert(key, value, expiry, false),
+        }
+    }
+
+    /// 插入一个条目；缓存满时先淘汰一个。`maybe_existing` 时键可能已经存在（不一定插入）
+    fn insert(&mut self, key: &str, value: &str, expiry: Option<Expiry>, maybe_existing: bool) {
+        let capacity = self.capacity();
+        let (lo, hi) = self.size_bounds();
+        if hi >= capacity {
+            self.evict(lo >= capacity && !maybe_existing);
+        }
+
+        let lo = if maybe_existing { lo } else { (lo + 1).min(capacity) };
+        self.size = (lo, (hi + 1).min(capacity));
+        self.entries.push(Entry {
+            key: key.to_string(),
+            value: value.to_string(),
+            expiry,
+            present: true,
+        });
+
+        // FIFO 下更新不改变位置: 键若已存在，它在队列中的位置未知
+        if maybe_existing && self.policy == EvictionPolicy::Fifo {
+            self.unordered = self.entries.len();
+        }
+    }
+
+    /// 淘汰队首的条目；`certain` 为 false 时可能不发生淘汰
+    fn evict(&mut self, certain: bool) {
+        if certain {
+            self.evictions.increment();
+        } else {
+            self.evictions.maybe_increment();
+        }
+
+        // 受害者可能在无序区中: 其中的条目都可能被淘汰
+        let unordered_may_exist = self.fillers.1 > 0 || self.unordered > 0;
+        let unordered_must_exist = self.fillers.0 > 0 || self.entries[..self.unordered].iter().any(|e| e.present);
+        if unordered_may_exist {
+            for entry in &mut self.entries[..self.unordered] {
+                entry.present = false;
+            }
+            self.fillers.0 = self.fillers.0.saturating_sub(1);
+        }
+        if certain && unordered_must_exist {
+            return;
+        }
+
+        // 否则受害者是有序区中第一个存在的条目
+        let ordered = self.unordered;
+        if certain && !unordered_may_exist && self.entries.get(ordered).is_some_and(|e| e.present) {
+            self.take(ordered);
+            return;
+        }
+        for entry in &mut self.entries[ordered..] {
+            let was_present = entry.present;
+            entry.present = false;
+            if was_present {
+                break;
+            }
+        }
+    }
+
+    fn expired(&self, entry: &Entry) -> Expired {
+        let Some(expiry) = entry.expiry else {
+            return Expired::No;
+        };
+        // 冻结时钟下写入的条目: 到期的那一毫秒起就过期
+        if let (Some(now), Some(written)) = (self.frozen_ms, expiry.written_ms) {
+            let ttl_ms = (expiry.ttl * 1000.0).round() as u64;
+            return if now - written >= ttl_ms { Expired::Yes } else { Expired::No };
+        }
+        // 实际经过的时间在 [最短, 最长] 之间
+        let shortest = self.clock.0 - expiry.written.1;
+        let longest = self.clock.1 - expiry.written.0 + TIMING_SLACK;
+        if shortest >= expiry.ttl {
+            Expired::Yes
+        } else if longest < expiry.ttl {
+            Expired::No
+        } else {
+            Expired::Maybe
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Result<ExpectedLine, String> {
+        let Some(i) = self.position(key) else {
+            if self.maybe_filler(key) {
+                // CONCURRENT 写入的值未知
+                self.hits.maybe_increment();
+                self.misses.maybe_increment();
+                self.fillers.0 = self.fillers.0.saturating_sub(1);
+                return ExpectedLine::pattern(r"\S+").map_err(|e| e.to_string());
+            }
+            self.misses.increment();
+            return Ok(ExpectedLine::exact("NULL"));
+        };
+
+        let entry = &self.entries[i];
+        let value = entry.value.clone();
+        match (entry.present, self.expired(entry)) {
+            (true, Expired::No) => {
+                self.hits.increment();
+                if self.policy == EvictionPolicy::Lru {
+                    let entry = self.take(i);
+                    self.entries.push(entry);
+                }
+                Ok(ExpectedLine::exact(value))
+            }
+            (present, Expired::Yes) => {
+                // 惰性删除: 过期条目在这里才被删除
+                self.misses.increment();
+                if present {
+                    self.expirations.increment();
+                    self.size = (self.size.0.saturating_sub(1), self.size.1.saturating_sub(1));
+                } else {
+                    self.expirations.maybe_increment();
+                    self.size.0 = self.size.0.saturating_sub(1);
+                }
+                self.take(i);
+                Ok(ExpectedLine::exact("NULL"))
+            }
+            (_, expired) => {
+                self.hits.maybe_increment();
+                self.misses.maybe_increment();
+                if expired == Expired::Maybe {
+                    self.expirations.maybe_increment();
+                    self.size.0 = self.size.0.saturating_sub(1);
+                }
+
+                // LRU 下若仍存在，它刚被访问过
+                self.entries[i].present = false;
+                if self.policy == EvictionPolicy::Lru {
+                    let entry = self.take(i);
+                    self.entries.push(entry);
+                }
+                Ok(ExpectedLine::OneOf(vec![value, "NULL".to_string()]))
+            }
+        }
+    }
+
+    fn concurrent(&mut self) {
+        // key_* 的值被随机覆盖，不再按键跟踪
+        self.entries.retain(|entry| !entry.key.starts_with(CONCURRENT_KEY_PREFIX));
+        for entry in &mut self.entries {
+            entry.present = false;
+        }
+        self.unordered = self.entries.len();
+
+        let capacity = self.capacity();
+        self.fillers = (capacity.saturating_sub(self.entries.len()), capacity);
+        self.size = (capacity, capacity);
+        self.hits.unbounded();
+        self.misses.unbounded();
+        self.evictions.unbounded();
+        if self.frozen_ms.is_none() {
+            self.clock.1 += CONCURRENT_SECONDS;
+        }
+    }
+
+    /// SIZE 的可能取值（过期但未被访问的条目仍然计入）
+    fn size_bounds(&self) -> (usize, usize) {
+        let present = self.entries.iter().filter(|entry| entry.present).count();
+        let lo = self.size.0.max(present + self.fillers.0);
+        (lo, self.size.1.max(lo))
+    }
+
+    fn stats(&self) -> ExpectedLine {
+        let capacity = self.capacity() as u64;
+        let (size_lo, size_hi) = self.size_bounds();
+        let exact = (
+            self.hits.exact(),
+            self.misses.exact(),
+            self.evictions.exact(),
+            self.expirations.exact(),
+        );
+        if let (Some(hits), Some(misses), Some(evictions), Some(expirations)) = exact {
+            if size_lo == size_hi {
+                let mut stats = Stats {
+                    hits,
+                    misses,
+                    hit_rate: 0.0,
+                    evictions,
+                    expirations,
+                    size: size_lo as u64,
+                    capacity,
+                };
+                stats.hit_rate = stats.expected_hit_rate();
+                return ExpectedLine::exact(stats.to_string());
+            }
+        }
+
+        let size = if size_lo == size_hi {
+            FieldExpectation::Exact(size_lo.to_string())
+        } else {
+            FieldExpectation::AtLeast(size_lo as u64)
+        };
+        let hit_rate = match (exact.0, exact.1) {
+            (Some(0), Some(0)) => FieldExpectation::Exact("0.00".to_string()),
+            (Some(hits), Some(misses)) => {
+                FieldExpectation::Exact(format!("{:.2}", hits as f64 / (hits + misses) as f64 * 100.0))
+            }
+            _ => FieldExpectation::Any,
+        };
+        ExpectedLine::Stats(
+            StatsAssertion::new()
+                .with_field("hits", self.hits.expectation())
+                .with_field("misses", self.misses.expectation())
+                .with_field("hit_rate", hit_rate)
+                .with_field("evictions", self.evictions.expectation())
+                .with_field("expirations", self.expirations.expectation())
+                .with_field("size", size)
+                .expect_field("capacity", capacity),
+        )
+    }
+}
+
+fn parse_seconds(text: &str) -> Result<f64, String> {
+    text.parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .ok_or_else(|| format!("invalid number of seconds '{}'", text))
+}
+
+/// 解析 CLOCK ADVANCE 的时长，例如 "1500ms"
+fn parse_millis(text: &str) -> Result<u64, String> {
+    text.strip_suffix("ms")
+        .and_then(|millis| millis.parse().ok())
+        .ok_or_else(|| format!("invalid duration '{}' (expected milliseconds, e.g. 1500ms)", text))
+}
+
+/// 比较手写的期望和模型计算的期望，返回每处不一致的说明
+///
+/// 模型允许的每个响应都必须被手写的期望接受，否则测试会随机失败（或本身就是错的）；
+/// 模型的期望无法逐一列出时（STATS 字段约束、正则），只检查手写的精确字符串。
+pub fn conflicts(
+    policy: EvictionPolicy,
+    commands: &[impl AsRef<str>],
+    expected: &[ExpectedLine],
+) -> Result<Vec<String>, String> {
+    let model = CacheModel::expectations(policy, commands)?;
+
+    let mut conflicts = Vec::new();
+    for (i, ((command, expected), model)) in commands.iter().zip(expected).zip(&model).enumerate() {
+        let problem = match possible_responses(model) {
+            Some(responses) => {
+                let rejected: Vec<&str> = responses
+                    .iter()
+                    .filter(|response| !expected.matches(response))
+                    .map(|response| response.as_str())
+                    .collect();
+                match (rejected.is_empty(), responses.len()) {
+                    (true, _) => None,
+                    (false, 1) => Some(format!("the model expects '{}'", model)),
+                    (false, _) => Some(format!("the model also allows '{}'", rejected.join("', '"))),
+                }
+            }
+            None => match expected {
+                ExpectedLine::Exact(line) if !model.matches(line) => {
+                    Some(format!("the model expects '{}'", model))
+                }
+                _ => None,
+            },
+        };
+        if let Some(problem) = problem {
+            conflicts.push(format!("#{} {}: expected '{}', but {}", i + 1, command.as_ref(), expected, problem));
+        }
+    }
+    Ok(conflicts)
+}
+
+/// 期望接受的所有响应（能逐一列出时）
+fn possible_responses(expected: &ExpectedLine) -> Option<Vec<String>> {
+    match expected {
+        ExpectedLine::Exact(line) => Some(vec![line.clone()]),
+        ExpectedLine::OneOf(options) => Some(options.clone()),
+        ExpectedLine::Range(range) if range.end() - range.start() <= MAX_ENUMERATED_RANGE => {
+            Some(range.clone().map(|value| value.to_string()).collect())
+        }
+        _ => None,
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(policy: EvictionPolicy, commands: &[&str]) -> Vec<String> {
+        CacheModel::expectations(policy, commands)
+            .unwrap()
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    /// 测试 FIFO 与 LRU 的淘汰顺序
+    #[test]
+    fn test_eviction_policies() {
+        let commands = ["INIT 2", "PUT a 1", "PUT b 2", "GET a", "PUT a 3", "PUT c 4", "GET a", "GET b", "SIZE"];
+        assert_eq!(run(EvictionPolicy::Fifo, &commands), vec!["OK", "OK", "OK", "1", "OK", "OK", "NULL", "2", "2"]);
+        assert_eq!(run(EvictionPolicy::Lru, &commands), vec!["OK", "OK", "OK", "1", "OK", "OK", "3", "NULL", "2"]);
+    }
+
+    /// 测试 TTL 惰性删除和 STATS 计数
+    #[test]
+    fn test_ttl_lazy_deletion_and_stats() {
+        let responses = run(EvictionPolicy::Lru, &[
+            "INIT 5", "PUT a 1 1", "PUT b 2", "SLEEP 1.5", "SIZE", "GET a", "GET b", "SIZE", "STATS",
+        ]);
+        assert_eq!(responses[4], "2");
+        assert_eq!(responses[5], "NULL");
+        assert_eq!(responses[7], "1");
+        assert_eq!(responses[8], "hits:1 misses:1 hit_rate:50.00 evictions:0 expirations:1 size:1 capacity:5");
+
+        // 时间太接近 TTL 时两种结果都可以
+        let responses = run(EvictionPolicy::Lru, &["INIT 5", "PUT a 1 1", "SLEEP 0.8", "GET a"]);
+        assert_eq!(responses[3], "1|NULL");
+    }
+
+    /// 测试冻结时钟下按毫秒精确过期，SLEEP 不再推进时钟
+    #[test]
+    fn test_virtual_clock() {
+        let responses = run(EvictionPolicy::Lru, &[
+            "CLOCK FREEZE", "INIT 5", "PUT a 1 1", "CLOCK ADVANCE 999ms", "GET a", "SLEEP 2", "GET a",
+            "CLOCK ADVANCE 1ms", "CLOCK NOW", "GET a", "INIT 5", "CLOCK NOW",
+        ]);
+        assert_eq!(responses, vec!["OK", "OK", "OK", "OK", "1", "OK", "1", "OK", "1000", "NULL", "OK", "1000"]);
+
+        assert!(CacheModel::expectations(EvictionPolicy::Lru, &["INIT 1", "CLOCK ADVANCE 5ms"]).is_err());
+        assert!(CacheModel::expectations(EvictionPolicy::Lru, &["INIT 1", "CLOCK FREEZE", "CLOCK ADVANCE 5"]).is_err());
+    }
+
+    /// 测试 CONCURRENT 之后的保守期望
+    #[test]
+    fn test_concurrent_uncertainty() {
+        let responses = run(EvictionPolicy::Lru, &[
+            "INIT 2", "PUT a 1", "PUT b 2", "CONCURRENT 5 READ_HEAVY", "PUT c 3", "GET a", "GET c", "SIZE", "STATS",
+        ]);
+        assert_eq!(responses[5..8], ["1|NULL", "3", "2"]);
+        assert_eq!(responses[8], "hits:>=1 misses:* hit_rate:* evictions:>=1 expirations:0 size:2 capacity:2");
+
+        // CONCURRENT 填满缓存: 之后的 SIZE 就是容量
+        let responses = run(EvictionPolicy::Lru, &["INIT 5", "PUT a 1", "CONCURRENT 5 READ_HEAVY", "SIZE"]);
+        assert_eq!(responses[3], "5");
+
+        let err = CacheModel::expectations(EvictionPolicy::Lru, &["PUT a 1"]).unwrap_err();
+        assert!(err.contains("INIT first"));
+        assert!(CacheModel::expectations(EvictionPolicy::Lru, &["INIT 2", "FLY"]).is_err());
+    }
+
+    /// 测试手写期望与模型的比较
+    #[test]
+    fn test_conflicts() {
+        let commands = ["INIT 1", "PUT a 1", "CONCURRENT 5 MIXED", "GET a", "SIZE"];
+        let strict = vec![
+            ExpectedLine::exact("OK"),
+            ExpectedLine::exact("OK"),
+            ExpectedLine::exact("OK"),
+            ExpectedLine::exact("1"),
+            ExpectedLine::exact("2"),
+        ];
+        assert_eq!(conflicts(EvictionPolicy::Lru, &commands, &strict).unwrap(), vec![
+            "#4 GET a: expected '1', but the model also allows 'NULL'".to_string(),
+            "#5 SIZE: expected '2', but the model expects '1'".to_string(),
+        ]);
+
+        let loose = vec![
+            ExpectedLine::exact("OK"),
+            ExpectedLine::exact("OK"),
+            ExpectedLine::exact("OK"),
+            ExpectedLine::one_of(&["1", "NULL"]),
+            ExpectedLine::range(0..=1),
+        ];
+        assert!(conflicts(EvictionPolicy::Lru, &commands, &loose).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::response::Response;
+
+/// 大小写折叠时识别的关键字
+pub const KEYWORDS: [&str; 3] = ["OK", "NULL", "ERROR"];
+
+/// Normalization - 比较前对响应做的规范化
+///
+/// 学生程序常见的"看不见"的差异: Windows 工具链输出 "\r\n"、行尾多余的空格、
+/// 小写的 "null"。默认只去掉行尾的一个 "\r"（与之前按 `str::lines()` 分行的行为一致，
+/// 行中间的 "\r" 保留），`strict()` 原样比较，`lenient()` 三种都做。
+///
+/// 无论使用哪种策略，只要规范化能消除不匹配，测试器都会明确指出原因
+/// （例如 "your output has a trailing \r"），见 `diagnose`。
+///
+/// # 示例
+/// ```rust
+/// # use lru_cache_tester::normalize::Normalization;
+/// assert_eq!(Normalization::default().apply("OK\r"), "OK");
+/// assert_eq!(Normalization::strict().apply("OK\r"), "OK\r");
+/// assert_eq!(Normalization::lenient().apply(" null "), "NULL");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalization {
+    /// 去掉行尾的 "\r"
+    pub strip_cr: bool,
+
+    /// 去掉首尾空白
+    pub trim: bool,
+
+    /// 关键字（OK / NULL / ERROR）不区分大小写
+    pub case_fold_keywords: bool,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            strip_cr: true,
+            trim: false,
+            case_fold_keywords: false,
+        }
+    }
+}
+
+impl Normalization {
+    /// 不做任何规范化
+    pub fn strict() -> Self {
+        Self {
+            strip_cr: false,
+            trim: false,
+            case_fold_keywords: false,
+        }
+    }
+
+    /// 去掉行尾的 "\r"、首尾空白，关键字不区分大小写
+    pub fn lenient() -> Self {
+        Self {
+            strip_cr: true,
+            trim: true,
+            case_fold_keywords: true,
+        }
+    }
+
+    pub fn with_strip_cr(mut self, enabled: bool) -> Self {
+        self.strip_cr = enabled;
+        self
+    }
+
+    pub fn with_trim(mut self, enabled: bool) -> Self {
+        self.trim = enabled;
+        self
+    }
+
+    pub fn with_case_fold_keywords(mut self, enabled: bool) -> Self {
+        self.case_fold_keywords = enabled;
+        self
+    }
+
+    /// 规范化一行
+    pub fn apply(&self, line: &str) -> String {
+        let mut line = match line.strip_suffix('\r') {
+            Some(stripped) if self.strip_cr => stripped.to_string(),
+            _ => line.to_string(),
+        };
+        if self.trim {
+            line = line.trim().to_string();
+        }
+        if self.case_fold_keywords {
+            line = line
+                .split(' ')
+                .map(|word| keyword(word).unwrap_or(word))
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+        line
+    }
+
+    /// 规范化一条响应（多行响应逐行处理）
+    pub fn apply_response(&self, response: &Response) -> Response {
+        match response {
+            Response::Line(line) => Response::Line(self.apply(line)),
+            Response::Lines(lines) => Response::Lines(lines.iter().map(|line| self.apply(line)).collect()),
+        }
+    }
+}
+
+/// 与关键字只有大小写不同时返回关键字本身
+fn keyword(word: &str) -> Option<&'static str> {
+    KEYWORDS.iter().copied().find(|k| word.eq_ignore_ascii_case(k))
+}
+
+/// 说明规范化能消除的差异
+///
+/// `matches` 判断一行是否符合期望。原样不匹配、但 `Normalization::lenient()`
+/// 之后匹配时，返回实际输出中每个"看不见"的问题；否则返回空列表。
+pub fn diagnose(actual: &str, matches: impl Fn(&str) -> bool) -> Vec<String> {
+    if matches(actual) || !matches(&Normalization::lenient().apply(actual)) {
+        return Vec::new();
+    }
+
+    let mut issues = Vec::new();
+    if actual.ends_with('\r') {
+        issues.push("your output has a trailing \\r (Windows line ending \"\\r\\n\"); print \"\\n\" only".to_string());
+    } else if actual.contains('\r') {
+        issues.push("your output contains a \\r character".to_string());
+    }
+
+    let without_cr = actual.replace('\r', "");
+    if without_cr.trim_end() != without_cr {
+        issues.push("your output has trailing whitespace".to_string());
+    }
+    if without_cr.trim_start() != without_cr {
+        issues.push("your output has leading whitespace".to_string());
+    }
+
+    for word in without_cr.split_whitespace() {
+        if let Some(keyword) = keyword(word).filter(|k| *k != word) {
+            issues.push(format!("keywords are case-sensitive: print '{}', not '{}'", keyword, word));
+        }
+    }
+    issues
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试各种规范化策略
+    #[test]
+    fn test_normalization_policies() {
+        assert_eq!(Normalization::default().apply("OK\r"), "OK");
+        assert_eq!(Normalization::default().apply("OK "), "OK ");
+        assert_eq!(Normalization::default().apply("a\rb"), "a\rb");
+        assert_eq!(Normalization::default().apply("a\rb\r"), "a\rb");
+        assert_eq!(Normalization::strict().apply("OK\r"), "OK\r");
+        assert_eq!(Normalization::lenient().apply("  null\r"), "NULL");
+        assert_eq!(Normalization::lenient().apply("error unknown command"), "ERROR unknown command");
+        assert_eq!(Normalization::lenient().apply("nullable"), "nullable");
+
+        let trim_only = Normalization::strict().with_trim(true);
+        assert_eq!(trim_only.apply(" Ok "), "Ok");
+
+        let response = Response::Lines(vec!["a\r".into(), "b\r".into()]);
+        assert_eq!(Normalization::default().apply_response(&response).text(), "a\nb");
+    }
+
+    /// 测试规范化能消除的差异会被明确指出
+    #[test]
+    fn test_diagnose() {
+        let is_ok = |line: &str| line == "OK";
+        assert_eq!(diagnose("OK\r", is_ok), vec![
+            "your output has a trailing \\r (Windows line ending \"\\r\\n\"); print \"\\n\" only".to_string()
+        ]);
+        assert_eq!(diagnose("OK  ", is_ok), vec!["your output has trailing whitespace".to_string()]);
+        assert_eq!(diagnose("ok", is_ok), vec!["keywords are case-sensitive: print 'OK', not 'ok'".to_string()]);
+
+        // 原样匹配或规范化后仍不匹配时不做说明
+        assert!(diagnose("OK", is_ok).is_empty());
+        assert!(diagnose("NULL\r", is_ok).is_empty());
+    }
+}
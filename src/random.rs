@@ -0,0 +1,435 @@
+use std::env;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tester_utils::{TestCaseHarness, TesterError};
+use crate::model::EvictionPolicy;
+use crate::test_case::CacheTestCase;
+
+/// 指定随机种子（复现失败的随机测试）；未设置时每次运行使用新的种子
+pub const SEED_ENV: &str = "SYSTEMQUEST_RANDOM_SEED";
+
+/// 默认的操作数量（不含 INIT）
+const DEFAULT_OPERATIONS: usize = 60;
+
+/// 一次测试中 SLEEP 的总时长上限（秒）
+const MAX_TOTAL_SLEEP: f64 = 3.0;
+
+/// 键和值使用的字符
+const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// 简单的 xorshift64* 伪随机数生成器（可复现，无需额外依赖）
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    pub(crate) fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound.max(1)
+    }
+
+    /// [lo, hi] 之间的整数
+    fn between(&mut self, lo: usize, hi: usize) -> usize {
+        lo + self.below((hi - lo + 1) as u64) as usize
+    }
+
+    /// 以 percent% 的概率返回 true
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    /// 长度在 [min_len, max_len] 之间的随机字母数字串
+    fn word(&mut self, min_len: usize, max_len: usize) -> String {
+        let len = self.between(min_len, max_len);
+        (0..len).map(|_| ALPHABET[self.below(ALPHABET.len() as u64) as usize] as char).collect()
+    }
+}
+
+/// StageProfile - 某个 Stage 允许使用的命令
+///
+/// Stage 是累积的: 后面的 Stage 可以使用前面所有 Stage 的命令。
+/// CONCURRENT 不会生成: 它的结果只能粗略地建模，由 Stage 5 的手写测试覆盖。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageProfile {
+    pub stage: u32,
+    pub policy: EvictionPolicy,
+
+    /// 是否会触发淘汰（Stage 1 的程序还没有实现淘汰，键空间不超过容量）
+    pub eviction: bool,
+
+    /// PUT 的 TTL 参数和 SLEEP（Stage 6 起）
+    pub ttl: bool,
+
+    /// STATS（Stage 7 起）
+    pub stats: bool,
+}
+
+impl StageProfile {
+    pub fn for_stage(stage: u32) -> Self {
+        Self {
+            stage,
+            policy: EvictionPolicy::for_stage(stage),
+            eviction: stage >= 2,
+            ttl: stage >= 6,
+            stats: stage >= 7,
+        }
+    }
+}
+
+/// RandomWorkload - 按种子生成的随机命令序列
+///
+/// 容量、键空间大小（小于、等于或大于容量）、各命令的比例、键和值都由种子决定，
+/// 期望响应由参考模型（`model::CacheModel`）计算。同一个种子总是生成同一个序列，
+/// 失败时输出种子（设置 `SYSTEMQUEST_RANDOM_SEED` 即可复现）和缩减后的最短失败序列。
+///
+/// # 示例
+/// ```rust
+/// # use lru_cache_tester::random::RandomWorkload;
+/// let workload = RandomWorkload::new(3, 42).with_operations(20);
+/// assert_eq!(workload.commands(), RandomWorkload::new(3, 42).with_operations(20).commands());
+/// assert!(workload.commands()[0].starts_with("INIT "));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RandomWorkload {
+    pub profile: StageProfile,
+    pub seed: u64,
+    pub operations: usize,
+}
+
+/// 生成时选定的参数
+struct Shape {
+    capacity: usize,
+    keys: Vec<String>,
+    put_weight: u64,
+    get_weight: u64,
+    size_weight: u64,
+    stats_weight: u64,
+}
+
+impl RandomWorkload {
+    pub fn new(stage: u32, seed: u64) -> Self {
+        Self {
+            profile: StageProfile::for_stage(stage),
+            seed,
+            operations: DEFAULT_OPERATIONS,
+        }
+    }
+
+    /// 设置操作数量（不含 INIT）
+    pub fn with_operations(mut self, operations: usize) -> Self {
+        self.operations = operations;
+        self
+    }
+
+    fn shape(&self, rng: &mut XorShift) -> Shape {
+        let capacity = rng.between(1, 8);
+        let key_count = if !self.profile.eviction {
+            rng.between(1, capacity)
+        } else {
+            match rng.below(3) {
+                0 => capacity,
+                1 => rng.between(capacity + 1, capacity * 2 + 1),
+                _ => rng.between(1, capacity),
+            }
+        };
+
+        let mut keys: Vec<String> = Vec::new();
+        while keys.len() < key_count {
+            let key = rng.word(1, 6);
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        Shape {
+            capacity,
+            keys,
+            put_weight: rng.between(25, 60) as u64,
+            get_weight: rng.between(25, 60) as u64,
+            size_weight: rng.between(3, 15) as u64,
+            stats_weight: if self.profile.stats { rng.between(3, 10) as u64 } else { 0 },
+        }
+    }
+
+    /// 生成命令序列（第一条总是 INIT）
+    pub fn commands(&self) -> Vec<String> {
+        let mut rng = XorShift::new(self.seed);
+        let shape = self.shape(&mut rng);
+        let total = shape.put_weight + shape.get_weight + shape.size_weight + shape.stats_weight;
+
+        let mut commands = vec![format!("INIT {}", shape.capacity)];
+        let mut slept = 0.0;
+        for _ in 0..self.operations {
+            // 少量的 SLEEP（比较慢）
+            if self.profile.ttl && slept < MAX_TOTAL_SLEEP && rng.chance(4) {
+                let seconds = if rng.chance(50) { 0.5 } else { 1.5 };
+                slept += seconds;
+                commands.push(format!("SLEEP {}", seconds));
+                continue;
+            }
+
+            let key = &shape.keys[rng.below(shape.keys.len() as u64) as usize];
+            let roll = rng.below(total);
+            let command = if roll < shape.put_weight {
+                let value = rng.word(1, 8);
+                if self.profile.ttl && rng.chance(30) {
+                    format!("PUT {} {} {}", key, value, rng.between(1, 3))
+                } else {
+                    format!("PUT {} {}", key, value)
+                }
+            } else if roll < shape.put_weight + shape.get_weight {
+                format!("GET {}", key)
+            } else if roll < total - shape.stats_weight {
+                "SIZE".to_string()
+            } else {
+                "STATS".to_string()
+            };
+            commands.push(command);
+        }
+        commands
+    }
+
+    /// 生成测试用例（期望响应由参考模型计算）
+    pub fn test_case(&self) -> Result<CacheTestCase, TesterError> {
+        let commands = self.commands();
+        let capacity = commands[0].trim_start_matches("INIT ").to_string();
+        let description = format!(
+            "Testing a random workload (seed {}, capacity {}, {} operations)",
+            self.seed,
+            capacity,
+            commands.len() - 1
+        );
+        let test_case = CacheTestCase::from_model(description, self.profile.policy, commands)?
+            .with_all_mismatches()
+            .with_shrinking(self.profile.policy);
+        // SLEEP 由测试器等待（SLEEP 本身由单独的测试检查）
+        Ok(if self.profile.ttl { test_case.with_tester_delays() } else { test_case })
+    }
+}
+
+/// 读取 SYSTEMQUEST_RANDOM_SEED；未设置时生成新的种子
+pub fn seed_from_env() -> Result<u64, TesterError> {
+    match env::var(SEED_ENV) {
+        Ok(seed) => seed.trim().parse().map_err(|_| {
+            TesterError::Configuration(format!("Invalid {} '{}': expected an unsigned integer", SEED_ENV, seed))
+        }),
+        Err(_) => {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos() as u64)
+                .unwrap_or_default();
+            Ok(XorShift::new(nanos ^ ((process::id() as u64) << 32)).next() % 1_000_000_000)
+        }
+    }
+}
+
+/// 运行某个 Stage 的随机负载测试（各 Stage 的 `test_random_workload`）
+///
+/// 容量、键空间和各命令的比例随机，只使用该 Stage 及之前的命令（CONCURRENT 除外，见 `StageProfile`），
+/// 期望由参考模型计算。种子在开始时记录，失败时连同缩减后的最短失败序列一起输出；
+/// 设置 SYSTEMQUEST_RANDOM_SEED 可以复现同一个序列。
+pub fn run_random_workload(harness: &mut TestCaseHarness, stage: u32) -> Result<(), TesterError> {
+    let seed = seed_from_env()?;
+    harness.logger.infof(&format!("Random seed: {} (set {}={} to reproduce)", seed, SEED_ENV, seed), &[]);
+
+    RandomWorkload::new(stage, seed)
+        .test_case()?
+        .run(harness)
+        .map_err(|err| match err {
+            TesterError::User(message) => {
+                TesterError::User(format!(
+                    "{}\n\nRandom seed: {} (rerun with {}={} to get exactly the same commands)",
+                    message, seed, SEED_ENV, seed
+                ).into())
+            }
+            other => other,
+        })
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::CacheModel;
+
+    /// 测试随机序列可复现
+    #[test]
+    fn test_xorshift_is_deterministic() {
+        let mut a = XorShift::new(42);
+        let mut b = XorShift::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.below(100), b.below(100));
+        }
+    }
+
+    /// 测试同一个种子生成同一个序列，不同种子生成不同序列
+    #[test]
+    fn test_workload_is_reproducible() {
+        let workload = RandomWorkload::new(7, 1234);
+        assert_eq!(workload.commands(), RandomWorkload::new(7, 1234).commands());
+        assert_ne!(workload.commands(), RandomWorkload::new(7, 1235).commands());
+        assert_eq!(workload.commands().len(), DEFAULT_OPERATIONS + 1);
+    }
+
+    /// 测试每个 Stage 只使用允许的命令，且模型能计算所有期望
+    #[test]
+    fn test_workload_respects_stage_profile() {
+        for stage in 1..=7 {
+            for seed in 0..50 {
+                let workload = RandomWorkload::new(stage, seed);
+                let commands = workload.commands();
+                let profile = workload.profile;
+
+                for command in &commands[1..] {
+                    let parts: Vec<&str> = command.split_whitespace().collect();
+                    let allowed = match parts[0] {
+                        "PUT" => parts.len() == 3 || profile.ttl,
+                        "GET" | "SIZE" => true,
+                        "SLEEP" => profile.ttl,
+                        "STATS" => profile.stats,
+                        _ => false,
+                    };
+                    assert!(allowed, "stage {} seed {}: '{}'", stage, seed, command);
+                }
+
+                // Stage 1 没有淘汰: 键的数量不超过容量
+                if !profile.eviction {
+                    let capacity: usize = commands[0][5..].parse().unwrap();
+                    let mut keys: Vec<&str> = commands[1..].iter().filter_map(|c| c.split(' ').nth(1)).collect();
+                    keys.sort();
+                    keys.dedup();
+                    assert!(keys.len() <= capacity);
+                }
+
+                assert!(workload.test_case().is_ok(), "stage {} seed {}", stage, seed);
+            }
+        }
+    }
+
+    /// 按协议正确实现的缓存（惰性删除过期条目），用来检查生成的期望
+    struct CorrectCache {
+        capacity: usize,
+        policy: EvictionPolicy,
+        /// (键, 值, 写入时间, TTL)，按淘汰顺序排列
+        entries: Vec<(String, String, f64, Option<f64>)>,
+        now: f64,
+        hits: u64,
+        misses: u64,
+        evictions: u64,
+        expirations: u64,
+    }
+
+    impl CorrectCache {
+        fn new(capacity: usize, policy: EvictionPolicy) -> Self {
+            Self { capacity, policy, entries: Vec::new(), now: 0.0, hits: 0, misses: 0, evictions: 0, expirations: 0 }
+        }
+
+        /// 执行一条命令；SLEEP 实际经过的时间多出 overshoot 秒
+        fn execute(&mut self, command: &str, overshoot: f64) -> String {
+            let parts: Vec<&str> = command.split_whitespace().collect();
+            match parts[0] {
+                "PUT" => {
+                    let ttl = parts.get(3).map(|ttl| ttl.parse().unwrap());
+                    let entry = (parts[1].to_string(), parts[2].to_string(), self.now, ttl);
+                    if let Some(i) = self.entries.iter().position(|e| e.0 == parts[1]) {
+                        if self.policy == EvictionPolicy::Lru {
+                            self.entries.remove(i);
+                            self.entries.push(entry);
+                        } else {
+                            self.entries[i] = entry;
+                        }
+                    } else {
+                        if self.entries.len() == self.capacity {
+                            self.entries.remove(0);
+                            self.evictions += 1;
+                        }
+                        self.entries.push(entry);
+                    }
+                    "OK".to_string()
+                }
+                "GET" => {
+                    let Some(i) = self.entries.iter().position(|e| e.0 == parts[1]) else {
+                        self.misses += 1;
+                        return "NULL".to_string();
+                    };
+                    if self.entries[i].3.is_some_and(|ttl| self.now - self.entries[i].2 >= ttl) {
+                        self.entries.remove(i);
+                        self.misses += 1;
+                        self.expirations += 1;
+                        return "NULL".to_string();
+                    }
+                    self.hits += 1;
+                    let value = self.entries[i].1.clone();
+                    if self.policy == EvictionPolicy::Lru {
+                        let entry = self.entries.remove(i);
+                        self.entries.push(entry);
+                    }
+                    value
+                }
+                "SIZE" => self.entries.len().to_string(),
+                "SLEEP" => {
+                    self.now += parts[1].parse::<f64>().unwrap() + overshoot;
+                    "OK".to_string()
+                }
+                "STATS" => {
+                    let total = self.hits + self.misses;
+                    let hit_rate = if total == 0 { 0.0 } else { self.hits as f64 * 100.0 / total as f64 };
+                    format!(
+                        "hits:{} misses:{} hit_rate:{:.2} evictions:{} expirations:{} size:{} capacity:{}",
+                        self.hits, self.misses, hit_rate, self.evictions, self.expirations, self.entries.len(), self.capacity
+                    )
+                }
+                other => panic!("unexpected command '{}'", other),
+            }
+        }
+    }
+
+    /// 测试生成的期望接受正确实现的输出（SLEEP 实际多睡 0 到 0.3 秒）
+    #[test]
+    fn test_workload_accepts_correct_implementation() {
+        for stage in 1..=7 {
+            for seed in 0..300 {
+                let workload = RandomWorkload::new(stage, seed);
+                let commands = workload.commands();
+                let expected = CacheModel::expectations(workload.profile.policy, &commands).unwrap();
+
+                let capacity = commands[0][5..].parse().unwrap();
+                let mut cache = CorrectCache::new(capacity, workload.profile.policy);
+                let mut rng = XorShift::new(seed);
+                for (i, command) in commands.iter().enumerate().skip(1) {
+                    let overshoot = rng.below(300) as f64 / 1000.0;
+                    let response = cache.execute(command, overshoot);
+                    assert!(
+                        expected[i].matches(&response),
+                        "stage {} seed {} #{} {}: expected {}, got '{}'",
+                        stage, seed, i + 1, command, expected[i], response
+                    );
+                }
+            }
+        }
+    }
+
+    /// 测试键空间与容量的各种关系都会出现
+    #[test]
+    fn test_workload_varies_shape() {
+        let mut relations = [false; 3];
+        for seed in 0..100 {
+            let workload = RandomWorkload::new(3, seed);
+            let mut rng = XorShift::new(seed);
+            let shape = workload.shape(&mut rng);
+            relations[(shape.keys.len().cmp(&shape.capacity) as i8 + 1) as usize] = true;
+        }
+        assert!(relations.iter().all(|seen| *seen));
+    }
+}
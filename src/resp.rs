@@ -0,0 +1,333 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// 单个 bulk string / array 允许的最大长度（防止错误的长度头导致巨量分配）
+const MAX_LENGTH: i64 = 64 * 1024 * 1024;
+
+/// RespValue - RESP2 (Redis Serialization Protocol) 值
+///
+/// 用于 RESP 传输方式: 命令编码为 bulk string 数组发送，
+/// 回复解码后再规范化为 ExactMatchAssertion 期望的字符串形式。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RespValue {
+    /// `+OK\r\n`
+    SimpleString(String),
+
+    /// `-ERR message\r\n`
+    Error(String),
+
+    /// `:42\r\n`
+    Integer(i64),
+
+    /// `$5\r\nhello\r\n`（二进制安全）
+    BulkString(Vec<u8>),
+
+    /// `$-1\r\n`
+    NullBulkString,
+
+    /// `*2\r\n...`
+    Array(Vec<RespValue>),
+
+    /// `*-1\r\n`
+    NullArray,
+}
+
+impl RespValue {
+    /// 创建 bulk string
+    pub fn bulk(bytes: impl Into<Vec<u8>>) -> Self {
+        Self::BulkString(bytes.into())
+    }
+
+    /// 创建命令: 每个参数都是 bulk string 的数组
+    pub fn command<A: AsRef<[u8]>>(args: &[A]) -> Self {
+        Self::Array(args.iter().map(|arg| Self::bulk(arg.as_ref())).collect())
+    }
+
+    /// 把一行文本命令（例如 "PUT name Alice"）按空白拆分为 RESP 命令
+    pub fn from_command_line(line: &str) -> Self {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        Self::command(&args)
+    }
+
+    /// 编码为 RESP 字节
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::SimpleString(s) => out.extend_from_slice(format!("+{}\r\n", s).as_bytes()),
+            Self::Error(e) => out.extend_from_slice(format!("-{}\r\n", e).as_bytes()),
+            Self::Integer(n) => out.extend_from_slice(format!(":{}\r\n", n).as_bytes()),
+            Self::BulkString(bytes) => {
+                out.extend_from_slice(format!("${}\r\n", bytes.len()).as_bytes());
+                out.extend_from_slice(bytes);
+                out.extend_from_slice(b"\r\n");
+            }
+            Self::NullBulkString => out.extend_from_slice(b"$-1\r\n"),
+            Self::Array(items) => {
+                out.extend_from_slice(format!("*{}\r\n", items.len()).as_bytes());
+                for item in items {
+                    item.encode_into(out);
+                }
+            }
+            Self::NullArray => out.extend_from_slice(b"*-1\r\n"),
+        }
+    }
+
+    /// 从流中读取一个完整的 RESP 值
+    ///
+    /// 连接在值开始之前关闭时返回 `UnexpectedEof`；格式错误返回 `InvalidData`。
+    pub fn read<R: BufRead>(reader: &mut R) -> io::Result<Self> {
+        let line = read_crlf_line(reader)?;
+        let (kind, rest) = match line.split_first() {
+            Some((kind, rest)) => (*kind, rest),
+            None => return Err(invalid("empty line where a RESP type byte was expected")),
+        };
+        let text = String::from_utf8_lossy(rest).into_owned();
+
+        match kind {
+            b'+' => Ok(Self::SimpleString(text)),
+            b'-' => Ok(Self::Error(text)),
+            b':' => Ok(Self::Integer(parse_integer(&text)?)),
+            b'$' => match parse_length(&text)? {
+                None => Ok(Self::NullBulkString),
+                Some(len) => {
+                    let mut bytes = vec![0u8; len + 2];
+                    reader.read_exact(&mut bytes)?;
+                    if !bytes.ends_with(b"\r\n") {
+                        return Err(invalid(&format!("bulk string of length {} is not followed by \\r\\n", len)));
+                    }
+                    bytes.truncate(len);
+                    Ok(Self::BulkString(bytes))
+                }
+            },
+            b'*' => match parse_length(&text)? {
+                None => Ok(Self::NullArray),
+                Some(len) => (0..len).map(|_| Self::read(reader)).collect::<io::Result<_>>().map(Self::Array),
+            },
+            other => Err(invalid(&format!("unknown RESP type byte {:?}", other as char))),
+        }
+    }
+
+    /// 规范化为文本协议的响应行（与 stdin/TCP 模式的期望响应一致）
+    ///
+    /// - `+OK` → `OK`，bulk string → 内容，`$-1` / `*-1` → `NULL`
+    /// - `:3` → `3`
+    /// - `-ERR msg` → `(error) ERR msg`
+    /// - 数组 → 计数头 `*<n>` 加每个元素一行（与 `Framing::Counted` 的多行响应一致）
+    pub fn to_response_lines(&self) -> Vec<String> {
+        let line = match self {
+            Self::SimpleString(s) => s.clone(),
+            Self::Error(e) => format!("(error) {}", e),
+            Self::Integer(n) => n.to_string(),
+            Self::BulkString(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+            Self::NullBulkString | Self::NullArray => "NULL".to_string(),
+            Self::Array(items) => {
+                return std::iter::once(format!("*{}", items.len()))
+                    .chain(items.iter().flat_map(Self::to_response_lines))
+                    .collect();
+            }
+        };
+        vec![line]
+    }
+
+    /// `to_response_lines` 以 `\n` 连接成一条响应
+    pub fn to_response_line(&self) -> String {
+        self.to_response_lines().join("\n")
+    }
+
+    /// 用于日志的简短描述，例如 `+OK`、`$5 "hello"`、`$-1`
+    pub fn describe(&self) -> String {
+        match self {
+            Self::SimpleString(s) => format!("+{}", s),
+            Self::Error(e) => format!("-{}", e),
+            Self::Integer(n) => format!(":{}", n),
+            Self::BulkString(bytes) => format!("${} {:?}", bytes.len(), String::from_utf8_lossy(bytes)),
+            Self::NullBulkString => "$-1".to_string(),
+            Self::Array(items) => format!(
+                "*{} [{}]",
+                items.len(),
+                items.iter().map(|item| item.describe()).collect::<Vec<_>>().join(", ")
+            ),
+            Self::NullArray => "*-1".to_string(),
+        }
+    }
+}
+
+/// RespCommandWriter - 把按行写入的文本命令编码为 RESP 数组
+///
+/// Runner 总是按行写命令（`INIT 10\n`）；RESP 传输方式用它包装 TCP 连接，
+/// 这样所有 Stage 都可以不加修改地通过 RESP 运行。
+pub struct RespCommandWriter<W: Write> {
+    inner: W,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> RespCommandWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for RespCommandWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            self.inner.write_all(&RespValue::from_command_line(line).encode())?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// RespConnection - 直接收发 RESP 值的客户端（用于 RESP 协议一致性测试）
+pub struct RespConnection {
+    writer: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl RespConnection {
+    /// 包装已建立的连接，读取超时为 timeout
+    pub fn new(stream: TcpStream, timeout: Duration) -> io::Result<Self> {
+        stream.set_read_timeout(Some(timeout))?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    /// 发送一个 RESP 值
+    pub fn send(&mut self, value: &RespValue) -> io::Result<()> {
+        self.send_raw(&value.encode())
+    }
+
+    /// 发送原始字节（用于测试分片、粘包等情况）
+    pub fn send_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.writer.flush()
+    }
+
+    /// 读取一个 RESP 回复
+    pub fn receive(&mut self) -> io::Result<RespValue> {
+        RespValue::read(&mut self.reader)
+    }
+}
+
+/// 读取以 \r\n 结尾的一行（不含 \r\n）
+fn read_crlf_line<R: BufRead>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Err(io::Error::new(ErrorKind::UnexpectedEof, "connection closed"));
+    }
+    if !line.ends_with(b"\r\n") {
+        return Err(invalid(&format!(
+            "line {:?} is not terminated by \\r\\n",
+            String::from_utf8_lossy(&line)
+        )));
+    }
+    line.truncate(line.len() - 2);
+    Ok(line)
+}
+
+fn parse_integer(text: &str) -> io::Result<i64> {
+    text.parse()
+        .map_err(|_| invalid(&format!("invalid integer {:?}", text)))
+}
+
+/// 解析 bulk string / array 的长度，-1 表示 null
+fn parse_length(text: &str) -> io::Result<Option<usize>> {
+    match parse_integer(text)? {
+        -1 => Ok(None),
+        len if (0..=MAX_LENGTH).contains(&len) => Ok(Some(len as usize)),
+        len => Err(invalid(&format!("invalid length {}", len))),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn decode(bytes: &[u8]) -> io::Result<RespValue> {
+        RespValue::read(&mut Cursor::new(bytes.to_vec()))
+    }
+
+    /// 测试命令行编码为 bulk string 数组
+    #[test]
+    fn test_encode_command_line() {
+        assert_eq!(
+            RespValue::from_command_line("PUT name Alice").encode(),
+            b"*3\r\n$3\r\nPUT\r\n$4\r\nname\r\n$5\r\nAlice\r\n".to_vec()
+        );
+    }
+
+    /// 测试各种回复类型的解码与规范化
+    #[test]
+    fn test_decode_replies() {
+        assert_eq!(decode(b"+OK\r\n").unwrap().to_response_line(), "OK");
+        assert_eq!(decode(b"$5\r\nAlice\r\n").unwrap().to_response_line(), "Alice");
+        assert_eq!(decode(b"$-1\r\n").unwrap().to_response_line(), "NULL");
+        assert_eq!(decode(b":3\r\n").unwrap().to_response_line(), "3");
+        assert_eq!(decode(b"-ERR unknown command\r\n").unwrap().to_response_line(), "(error) ERR unknown command");
+        assert_eq!(
+            decode(b"*2\r\n$1\r\na\r\n$1\r\nb\r\n").unwrap(),
+            RespValue::Array(vec![RespValue::bulk("a"), RespValue::bulk("b")])
+        );
+
+        let array = decode(b"*2\r\n$1\r\na\r\n$-1\r\n").unwrap();
+        assert_eq!(array.to_response_lines(), ["*2", "a", "NULL"]);
+        assert_eq!(array.to_response_line(), "*2\na\nNULL");
+    }
+
+    /// 测试 bulk string 二进制安全
+    #[test]
+    fn test_bulk_string_is_binary_safe() {
+        let value = RespValue::bulk(b"a\r\nb\x00\xff".to_vec());
+        assert_eq!(decode(&value.encode()).unwrap(), value);
+    }
+
+    /// 测试格式错误的回复
+    #[test]
+    fn test_decode_invalid() {
+        assert_eq!(decode(b"OK\r\n").unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(decode(b"+OK\n").unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(decode(b"$3\r\nabcd\r\n").unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(decode(b"").unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    /// 测试按行写入时的编码（跨多次 write 的行也能正确拼接）
+    #[test]
+    fn test_command_writer() {
+        let mut writer = RespCommandWriter::new(Vec::new());
+        writer.write_all(b"INIT 10\nGET").unwrap();
+        writer.write_all(b" a\n").unwrap();
+
+        assert_eq!(
+            writer.inner,
+            b"*2\r\n$4\r\nINIT\r\n$2\r\n10\r\n*2\r\n$3\r\nGET\r\n$1\r\na\r\n".to_vec()
+        );
+    }
+}
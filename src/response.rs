@@ -0,0 +1,227 @@
+use std::fmt;
+
+/// 单条命令的响应
+///
+/// 大多数命令恰好返回一行；返回集合的命令（例如按访问顺序列出键、导出状态、
+/// 批量 GET）返回多行，由 `Framing` 决定如何从输出中切分。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// 单行响应
+    Line(String),
+
+    /// 多行响应（不含计数头或终止行）
+    Lines(Vec<String>),
+}
+
+impl Response {
+    /// 响应的各行
+    pub fn lines(&self) -> Vec<&str> {
+        match self {
+            Self::Line(line) => vec![line.as_str()],
+            Self::Lines(lines) => lines.iter().map(|s| s.as_str()).collect(),
+        }
+    }
+
+    /// 用于比较的文本形式: 单行原样返回，多行用 "\n" 连接
+    ///
+    /// CacheTestCase 中多行响应的期望值因此可以写成 `"c\nb\na"`。
+    pub fn text(&self) -> String {
+        match self {
+            Self::Line(line) => line.clone(),
+            Self::Lines(lines) => lines.join("\n"),
+        }
+    }
+
+    pub fn is_multi_line(&self) -> bool {
+        matches!(self, Self::Lines(_))
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Line(line) => write!(f, "{}", line),
+            Self::Lines(lines) => write!(f, "[{}]", lines.join(", ")),
+        }
+    }
+}
+
+/// 按文本形式与字符串比较（多行响应与 "\n" 连接的期望值比较）
+impl PartialEq<&str> for Response {
+    fn eq(&self, other: &&str) -> bool {
+        self.text() == *other
+    }
+}
+
+impl From<&str> for Response {
+    fn from(line: &str) -> Self {
+        Self::Line(line.to_string())
+    }
+}
+
+/// 响应的帧格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// 恰好一行（默认）
+    #[default]
+    Line,
+
+    /// 计数头 `*<n>` 后跟 n 行；第一行不是计数头时（例如 `ERROR ...`）按单行响应处理
+    Counted,
+
+    /// 若干行后跟终止行（终止行本身不属于响应）
+    Terminated(&'static str),
+}
+
+/// 按命令名选择帧格式
+///
+/// 命令名取命令行的第一个单词（不区分大小写）；未配置的命令使用 `Framing::Line`。
+///
+/// # 示例
+/// ```rust
+/// # use lru_cache_tester::response::{Framing, FramingRules};
+/// let rules = FramingRules::new()
+///     .with("KEYS", Framing::Counted)
+///     .with("DUMP", Framing::Terminated("END"));
+/// assert_eq!(rules.framing_for("keys"), Framing::Counted);
+/// assert_eq!(rules.framing_for("GET a"), Framing::Line);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FramingRules {
+    rules: Vec<(String, Framing)>,
+}
+
+impl FramingRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为某个命令设置帧格式
+    pub fn with(mut self, command: &str, framing: Framing) -> Self {
+        self.rules.push((command.to_ascii_uppercase(), framing));
+        self
+    }
+
+    /// 查找命令行对应的帧格式
+    pub fn framing_for(&self, command_line: &str) -> Framing {
+        let name = command_line.split_whitespace().next().unwrap_or("").to_ascii_uppercase();
+        self.rules
+            .iter()
+            .find(|(command, _)| *command == name)
+            .map_or(Framing::Line, |(_, framing)| *framing)
+    }
+}
+
+/// ResponseFramer - 逐行组装一条响应
+///
+/// 每次 `push` 一行输出，响应完整时返回 `Some(Response)`。
+#[derive(Debug, Clone)]
+pub(crate) struct ResponseFramer {
+    framing: Framing,
+    expected: Option<usize>,
+    lines: Vec<String>,
+}
+
+impl ResponseFramer {
+    pub(crate) fn new(framing: Framing) -> Self {
+        Self {
+            framing,
+            expected: None,
+            lines: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, line: String) -> Option<Response> {
+        // 计数头和终止行忽略 "\r"；数据行原样保留，交给 Normalization 处理
+        let bare = line.strip_suffix('\r').unwrap_or(&line);
+        match self.framing {
+            Framing::Line => Some(Response::Line(line)),
+            Framing::Counted => match self.expected {
+                None => match parse_count_header(bare) {
+                    Some(0) => Some(Response::Lines(Vec::new())),
+                    Some(count) => {
+                        self.expected = Some(count);
+                        None
+                    }
+                    None => Some(Response::Line(line)),
+                },
+                Some(count) => {
+                    self.lines.push(line);
+                    (self.lines.len() == count).then(|| Response::Lines(std::mem::take(&mut self.lines)))
+                }
+            },
+            Framing::Terminated(terminator) => {
+                if bare == terminator {
+                    return Some(Response::Lines(std::mem::take(&mut self.lines)));
+                }
+                self.lines.push(line);
+                None
+            }
+        }
+    }
+
+    /// 响应不完整时（EOF / 超时）已经读到的行，用于诊断信息
+    pub(crate) fn partial(&self) -> Vec<String> {
+        let header = self.expected.map(|count| format!("*{}", count));
+        header.into_iter().chain(self.lines.iter().cloned()).collect()
+    }
+}
+
+/// 解析计数头 `*<n>`
+fn parse_count_header(line: &str) -> Option<usize> {
+    line.strip_prefix('*')?.parse().ok()
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(framing: Framing, lines: &[&str]) -> Option<Response> {
+        let mut framer = ResponseFramer::new(framing);
+        lines.iter().find_map(|line| framer.push(line.to_string()))
+    }
+
+    /// 测试计数头帧格式
+    #[test]
+    fn test_counted_framing() {
+        assert_eq!(
+            frame(Framing::Counted, &["*3", "c", "b", "a"]),
+            Some(Response::Lines(vec!["c".into(), "b".into(), "a".into()]))
+        );
+        assert_eq!(frame(Framing::Counted, &["*0"]), Some(Response::Lines(vec![])));
+        assert_eq!(frame(Framing::Counted, &["ERROR unknown"]), Some(Response::from("ERROR unknown")));
+        assert_eq!(frame(Framing::Counted, &["*2", "a"]), None);
+        assert_eq!(frame(Framing::Counted, &["*1\r", "a\r"]), Some(Response::Lines(vec!["a\r".into()])));
+        assert_eq!(frame(Framing::Terminated("END"), &["a", "END\r"]), Some(Response::Lines(vec!["a".into()])));
+    }
+
+    /// 测试终止行帧格式
+    #[test]
+    fn test_terminated_framing() {
+        let response = frame(Framing::Terminated("END"), &["a=1", "b=2", "END"]).unwrap();
+        assert_eq!(response.text(), "a=1\nb=2");
+        assert!(response.is_multi_line());
+    }
+
+    /// 测试不完整响应的诊断信息
+    #[test]
+    fn test_partial_response() {
+        let mut framer = ResponseFramer::new(Framing::Counted);
+        assert!(framer.push("*3".into()).is_none());
+        assert!(framer.push("a".into()).is_none());
+        assert_eq!(framer.partial(), vec!["*3", "a"]);
+    }
+
+    /// 测试按命令名选择帧格式
+    #[test]
+    fn test_framing_rules() {
+        let rules = FramingRules::new().with("keys", Framing::Counted);
+        assert_eq!(rules.framing_for("KEYS"), Framing::Counted);
+        assert_eq!(rules.framing_for("KEYSPACE"), Framing::Line);
+        assert_eq!(rules.framing_for(""), Framing::Line);
+    }
+}
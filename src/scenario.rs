@@ -0,0 +1,596 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use tester_utils::{Logger, TestCaseHarness, TesterError};
+use crate::assertions::ExpectedLine;
+use crate::helpers::{describe_signal, output_block, InteractiveCommandRunner, ProgramSpec, Timeouts};
+use crate::normalize::Normalization;
+use crate::response::FramingRules;
+use crate::stats::Stats;
+use crate::usage;
+
+/// 发送信号后等待程序退出的最长时间
+const EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 场景中的一步: 发给程序的命令，或测试器侧的动作
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// 发送一条命令并检查响应（命令和精确期望中的 `${name}` 替换为变量的值）
+    Send { command: String, expected: ExpectedLine },
+
+    /// 发送一条命令，把响应存入变量
+    Capture { command: String, variable: String },
+
+    /// 发送 STATS，把其中一个字段存入变量
+    CaptureStat { field: String, variable: String },
+
+    /// 测试器侧等待（程序继续运行，不发送任何命令）
+    Pause(Duration),
+
+    /// 杀掉程序（SIGKILL）并重新启动，模拟崩溃后重启
+    Restart,
+
+    /// 正常结束当前会话（关闭 stdin，检查退出状态）并启动新的会话
+    NewSession,
+
+    /// 向程序发送信号，例如 `libc::SIGTERM`
+    Signal(i32),
+
+    /// 等待程序自行退出（不关闭 stdin），检查退出码
+    ExpectExit(i32),
+
+    /// 检查程序写入的文件（路径相对于程序的工作目录，即 SYSTEMQUEST_REPOSITORY_DIR）
+    AssertFile { path: String, check: FileCheck },
+
+    /// 删除文件（如果存在），保证场景从干净的状态开始
+    RemoveFile(String),
+}
+
+/// 对文件的检查
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileCheck {
+    Exists,
+    Missing,
+    Contains(String),
+}
+
+/// Scenario - 混合命令和测试器侧动作的测试
+///
+/// `CacheTestCase` 只能描述"发送这些行，期望这些行"；场景的步骤之间还可以暂停、
+/// 杀掉并重启程序、开始新的会话、发送信号、检查程序写入的文件，或者把 STATS 的
+/// 字段读入变量供后面的步骤使用。程序以交互模式运行（发一条、读一条）。
+///
+/// # 示例
+/// ```rust,no_run
+/// # use lru_cache_tester::scenario::{FileCheck, Scenario};
+/// # use std::time::Duration;
+/// # use tester_utils::TestCaseHarness;
+/// # fn example(harness: &mut TestCaseHarness) -> Result<(), Box<dyn std::error::Error>> {
+/// Scenario::new("Testing that the cache is saved on SIGTERM")
+///     .remove_file("cache.db")
+///     .send("INIT 5", "OK")
+///     .send("PUT a 1", "OK")
+///     .capture_stat("size", "size_before")
+///     .pause(Duration::from_millis(500))
+///     .signal(libc::SIGTERM)
+///     .expect_exit(0)
+///     .assert_file("cache.db", FileCheck::Exists)
+///     .new_session()
+///     .send("SIZE", "${size_before}")
+///     .with_hint("Save the cache to cache.db when you receive SIGTERM")
+///     .run(harness)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Scenario {
+    /// 测试描述（用于日志）
+    pub description: String,
+
+    /// 按顺序执行的步骤
+    pub steps: Vec<Step>,
+
+    /// 失败时的提示信息（可选）
+    pub hint: Option<String>,
+
+    /// 程序崩溃时的提示信息（可选）
+    pub crash_hint: Option<&'static str>,
+
+    /// 每个会话的超时（per_test 从会话启动时开始计算）
+    pub timeouts: Timeouts,
+
+    /// 多行响应的帧格式
+    pub framing: FramingRules,
+
+    /// 比较前的规范化策略
+    pub normalization: Normalization,
+}
+
+/// 运行中的场景: 当前会话和已捕获的变量
+struct State {
+    program: ProgramSpec,
+    runner: Option<InteractiveCommandRunner>,
+    variables: HashMap<String, String>,
+}
+
+impl Scenario {
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            steps: Vec::new(),
+            hint: None,
+            crash_hint: None,
+            timeouts: Timeouts::default(),
+            framing: FramingRules::new(),
+            normalization: Normalization::default(),
+        }
+    }
+
+    /// 追加一个步骤
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// 发送命令，期望精确的响应
+    pub fn send(self, command: impl Into<String>, expected: impl Into<String>) -> Self {
+        self.send_expecting(command, ExpectedLine::exact(expected))
+    }
+
+    /// 发送命令，响应按指定的期望检查（范围、候选集合等）
+    pub fn send_expecting(self, command: impl Into<String>, expected: ExpectedLine) -> Self {
+        self.step(Step::Send { command: command.into(), expected })
+    }
+
+    /// 发送命令，把响应存入变量（之后用 `${variable}` 引用）
+    pub fn capture(self, command: impl Into<String>, variable: impl Into<String>) -> Self {
+        self.step(Step::Capture { command: command.into(), variable: variable.into() })
+    }
+
+    /// 发送 STATS，把一个字段（例如 "hits"）存入变量
+    pub fn capture_stat(self, field: impl Into<String>, variable: impl Into<String>) -> Self {
+        self.step(Step::CaptureStat { field: field.into(), variable: variable.into() })
+    }
+
+    /// 测试器侧等待
+    pub fn pause(self, duration: Duration) -> Self {
+        self.step(Step::Pause(duration))
+    }
+
+    /// 杀掉程序并重新启动
+    pub fn restart(self) -> Self {
+        self.step(Step::Restart)
+    }
+
+    /// 正常结束当前会话并启动新的会话
+    pub fn new_session(self) -> Self {
+        self.step(Step::NewSession)
+    }
+
+    /// 向程序发送信号
+    pub fn signal(self, signal: i32) -> Self {
+        self.step(Step::Signal(signal))
+    }
+
+    /// 等待程序自行退出，期望退出码为 code
+    pub fn expect_exit(self, code: i32) -> Self {
+        self.step(Step::ExpectExit(code))
+    }
+
+    /// 检查程序写入的文件
+    pub fn assert_file(self, path: impl Into<String>, check: FileCheck) -> Self {
+        self.step(Step::AssertFile { path: path.into(), check })
+    }
+
+    /// 删除文件（如果存在）
+    pub fn remove_file(self, path: impl Into<String>) -> Self {
+        self.step(Step::RemoveFile(path.into()))
+    }
+
+    /// 添加提示信息（用于失败时的教学性提示）
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// 添加崩溃提示
+    pub fn with_crash_hint(mut self, hint: &'static str) -> Self {
+        self.crash_hint = Some(hint);
+        self
+    }
+
+    /// 设置超时
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// 设置多行响应的帧格式
+    pub fn with_framing(mut self, framing: FramingRules) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// 设置规范化策略
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// 对学生程序运行场景
+    pub fn run(&self, harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+        self.run_program(ProgramSpec::from_env()?, &harness.logger)
+    }
+
+    /// 对指定的程序运行场景
+    ///
+    /// 启动程序后依次执行每一步；最后仍在运行的会话正常结束（关闭 stdin，检查退出状态）。
+    pub fn run_program(&self, program: ProgramSpec, logger: &Logger) -> Result<(), TesterError> {
+        logger.infof(&self.description, &[]);
+
+        let result = self.run_steps(program, logger);
+        usage::record_result(&self.description, result.is_ok(), None);
+        result.map_err(|err| match (&err, &self.hint) {
+            (TesterError::User(_), Some(hint)) => TesterError::User(format!("{}\n\nHint: {}", err, hint).into()),
+            _ => err,
+        })
+    }
+
+    fn run_steps(&self, program: ProgramSpec, logger: &Logger) -> Result<(), TesterError> {
+        let mut program = program.with_framing(self.framing.clone());
+        if let Some(hint) = self.crash_hint {
+            program = program.with_crash_hint(hint);
+        }
+        let mut state = State {
+            runner: Some(self.start(&program)?),
+            program,
+            variables: HashMap::new(),
+        };
+
+        for (i, step) in self.steps.iter().enumerate() {
+            self.run_step(i + 1, step, &mut state, logger)?;
+        }
+
+        if let Some(runner) = state.runner.take() {
+            runner.finish()?;
+        }
+        Ok(())
+    }
+
+    fn start(&self, program: &ProgramSpec) -> Result<InteractiveCommandRunner, TesterError> {
+        InteractiveCommandRunner::start(program, self.timeouts)
+    }
+
+    fn run_step(
+        &self,
+        number: usize,
+        step: &Step,
+        state: &mut State,
+        logger: &Logger,
+    ) -> Result<(), TesterError> {
+        match step {
+            Step::Send { command, expected } => {
+                let command = substitute(command, &state.variables)?;
+                let expected = match expected {
+                    ExpectedLine::Exact(line) => ExpectedLine::exact(substitute(line, &state.variables)?),
+                    other => other.clone(),
+                };
+                let actual = self.send_command(number, &command, state)?;
+                if let Err(details) = expected.check(&actual) {
+                    logger.errorf(&format!("𐄂 {} ({})", actual, command), &[]);
+                    let mut message = format!(
+                        "Step #{} ({}): expected '{}', got '{}'",
+                        number,
+                        command,
+                        expected,
+                        actual.replace('\n', "\\n")
+                    );
+                    for detail in details {
+                        message.push_str(&format!("\n  - {}", detail));
+                    }
+                    return Err(self.user_error(message, state));
+                }
+                logger.successf(&format!("✓ {} ({})", actual, command), &[]);
+            }
+            Step::Capture { command, variable } => {
+                let command = substitute(command, &state.variables)?;
+                let actual = self.send_command(number, &command, state)?;
+                logger.infof(&format!("{} = {} ({})", variable, actual, command), &[]);
+                state.variables.insert(variable.clone(), actual);
+            }
+            Step::CaptureStat { field, variable } => {
+                let actual = self.send_command(number, "STATS", state)?;
+                let value = Stats::parse(&actual)
+                    .map_err(|e| format!("invalid STATS response '{}': {}", actual, e))
+                    .and_then(|stats| stats.field(field).ok_or_else(|| format!("unknown STATS field '{}'", field)));
+                let value = match value {
+                    Ok(value) => value,
+                    Err(reason) => return Err(self.user_error(format!("Step #{} (STATS): {}", number, reason), state)),
+                };
+                logger.infof(&format!("{} = {} (STATS {})", variable, value, field), &[]);
+                state.variables.insert(variable.clone(), value);
+            }
+            Step::Pause(duration) => {
+                logger.infof(&format!("Tester: waiting {:.1}s", duration.as_secs_f64()), &[]);
+                thread::sleep(*duration);
+            }
+            Step::Restart => {
+                logger.infof("Tester: killing the program (SIGKILL) and starting it again", &[]);
+                if let Some(runner) = state.runner.take() {
+                    runner.kill();
+                }
+                state.runner = Some(self.start(&state.program)?);
+            }
+            Step::NewSession => {
+                logger.infof("Tester: closing stdin and starting a new session", &[]);
+                if let Some(runner) = state.runner.take() {
+                    runner.finish()?;
+                }
+                state.runner = Some(self.start(&state.program)?);
+            }
+            Step::Signal(signal) => {
+                logger.infof(&format!("Tester: sending signal {}", describe_signal(*signal)), &[]);
+                self.running(number, "send a signal", state)?.signal(*signal)?;
+            }
+            Step::ExpectExit(code) => {
+                self.running(number, "wait for the program to exit", state)?;
+                let runner = state.runner.take().expect("checked above");
+                let output = runner.wait_for_exit(EXIT_TIMEOUT)?;
+                if output.exit_status.and_then(|status| status.code()) != Some(*code) {
+                    return Err(TesterError::User(format!(
+                        "Step #{}: expected the program to exit with code {}, but it exited with {}\n\n{}",
+                        number,
+                        code,
+                        output.describe_exit_status(),
+                        output_block("stderr", &output.stderr)
+                    ).into()));
+                }
+                logger.successf(&format!("✓ Program exited with code {}", code), &[]);
+            }
+            Step::AssertFile { path, check } => {
+                let full_path = self.file_path(&state.program, path);
+                if let Err(reason) = check_file(&full_path, check) {
+                    return Err(self.user_error(format!("Step #{} ({}): {}", number, path, reason), state));
+                }
+                logger.successf(&format!("✓ {} {}", path, describe_check(check)), &[]);
+            }
+            Step::RemoveFile(path) => {
+                let full_path = self.file_path(&state.program, path);
+                if full_path.exists() {
+                    fs::remove_file(&full_path).map_err(|e| {
+                        TesterError::Configuration(format!("Failed to remove {}: {}", full_path.display(), e))
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 发送命令，返回规范化后的响应文本
+    fn send_command(&self, number: usize, command: &str, state: &mut State) -> Result<String, TesterError> {
+        let response = self.running(number, command, state)?.send(command)?;
+        Ok(self.normalization.apply_response(&response).text())
+    }
+
+    /// 当前会话；程序已经退出时返回配置错误（场景缺少 restart / new_session）
+    fn running<'a>(
+        &self,
+        number: usize,
+        action: &str,
+        state: &'a mut State,
+    ) -> Result<&'a mut InteractiveCommandRunner, TesterError> {
+        state.runner.as_mut().ok_or_else(|| {
+            TesterError::Configuration(format!(
+                "Scenario step #{} ({}) needs a running program, but it has exited; \
+                add .restart() or .new_session() first",
+                number, action
+            ))
+        })
+    }
+
+    /// 带当前会话 stderr 的错误
+    fn user_error(&self, message: String, state: &State) -> TesterError {
+        match &state.runner {
+            Some(runner) => TesterError::User(format!("{}\n\n{}", message, output_block("stderr", &runner.stderr())).into()),
+            None => TesterError::User(message.into()),
+        }
+    }
+
+    /// 文件路径相对于程序的工作目录（your_program.sh 所在目录）
+    fn file_path(&self, program: &ProgramSpec, path: &str) -> PathBuf {
+        program.path.parent().unwrap_or(Path::new(".")).join(path)
+    }
+}
+
+/// 把 `${name}` 替换为变量的值；未定义的变量是场景本身的错误
+fn substitute(text: &str, variables: &HashMap<String, String>) -> Result<String, TesterError> {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + end];
+        let value = variables.get(name).ok_or_else(|| {
+            TesterError::Configuration(format!("Scenario uses ${{{}}} before capturing it", name))
+        })?;
+        result.push_str(&rest[..start]);
+        result.push_str(value);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// 检查文件；不满足时返回说明
+fn check_file(path: &Path, check: &FileCheck) -> Result<(), String> {
+    match check {
+        FileCheck::Exists if path.is_file() => Ok(()),
+        FileCheck::Exists => Err("expected the file to exist, but it does not".to_string()),
+        FileCheck::Missing if !path.exists() => Ok(()),
+        FileCheck::Missing => Err("expected the file not to exist, but it does".to_string()),
+        FileCheck::Contains(text) => match fs::read_to_string(path) {
+            Ok(content) if content.contains(text.as_str()) => Ok(()),
+            Ok(content) => Err(format!(
+                "expected the file to contain '{}'\n{}",
+                text,
+                output_block("file", &content)
+            )),
+            Err(e) => Err(format!("expected the file to contain '{}', but it cannot be read: {}", text, e)),
+        },
+    }
+}
+
+fn describe_check(check: &FileCheck) -> String {
+    match check {
+        FileCheck::Exists => "exists".to_string(),
+        FileCheck::Missing => "does not exist".to_string(),
+        FileCheck::Contains(text) => format!("contains '{}'", text),
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::tests::{write_script, Script};
+
+    /// 测试变量替换
+    #[test]
+    fn test_substitute_variables() {
+        let mut variables = HashMap::new();
+        variables.insert("hits".to_string(), "3".to_string());
+        variables.insert("key".to_string(), "a".to_string());
+
+        assert_eq!(substitute("GET ${key}", &variables).unwrap(), "GET a");
+        assert_eq!(substitute("${hits}${hits} ${key}", &variables).unwrap(), "33 a");
+        assert_eq!(substitute("PUT a {b}", &variables).unwrap(), "PUT a {b}");
+        assert!(matches!(substitute("GET ${missing}", &variables), Err(TesterError::Configuration(_))));
+    }
+
+    /// 测试文件检查
+    #[test]
+    fn test_check_file() {
+        let path = std::env::temp_dir().join(format!("scenario-test-{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(check_file(&path, &FileCheck::Missing).is_ok());
+        assert!(check_file(&path, &FileCheck::Exists).is_err());
+
+        fs::write(&path, "a=1\nb=2\n").unwrap();
+        assert!(check_file(&path, &FileCheck::Exists).is_ok());
+        assert!(check_file(&path, &FileCheck::Contains("b=2".to_string())).is_ok());
+        assert!(check_file(&path, &FileCheck::Contains("c=3".to_string())).unwrap_err().contains("c=3"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// 测试构建器按顺序记录步骤
+    #[test]
+    fn test_builder_records_steps() {
+        let scenario = Scenario::new("Restart")
+            .send("INIT 2", "OK")
+            .capture_stat("hits", "before")
+            .restart()
+            .pause(Duration::from_millis(10))
+            .send_expecting("SIZE", ExpectedLine::range(0..=2));
+
+        assert_eq!(scenario.steps.len(), 5);
+        assert!(matches!(&scenario.steps[0], Step::Send { command, .. } if command == "INIT 2"));
+        assert!(matches!(&scenario.steps[1], Step::CaptureStat { field, variable } if field == "hits" && variable == "before"));
+        assert!(matches!(scenario.steps[2], Step::Restart));
+        assert!(matches!(scenario.steps[3], Step::Pause(_)));
+    }
+
+    /// 记录启动次数的脚本: RUNS 返回这是第几次启动，QUIT 以退出码 3 退出，
+    /// SIGTERM 时在脚本所在目录写入 saved 后正常退出
+    fn counting_program(name: &str) -> Script {
+        write_script(name, "dir=$(dirname \"$0\")\n\
+            trap 'echo saved > \"$dir/saved\"; exit 0' TERM\n\
+            n=$(( $(cat \"$dir/count\" 2>/dev/null || echo 0) + 1 )); echo $n > \"$dir/count\"\n\
+            while read line; do\n\
+              case \"$line\" in\n\
+                RUNS) echo $n ;;\n\
+                STATS) echo \"hits:3 misses:1 hit_rate:75.00 evictions:0 expirations:0 size:2 capacity:5\" ;;\n\
+                QUIT) echo BYE; exit 3 ;;\n\
+                ECHO*) echo \"${line#ECHO }\" ;;\n\
+                *) echo OK ;;\n\
+              esac\n\
+            done\n")
+    }
+
+    /// 测试重启和新会话都会重新启动程序，捕获的变量在之后的步骤中替换
+    #[test]
+    fn test_run_restart_and_new_session() {
+        let program = counting_program("scenario-sessions");
+        let result = Scenario::new("Sessions")
+            .send("RUNS", "1")
+            .capture("RUNS", "first")
+            .capture_stat("hits", "hits")
+            .send("ECHO ${hits}", "3")
+            .restart()
+            .send("RUNS", "2")
+            .new_session()
+            .send_expecting("RUNS", ExpectedLine::range(3..=3))
+            .send("ECHO ${first}", "1")
+            .run_program(program.spec.clone(), &Logger::get_quiet_logger(""));
+        assert!(result.is_ok(), "{:?}", result.err());
+    }
+
+    /// 测试信号、等待退出和文件检查
+    #[test]
+    fn test_run_signal_exit_and_files() {
+        let program = counting_program("scenario-signal");
+        // 相对路径以程序所在目录为基准
+        let saved = "saved";
+        let result = Scenario::new("Signal")
+            .remove_file(saved)
+            .assert_file(saved, FileCheck::Missing)
+            .send("PUT a 1", "OK")
+            .pause(Duration::from_millis(50))
+            .signal(libc::SIGTERM)
+            .expect_exit(0)
+            .assert_file(saved, FileCheck::Contains("saved".to_string()))
+            .new_session()
+            .send("QUIT", "BYE")
+            .expect_exit(3)
+            .remove_file(saved)
+            .run_program(program.spec.clone(), &Logger::get_quiet_logger(""));
+        assert!(result.is_ok(), "{:?}", result.err());
+    }
+
+    /// 测试失败的步骤: 错误的响应、错误的退出码和程序退出后的命令
+    #[test]
+    fn test_run_reports_failing_step() {
+        let program = counting_program("scenario-failing");
+        let quiet = Logger::get_quiet_logger("");
+
+        let err = Scenario::new("Wrong response")
+            .send("RUNS", "1")
+            .send("ECHO 2", "3")
+            .with_hint("check ECHO")
+            .run_program(program.spec.clone(), &quiet)
+            .unwrap_err();
+        let message = err.to_string();
+        assert!(matches!(err, TesterError::User(_)));
+        assert!(message.contains("Step #2 (ECHO 2): expected '3', got '2'"), "{}", message);
+        assert!(message.contains("Hint: check ECHO"), "{}", message);
+
+        let err = Scenario::new("Wrong exit code")
+            .send("QUIT", "BYE")
+            .expect_exit(0)
+            .run_program(program.spec.clone(), &quiet)
+            .unwrap_err();
+        assert!(err.to_string().contains("Step #2: expected the program to exit with code 0"), "{}", err);
+
+        let err = Scenario::new("Command after exit")
+            .send("QUIT", "BYE")
+            .expect_exit(3)
+            .send("RUNS", "1")
+            .run_program(program.spec.clone(), &quiet)
+            .unwrap_err();
+        assert!(matches!(err, TesterError::Configuration(_)));
+    }
+}
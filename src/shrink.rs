@@ -0,0 +1,352 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use tester_utils::TesterError;
+use crate::assertions::ExpectedLine;
+use crate::helpers::{CommandRunner, InteractiveCommandRunner, ProgramSpec, Timeouts};
+use crate::model::{CacheModel, EvictionPolicy};
+use crate::normalize::Normalization;
+use crate::response::Response;
+
+/// 缩减时最多运行学生程序的次数
+const DEFAULT_MAX_RUNS: usize = 200;
+
+/// 缩减的总时长上限（超过后返回目前找到的最短序列）
+const DEFAULT_MAX_TIME: Duration = Duration::from_secs(30);
+
+/// Shrinker - 把失败的命令序列缩减到最短
+///
+/// 在 `CommandRunner` 之上做 delta debugging（ddmin）: 反复运行命令序列的子序列，
+/// 每次都用参考模型（`model::CacheModel`）重新计算期望，只要子序列仍然失败就继续缩减。
+/// 第一条 INIT 总是保留。运行次数和总时长都有上限，达到上限时返回目前最短的序列。
+///
+/// # 示例
+/// ```rust,no_run
+/// # use lru_cache_tester::helpers::ProgramSpec;
+/// # use lru_cache_tester::model::EvictionPolicy;
+/// # use lru_cache_tester::shrink::Shrinker;
+/// # use tester_utils::TestCaseHarness;
+/// # fn example(harness: &TestCaseHarness, commands: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+/// let shrinker = Shrinker::new(ProgramSpec::from_env()?, EvictionPolicy::Lru);
+/// if let Some(shrunk) = shrinker.shrink(commands)? {
+///     println!("{}", shrunk);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Shrinker {
+    program: ProgramSpec,
+    policy: EvictionPolicy,
+    timeouts: Timeouts,
+    normalization: Normalization,
+    max_runs: usize,
+    max_time: Duration,
+    tester_delays: bool,
+}
+
+/// 子序列失败的方式
+#[derive(Debug, Clone)]
+pub enum Failure {
+    /// 响应与模型的期望不符（或数量不符）
+    Mismatch {
+        expected: Vec<ExpectedLine>,
+        responses: Vec<Response>,
+    },
+
+    /// 程序崩溃、超时等（错误信息）
+    Error(String),
+}
+
+/// 缩减的结果
+#[derive(Debug, Clone)]
+pub struct Shrunk {
+    /// 原始序列的命令数量
+    pub original_len: usize,
+
+    /// 最短的失败序列
+    pub commands: Vec<String>,
+
+    /// 最短序列的失败方式
+    pub failure: Failure,
+
+    /// 运行学生程序的次数
+    pub runs: usize,
+}
+
+impl Shrinker {
+    pub fn new(program: ProgramSpec, policy: EvictionPolicy) -> Self {
+        Self {
+            program,
+            policy,
+            timeouts: Timeouts::default(),
+            normalization: Normalization::default(),
+            max_runs: DEFAULT_MAX_RUNS,
+            max_time: DEFAULT_MAX_TIME,
+            tester_delays: false,
+        }
+    }
+
+    /// 设置每次运行的超时（与原测试一致）
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// 设置比较前的规范化策略（与原测试一致）
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// SLEEP 由测试器等待（与原测试一致，见 `InteractiveCommandRunner::run_with_tester_delays`）
+    pub fn with_tester_delays(mut self, enabled: bool) -> Self {
+        self.tester_delays = enabled;
+        self
+    }
+
+    /// 设置运行次数和总时长的上限
+    pub fn with_budget(mut self, max_runs: usize, max_time: Duration) -> Self {
+        self.max_runs = max_runs;
+        self.max_time = max_time;
+        self
+    }
+
+    /// 运行一次命令序列；失败时返回失败方式
+    ///
+    /// 模型无法计算期望的序列（例如缺少 INIT）视为不失败。
+    pub fn check(&self, commands: &[String]) -> Result<Option<Failure>, TesterError> {
+        let expected = match CacheModel::expectations(self.policy, commands) {
+            Ok(expected) => expected,
+            Err(_) => return Ok(None),
+        };
+
+        let commands: Vec<&str> = commands.iter().map(|s| s.as_str()).collect();
+        let output = if self.tester_delays {
+            InteractiveCommandRunner::run_with_tester_delays(&self.program, self.timeouts, &commands)
+        } else {
+            CommandRunner::new(self.program.clone()).with_timeouts(self.timeouts).run(&commands)
+        };
+        match output {
+            Ok(output) => {
+                // 测试器侧等待多出的时间计入期望（见 `RunOutput::timed_commands`）
+                let expected = if output.delays.is_empty() {
+                    expected
+                } else {
+                    let commands: Vec<String> = commands.iter().map(|s| s.to_string()).collect();
+                    CacheModel::expectations(self.policy, &output.timed_commands(&commands)).unwrap_or(expected)
+                };
+                let responses: Vec<Response> = output.responses.iter()
+                    .map(|response| self.normalization.apply_response(response))
+                    .collect();
+                let passed = responses.len() == expected.len()
+                    && expected.iter().zip(&responses).all(|(line, response)| line.matches(&response.text()));
+                Ok((!passed).then_some(Failure::Mismatch { expected, responses }))
+            }
+            Err(TesterError::User(message)) => Ok(Some(Failure::Error(message.to_string()))),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// 缩减失败的命令序列
+    ///
+    /// 原序列在模型下不失败时（例如失败来自模型之外的检查）返回 None。
+    pub fn shrink(&self, commands: &[String]) -> Result<Option<Shrunk>, TesterError> {
+        let started = Instant::now();
+        let Some(failure) = self.check(commands)? else {
+            return Ok(None);
+        };
+
+        // INIT 固定在开头，只缩减之后的命令
+        let fixed = usize::from(commands.first().is_some_and(|c| c.split_whitespace().next() == Some("INIT")));
+        let (prefix, rest) = commands.split_at(fixed);
+
+        let mut runs = 1;
+        let mut last_failure = failure;
+        let minimal = ddmin(rest, |candidate| {
+            if runs >= self.max_runs || started.elapsed() >= self.max_time {
+                return Ok(false);
+            }
+            runs += 1;
+            let candidate: Vec<String> = prefix.iter().chain(candidate).cloned().collect();
+            match self.check(&candidate)? {
+                Some(failure) => {
+                    last_failure = failure;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })?;
+
+        // ddmin 最后一次判定失败的子序列就是它返回的序列
+        Ok(Some(Shrunk {
+            original_len: commands.len(),
+            commands: prefix.iter().cloned().chain(minimal).collect(),
+            failure: last_failure,
+            runs,
+        }))
+    }
+}
+
+/// ddmin: 返回 items 的一个仍然失败的子序列，去掉其中任何一块都不再失败
+///
+/// 依次尝试把序列分成 n 块后的每一块和每一块的补集，失败就缩小到它，
+/// 否则把块分得更细（n 翻倍），直到每块只有一个元素。
+pub fn ddmin<T: Clone, E>(
+    items: &[T],
+    mut fails: impl FnMut(&[T]) -> Result<bool, E>,
+) -> Result<Vec<T>, E> {
+    let mut current = items.to_vec();
+    let mut n = 2;
+
+    while current.len() >= 2 {
+        let chunk = current.len().div_ceil(n);
+        let parts: Vec<&[T]> = current.chunks(chunk).collect();
+        let mut reduced: Option<(Vec<T>, usize)> = None;
+
+        for part in &parts {
+            if fails(part)? {
+                reduced = Some((part.to_vec(), 2));
+                break;
+            }
+        }
+        // 只有两块时，补集就是另一块，已经试过了
+        if reduced.is_none() && parts.len() > 2 {
+            for i in 0..parts.len() {
+                let complement: Vec<T> = parts.iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .flat_map(|(_, part)| part.iter().cloned())
+                    .collect();
+                if fails(&complement)? {
+                    reduced = Some((complement, (n - 1).max(2)));
+                    break;
+                }
+            }
+        }
+
+        match reduced {
+            Some((smaller, next_n)) => {
+                current = smaller;
+                n = next_n;
+            }
+            None if n >= current.len() => break,
+            None => n = (n * 2).min(current.len()),
+        }
+    }
+
+    if current.len() == 1 && fails(&[])? {
+        current.clear();
+    }
+    Ok(current)
+}
+
+/// 列出最短序列的每条命令和响应，标出不符合期望的行
+impl fmt::Display for Shrunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Shortest failing sequence ({} of {} commands, found in {} runs):",
+            self.commands.len(),
+            self.original_len,
+            self.runs
+        )?;
+
+        match &self.failure {
+            Failure::Mismatch { expected, responses } => {
+                let width = self.commands.iter().map(|c| c.chars().count()).max().unwrap_or(0);
+                for (i, command) in self.commands.iter().enumerate() {
+                    let expected_line = expected.get(i);
+                    let actual = responses.get(i).map(|response| response.text());
+                    let matched = match (expected_line, &actual) {
+                        (Some(line), Some(actual)) => line.matches(actual),
+                        _ => false,
+                    };
+                    let actual = actual.as_deref().unwrap_or("(missing)").replace('\n', "\\n");
+                    write!(f, "\n{} {:>3}  {:<w$}  → {}", if matched { " " } else { ">" }, i + 1, command, actual, w = width)?;
+                    if !matched {
+                        if let Some(line) = expected_line {
+                            write!(f, " (expected {})", line)?;
+                        }
+                    }
+                }
+                for (i, response) in responses.iter().enumerate().skip(self.commands.len()) {
+                    write!(f, "\n! {:>3}  {:<w$}  → {} (extra)", i + 1, "", response, w = width)?;
+                }
+                Ok(())
+            }
+            Failure::Error(message) => {
+                for (i, command) in self.commands.iter().enumerate() {
+                    write!(f, "\n  {:>3}  {}", i + 1, command)?;
+                }
+                let first_line = message.lines().next().unwrap_or_default();
+                write!(f, "\nThis sequence fails with: {}", first_line)
+            }
+        }
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    /// 测试 ddmin 找到导致失败的最少元素
+    #[test]
+    fn test_ddmin_finds_minimal_subsequence() {
+        let items: Vec<u32> = (0..40).collect();
+        let minimal = ddmin(&items, |candidate| {
+            Ok::<_, Infallible>(candidate.contains(&3) && candidate.contains(&31))
+        }).unwrap();
+        assert_eq!(minimal, vec![3, 31]);
+    }
+
+    /// 测试 ddmin 保持元素的相对顺序
+    #[test]
+    fn test_ddmin_keeps_order() {
+        let items = ["PUT a 1", "PUT b 2", "GET x", "PUT c 3", "GET a", "SIZE"];
+        let minimal = ddmin(&items, |candidate| {
+            let put = candidate.iter().position(|c| *c == "PUT c 3");
+            let get = candidate.iter().position(|c| *c == "GET a");
+            Ok::<_, Infallible>(matches!((put, get), (Some(p), Some(g)) if p < g))
+        }).unwrap();
+        assert_eq!(minimal, vec!["PUT c 3", "GET a"]);
+    }
+
+    /// 测试单个元素和空序列
+    #[test]
+    fn test_ddmin_single_and_empty() {
+        let minimal = ddmin(&[1, 2, 3], |candidate| Ok::<_, Infallible>(candidate.contains(&2))).unwrap();
+        assert_eq!(minimal, vec![2]);
+
+        let minimal = ddmin(&[1, 2, 3], |_| Ok::<_, Infallible>(true)).unwrap();
+        assert!(minimal.is_empty());
+    }
+
+    /// 测试判定函数的错误会直接返回
+    #[test]
+    fn test_ddmin_propagates_errors() {
+        let result = ddmin(&[1, 2, 3, 4], |_| Err("program not found"));
+        assert_eq!(result, Err("program not found"));
+    }
+
+    /// 测试最短序列的输出标出不符合期望的行
+    #[test]
+    fn test_shrunk_display_marks_mismatches() {
+        let shrunk = Shrunk {
+            original_len: 61,
+            commands: vec!["INIT 1".to_string(), "PUT a 1".to_string(), "GET a".to_string()],
+            failure: Failure::Mismatch {
+                expected: vec![ExpectedLine::exact("OK"), ExpectedLine::exact("OK"), ExpectedLine::exact("1")],
+                responses: vec![Response::from("OK"), Response::from("OK"), Response::from("NULL")],
+            },
+            runs: 12,
+        };
+        let text = shrunk.to_string();
+        assert!(text.starts_with("Shortest failing sequence (3 of 61 commands, found in 12 runs):"));
+        assert!(text.contains("    2  PUT a 1  → OK\n"));
+        assert!(text.ends_with(">   3  GET a    → NULL (expected 1)"));
+    }
+}
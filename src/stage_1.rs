@@ -1,5 +1,6 @@
 use tester_utils::{TestCaseHarness, TesterError};
 use crate::test_case::CacheTestCase;
+use crate::random;
 
 /// Stage 1: Basic Cache Operations
 /// 
@@ -9,6 +10,10 @@ use crate::test_case::CacheTestCase;
 /// - GET: 获取键值
 /// - 不存在的键返回 NULL
 pub fn test_basic_cache(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    basic_cache_case().run(harness)
+}
+
+fn basic_cache_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing basic cache operations",
         vec![
@@ -22,13 +27,16 @@ pub fn test_basic_cache(harness: &mut TestCaseHarness) -> Result<(), TesterError
         vec!["OK", "OK", "Alice", "NULL", "OK", "Bob"],
     )
     .with_hint("Basic cache operations: INIT, PUT, GET should work correctly. Non-existent keys should return NULL.")
-    .run(harness)
 }
 
 /// Stage 1: Multiple Keys Test
 /// 
 /// 测试多个键值对的操作
 pub fn test_multiple_keys(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    multiple_keys_case().run(harness)
+}
+
+fn multiple_keys_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing multiple keys operations",
         vec![
@@ -44,13 +52,16 @@ pub fn test_multiple_keys(harness: &mut TestCaseHarness) -> Result<(), TesterErr
         vec!["OK", "OK", "OK", "OK", "value1", "value2", "value3", "NULL"],
     )
     .with_hint("Cache should handle multiple different keys independently.")
-    .run(harness)
 }
 
 /// Stage 1: Key Update Test
 /// 
 /// 测试更新已存在的键值对
 pub fn test_key_update(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    key_update_case().run(harness)
+}
+
+fn key_update_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing key update operations",
         vec![
@@ -65,13 +76,16 @@ pub fn test_key_update(harness: &mut TestCaseHarness) -> Result<(), TesterError>
         vec!["OK", "OK", "Alice", "OK", "Bob", "OK", "Charlie"],
     )
     .with_hint("Updating an existing key should replace its value.")
-    .run(harness)
 }
 
 /// Stage 1: SIZE Command Test
 /// 
 /// 测试 SIZE 命令，验证缓存正确跟踪条目数量
 pub fn test_size_command(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    size_command_case().run(harness)
+}
+
+fn size_command_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing SIZE command",
         vec![
@@ -90,5 +104,33 @@ pub fn test_size_command(harness: &mut TestCaseHarness) -> Result<(), TesterErro
         vec!["OK", "0", "OK", "1", "OK", "OK", "3", "OK", "3", "value2", "3"],
     )
     .with_hint("SIZE should return the current number of items in cache. Updates don't change size, only new keys do.")
-    .run(harness)
+}
+
+/// Stage 1: Random Workload Test
+pub fn test_random_workload(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    random::run_random_workload(harness, 1)
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::EvictionPolicy;
+
+    /// 测试手写的期望与参考模型一致
+    #[test]
+    fn test_expectations_match_model() {
+        for test_case in [
+            basic_cache_case(),
+            multiple_keys_case(),
+            key_update_case(),
+            size_command_case(),
+        ] {
+            let conflicts = test_case.model_conflicts(EvictionPolicy::Lru).unwrap();
+            assert!(conflicts.is_empty(), "{}:\n{}", test_case.description, conflicts.join("\n"));
+        }
+    }
 }
@@ -1,5 +1,6 @@
 use tester_utils::{TestCaseHarness, TesterError};
-use crate::helpers::CommandRunner;
+use crate::test_case::CacheTestCase;
+use crate::random;
 
 /// Stage 3: LRU Eviction
 /// 
@@ -20,47 +21,28 @@ use crate::helpers::CommandRunner;
 /// This demonstrates the core LRU behavior: accessing an item
 /// prevents it from being evicted.
 pub fn test_lru_eviction(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
-    harness.logger.infof("Testing LRU eviction", &[]);
-    
-    let mut runner = CommandRunner::new(harness.executable.clone_executable());
-    
-    harness.logger.debugf("Step 1: Initialize cache with capacity 2", &[]);
-    harness.logger.debugf("Step 2: Add 'a' and 'b'", &[]);
-    harness.logger.debugf("Step 3: Access 'a' (updates access time)", &[]);
-    harness.logger.debugf("Step 4: Add 'c' (should evict 'b', the least recently used)", &[]);
-    
-    let responses = runner.send_commands(&[
-        "INIT 2",
-        "PUT a 1",
-        "PUT b 2",
-        "GET a",    // Access 'a' - makes it "recently used"
-        "PUT c 3",  // This should evict 'b' (least recently used)
-        "GET a",    // Should return 1 (still in cache)
-        "GET b",    // Should return NULL (evicted)
-        "GET c",    // Should return 3 (just added)
-    ])?;
-    
-    // Verify responses
-    let expected = vec!["OK", "OK", "OK", "1", "OK", "1", "NULL", "3"];
-    
-    for (i, (actual, expected)) in responses.iter().zip(expected.iter()).enumerate() {
-        if actual != expected {
-            return Err(TesterError::User(format!(
-                "Command {} failed: expected '{}', got '{}'\n\
-                Hint: In LRU, accessing an item (GET) should make it 'recently used'.\n\
-                When cache is full, the least recently accessed item should be evicted.",
-                i + 1, expected, actual
-            ).into()));
-        }
-        harness.logger.debugf(&format!("✓ Command {}: {}", i + 1, actual), &[]);
-    }
-    
-    harness.logger.successf("✓ LRU eviction working correctly", &[]);
-    harness.logger.debugf("  - GET operation updated access time for 'a'", &[]);
-    harness.logger.debugf("  - Least recently used item 'b' was evicted", &[]);
-    harness.logger.debugf("  - Recently accessed item 'a' was retained", &[]);
-    
-    Ok(())
+    lru_eviction_case().run(harness)
+}
+
+fn lru_eviction_case() -> CacheTestCase {
+    CacheTestCase::new(
+        "Testing LRU eviction",
+        vec![
+            "INIT 2",
+            "PUT a 1",
+            "PUT b 2",
+            "GET a",    // Access 'a' - makes it "recently used"
+            "PUT c 3",  // This should evict 'b' (least recently used)
+            "GET a",    // Should return 1 (still in cache)
+            "GET b",    // Should return NULL (evicted)
+            "GET c",    // Should return 3 (just added)
+        ],
+        vec!["OK", "OK", "OK", "1", "OK", "1", "NULL", "3"],
+    )
+    .with_hint(
+        "In LRU, accessing an item (GET) should make it 'recently used'.\n\
+        When cache is full, the least recently accessed item should be evicted."
+    )
 }
 
 /// Test LRU vs FIFO difference
@@ -78,46 +60,29 @@ pub fn test_lru_eviction(harness: &mut TestCaseHarness) -> Result<(), TesterErro
 /// - FIFO: Would evict 'a' (oldest insertion)
 /// - LRU: Should evict 'b' (least recently used, since 'a' was just updated)
 pub fn test_lru_vs_fifo(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
-    harness.logger.infof("Testing LRU vs FIFO difference", &[]);
-    
-    let mut runner = CommandRunner::new(harness.executable.clone_executable());
-    
-    harness.logger.debugf("Step 1: Add items in order: a, b", &[]);
-    harness.logger.debugf("Step 2: Update 'a' (in LRU this moves 'a' to most recent)", &[]);
-    harness.logger.debugf("Step 3: Add 'c' (should evict 'b', not 'a')", &[]);
-    
-    let responses = runner.send_commands(&[
-        "INIT 2",
-        "PUT a 1",
-        "PUT b 2",
-        "PUT a 100",  // Update 'a' - in LRU this makes 'a' most recent
-        "PUT c 3",    // This should evict 'b' (least recent)
-        "GET a",      // Should return 100 (retained, was just updated)
-        "GET b",      // Should return NULL (evicted)
-        "GET c",      // Should return 3 (just added)
-    ])?;
-    
-    let expected = vec!["OK", "OK", "OK", "OK", "OK", "100", "NULL", "3"];
-    
-    for (i, (actual, expected)) in responses.iter().zip(expected.iter()).enumerate() {
-        if actual != expected {
-            return Err(TesterError::User(format!(
-                "Command {} failed: expected '{}', got '{}'\n\
-                Hint: This is the key difference between LRU and FIFO!\n\
-                - FIFO: Update doesn't change eviction order (would evict 'a')\n\
-                - LRU: Update makes item 'recently used' (should evict 'b')",
-                i + 1, expected, actual
-            ).into()));
-        }
-        harness.logger.debugf(&format!("✓ Command {}: {}", i + 1, actual), &[]);
-    }
-    
-    harness.logger.successf("✓ LRU correctly differs from FIFO", &[]);
-    harness.logger.debugf("  - PUT operation updated access time for 'a'", &[]);
-    harness.logger.debugf("  - Item 'b' evicted (least recently accessed)", &[]);
-    harness.logger.debugf("  - Item 'a' retained (recently updated)", &[]);
-    
-    Ok(())
+    lru_vs_fifo_case().run(harness)
+}
+
+fn lru_vs_fifo_case() -> CacheTestCase {
+    CacheTestCase::new(
+        "Testing LRU vs FIFO difference",
+        vec![
+            "INIT 2",
+            "PUT a 1",
+            "PUT b 2",
+            "PUT a 100",  // Update 'a' - in LRU this makes 'a' most recent
+            "PUT c 3",    // This should evict 'b' (least recent)
+            "GET a",      // Should return 100 (retained, was just updated)
+            "GET b",      // Should return NULL (evicted)
+            "GET c",      // Should return 3 (just added)
+        ],
+        vec!["OK", "OK", "OK", "OK", "OK", "100", "NULL", "3"],
+    )
+    .with_hint(
+        "This is the key difference between LRU and FIFO!\n\
+        - FIFO: Update doesn't change eviction order (would evict 'a')\n\
+        - LRU: Update makes item 'recently used' (should evict 'b')"
+    )
 }
 
 /// Test multiple GET operations update access order
@@ -128,48 +93,32 @@ pub fn test_lru_vs_fifo(harness: &mut TestCaseHarness) -> Result<(), TesterError
 /// 3. Access 'a' and 'b' (moves them to recent)
 /// 4. Add 'd' (should evict 'c', the only item not accessed)
 pub fn test_lru_multiple_access(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
-    harness.logger.infof("Testing LRU with multiple access patterns", &[]);
-    
-    let mut runner = CommandRunner::new(harness.executable.clone_executable());
-    
-    harness.logger.debugf("Step 1: Fill cache with a, b, c", &[]);
-    harness.logger.debugf("Step 2: Access 'a' and 'b' (updates their access time)", &[]);
-    harness.logger.debugf("Step 3: Add 'd' (should evict 'c', the only unaccessed item)", &[]);
-    
-    let responses = runner.send_commands(&[
-        "INIT 3",
-        "PUT a 1",
-        "PUT b 2",
-        "PUT c 3",
-        "GET a",    // Access 'a'
-        "GET b",    // Access 'b'
-        "PUT d 4",  // Should evict 'c' (least recently used)
-        "GET a",    // Should return 1
-        "GET b",    // Should return 2
-        "GET c",    // Should return NULL (evicted)
-        "GET d",    // Should return 4
-        "SIZE",     // Should be 3
-    ])?;
-    
-    let expected = vec!["OK", "OK", "OK", "OK", "1", "2", "OK", "1", "2", "NULL", "4", "3"];
-    
-    for (i, (actual, expected)) in responses.iter().zip(expected.iter()).enumerate() {
-        if actual != expected {
-            return Err(TesterError::User(format!(
-                "Command {} failed: expected '{}', got '{}'\n\
-                Hint: Multiple GET operations should all update access time.\n\
-                The item that hasn't been accessed should be evicted first.",
-                i + 1, expected, actual
-            ).into()));
-        }
-        harness.logger.debugf(&format!("✓ Command {}: {}", i + 1, actual), &[]);
-    }
-    
-    harness.logger.successf("✓ LRU handles multiple accesses correctly", &[]);
-    harness.logger.debugf("  - Items 'a' and 'b' retained (recently accessed)", &[]);
-    harness.logger.debugf("  - Item 'c' evicted (not accessed after insertion)", &[]);
-    
-    Ok(())
+    lru_multiple_access_case().run(harness)
+}
+
+fn lru_multiple_access_case() -> CacheTestCase {
+    CacheTestCase::new(
+        "Testing LRU with multiple access patterns",
+        vec![
+            "INIT 3",
+            "PUT a 1",
+            "PUT b 2",
+            "PUT c 3",
+            "GET a",    // Access 'a'
+            "GET b",    // Access 'b'
+            "PUT d 4",  // Should evict 'c' (least recently used)
+            "GET a",    // Should return 1
+            "GET b",    // Should return 2
+            "GET c",    // Should return NULL (evicted)
+            "GET d",    // Should return 4
+            "SIZE",     // Should be 3
+        ],
+        vec!["OK", "OK", "OK", "OK", "1", "2", "OK", "1", "2", "NULL", "4", "3"],
+    )
+    .with_hint(
+        "Multiple GET operations should all update access time.\n\
+        The item that hasn't been accessed should be evicted first."
+    )
 }
 
 /// Test LRU with sequential evictions
@@ -184,39 +133,56 @@ pub fn test_lru_multiple_access(harness: &mut TestCaseHarness) -> Result<(), Tes
 /// 4. Access c
 /// 5. Add e (evicts d, not c)
 pub fn test_lru_sequential_evictions(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
-    harness.logger.infof("Testing LRU with sequential evictions", &[]);
-    
-    let mut runner = CommandRunner::new(harness.executable.clone_executable());
-    
-    harness.logger.debugf("Testing sequence: a, b, c(evict a), d(evict b), GET c, e(evict d)", &[]);
-    
-    let responses = runner.send_commands(&[
-        "INIT 2",
-        "PUT a 1",
-        "PUT b 2",
-        "PUT c 3",  // Evicts 'a'
-        "PUT d 4",  // Evicts 'b'
-        "GET c",    // Access 'c', makes it recent
-        "PUT e 5",  // Should evict 'd' (not 'c')
-        "GET c",    // Should return 3
-        "GET d",    // Should return NULL
-        "GET e",    // Should return 5
-    ])?;
-    
-    let expected = vec!["OK", "OK", "OK", "OK", "OK", "3", "OK", "3", "NULL", "5"];
-    
-    for (i, (actual, expected)) in responses.iter().zip(expected.iter()).enumerate() {
-        if actual != expected {
-            return Err(TesterError::User(format!(
-                "Command {} failed: expected '{}', got '{}'\n\
-                Hint: LRU should maintain correct access order through multiple evictions.",
-                i + 1, expected, actual
-            ).into()));
+    lru_sequential_evictions_case().run(harness)
+}
+
+fn lru_sequential_evictions_case() -> CacheTestCase {
+    CacheTestCase::new(
+        "Testing LRU with sequential evictions",
+        vec![
+            "INIT 2",
+            "PUT a 1",
+            "PUT b 2",
+            "PUT c 3",  // Evicts 'a'
+            "PUT d 4",  // Evicts 'b'
+            "GET c",    // Access 'c', makes it recent
+            "PUT e 5",  // Should evict 'd' (not 'c')
+            "GET c",    // Should return 3
+            "GET d",    // Should return NULL
+            "GET e",    // Should return 5
+        ],
+        vec!["OK", "OK", "OK", "OK", "OK", "3", "OK", "3", "NULL", "5"],
+    )
+    .with_hint(
+        "LRU should maintain correct access order through multiple evictions."
+    )
+}
+
+/// Test a seeded random workload
+pub fn test_random_workload(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    random::run_random_workload(harness, 3)
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::EvictionPolicy;
+
+    /// 测试手写的期望与参考模型一致
+    #[test]
+    fn test_expectations_match_model() {
+        for test_case in [
+            lru_eviction_case(),
+            lru_vs_fifo_case(),
+            lru_multiple_access_case(),
+            lru_sequential_evictions_case(),
+        ] {
+            let conflicts = test_case.model_conflicts(EvictionPolicy::Lru).unwrap();
+            assert!(conflicts.is_empty(), "{}:\n{}", test_case.description, conflicts.join("\n"));
         }
-        harness.logger.debugf(&format!("✓ Command {}: {}", i + 1, actual), &[]);
     }
-    
-    harness.logger.successf("✓ Sequential evictions maintain correct LRU order", &[]);
-    
-    Ok(())
 }
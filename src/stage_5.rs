@@ -1,5 +1,9 @@
 use tester_utils::{TestCaseHarness, TesterError};
+use crate::concurrency::{ConcurrentWorkload, MultiClientTestCase, WorkloadMode};
+use crate::assertions::PredicateAssertion;
+use crate::model::EvictionPolicy;
 use crate::test_case::CacheTestCase;
+use crate::random;
 
 /// Stage 5: Thread Safety
 /// 
@@ -9,11 +13,22 @@ use crate::test_case::CacheTestCase;
 /// 2. CONCURRENT command spawns multiple threads safely
 /// 3. Cache capacity is respected under concurrent load
 /// 4. No race conditions or crashes occur
+/// 5. In server mode, the tester's own concurrent clients see consistent results
+
+/// 程序崩溃时的提示
+const CRASH_HINT: &str = "A crash under CONCURRENT usually means shared state is modified without the lock held \
+    (e.g. 'dictionary changed size during iteration' or a corrupted linked list). \
+    Hold one lock around every access to the map and the list, and use a re-entrant lock \
+    (or lock-free internal helpers) when put() calls the eviction code.";
 
 /// Test basic operations with thread safety
 /// 
 /// Ensures that adding locks doesn't break existing functionality
 pub fn test_thread_safe_basic(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    thread_safe_basic_case().run(harness)
+}
+
+fn thread_safe_basic_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing basic operations with thread safety",
         vec![
@@ -32,13 +47,17 @@ pub fn test_thread_safe_basic(harness: &mut TestCaseHarness) -> Result<(), Teste
         "Thread safety should not affect basic operations. \
         Make sure all public methods (get, put, size) are protected with locks."
     )
-    .run(harness)
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test concurrent reads and writes with READ_HEAVY workload
 /// 
 /// Spawns multiple threads performing mostly reads (70%) with some writes (30%)
 pub fn test_concurrent_read_heavy(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    concurrent_read_heavy_case().run(harness)
+}
+
+fn concurrent_read_heavy_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing concurrent reads and writes (READ_HEAVY workload)",
         vec![
@@ -59,13 +78,17 @@ pub fn test_concurrent_read_heavy(harness: &mut TestCaseHarness) -> Result<(), T
         2. SIZE is valid (never exceeds capacity)\n\
         3. Lock protects all operations correctly"
     )
-    .run(harness)
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test concurrent writes with high contention
 /// 
 /// Spawns many threads performing mostly writes (90%) with few reads (10%)
 pub fn test_concurrent_write_heavy(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    concurrent_write_heavy_case().run(harness)
+}
+
+fn concurrent_write_heavy_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing concurrent writes with high contention (WRITE_HEAVY workload)",
         vec![
@@ -82,13 +105,17 @@ pub fn test_concurrent_write_heavy(harness: &mut TestCaseHarness) -> Result<(),
         2. No lost updates or race conditions\n\
         3. Eviction logic works correctly under concurrent load"
     )
-    .run(harness)
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test stress with small capacity and many threads
 /// 
 /// Small cache (capacity=2) with many concurrent threads forces frequent evictions
 pub fn test_concurrent_stress(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    concurrent_stress_case().run(harness)
+}
+
+fn concurrent_stress_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing stress with small capacity (MIXED workload)",
         vec![
@@ -107,13 +134,17 @@ pub fn test_concurrent_stress(harness: &mut TestCaseHarness) -> Result<(), Teste
         3. Deadlocks (nested lock acquisition)\n\
         4. Lost updates (operations not fully atomic)"
     )
-    .run(harness)
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test multiple concurrent operations in sequence
 /// 
 /// Verifies that cache state remains consistent across multiple CONCURRENT commands
 pub fn test_concurrent_sequential(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    concurrent_sequential_case().run(harness)
+}
+
+fn concurrent_sequential_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing multiple concurrent operations in sequence",
         vec![
@@ -133,35 +164,41 @@ pub fn test_concurrent_sequential(harness: &mut TestCaseHarness) -> Result<(), T
         After each concurrent operation, SIZE should equal capacity. \
         This tests that the cache recovers correctly between concurrent workloads."
     )
-    .run(harness)
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test that LRU behavior is preserved under concurrent load
 /// 
 /// Verifies that concurrent operations don't break LRU eviction logic
 pub fn test_concurrent_lru_preserved(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
-    CacheTestCase::new(
+    concurrent_lru_preserved_case()?.run(harness)
+}
+
+fn concurrent_lru_preserved_case() -> Result<CacheTestCase, TesterError> {
+    // Expectations come from the reference model: after CONCURRENT, 'a' may have been evicted
+    Ok(CacheTestCase::from_model(
         "Testing that LRU behavior is preserved under concurrent load",
+        EvictionPolicy::Lru,
         vec![
             "INIT 2",
             "PUT a 1",
             "PUT b 2",
             "GET a",           // Make 'a' most recent
-            "CONCURRENT 5 READ_HEAVY",  // Concurrent operations on random keys
-            "PUT c 3",         // Should evict 'b' if 'a' was recently accessed
-            "GET a",           // 'a' might still be in cache (depends on CONCURRENT operations)
-            "GET c",           // 'c' should be in cache
-            "SIZE",            // SIZE should be 2
+            "CONCURRENT 5 READ_HEAVY",  // Concurrent operations on random keys fill the cache
+            "PUT c 3",         // Evicts one of the older entries
+            "GET a",           // 1 or NULL
+            "GET c",           // 'c' was just written
+            "SIZE",            // Capacity is still 2
         ],
-        vec!["OK", "OK", "OK", "1", "OK", "OK", "NULL", "3", "2"],
-    )
+    )?
+    .with_assertion(PredicateAssertion::get_returns_last_put(EvictionPolicy::Lru))
     .with_hint(
         "Note: After CONCURRENT operations on random keys (key_0 to key_99), \
         the original keys 'a' and 'b' are likely evicted. \
         This is expected behavior - CONCURRENT uses different key space. \
         The test verifies that cache capacity is maintained and new keys work correctly."
     )
-    .run(harness)
+    .with_crash_hint(CRASH_HINT))
 }
 
 /// Test SIZE consistency under concurrent load
@@ -174,20 +211,7 @@ pub fn test_size_consistency(harness: &mut TestCaseHarness) -> Result<(), Tester
     for round in 1..=3 {
         harness.logger.debugf(&format!("Round {}/3", round), &[]);
         
-        CacheTestCase::new(
-            "SIZE consistency test (multiple rounds)",
-            vec![
-                "INIT 5",
-                "CONCURRENT 30 MIXED",
-                "SIZE",
-            ],
-            vec!["OK", "OK", "5"],
-        )
-        .with_hint(
-            "SIZE must never exceed capacity, even under concurrent load. \
-            If SIZE > capacity, there's a race condition in your eviction logic."
-        )
-        .run(harness)?;
+        size_consistency_case().run(harness)?;
     }
     
     harness.logger.successf("✓ SIZE remained consistent across all rounds", &[]);
@@ -195,10 +219,31 @@ pub fn test_size_consistency(harness: &mut TestCaseHarness) -> Result<(), Tester
     Ok(())
 }
 
+fn size_consistency_case() -> CacheTestCase {
+    CacheTestCase::new(
+        "SIZE consistency test (multiple rounds)",
+        vec![
+            "INIT 5",
+            "CONCURRENT 30 MIXED",
+            "SIZE",
+        ],
+        vec!["OK", "OK", "5"],
+    )
+    .with_hint(
+        "SIZE must never exceed capacity, even under concurrent load. \
+        If SIZE > capacity, there's a race condition in your eviction logic."
+    )
+    .with_crash_hint(CRASH_HINT)
+}
+
 /// Test edge case: CONCURRENT with capacity=1
 /// 
 /// Extreme contention scenario with smallest possible cache
 pub fn test_concurrent_capacity_one(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    concurrent_capacity_one_case().run(harness)
+}
+
+fn concurrent_capacity_one_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing CONCURRENT with capacity=1 (extreme contention)",
         vec![
@@ -217,13 +262,17 @@ pub fn test_concurrent_capacity_one(harness: &mut TestCaseHarness) -> Result<(),
         2. Crash (pointer corruption in DLL)\n\
         3. Deadlock (lock not released properly)"
     )
-    .run(harness)
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test that operations after CONCURRENT still work correctly
 /// 
 /// Verifies cache is usable after concurrent operations complete
 pub fn test_operations_after_concurrent(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    operations_after_concurrent_case().run(harness)
+}
+
+fn operations_after_concurrent_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing operations work correctly after CONCURRENT",
         vec![
@@ -243,5 +292,75 @@ pub fn test_operations_after_concurrent(harness: &mut TestCaseHarness) -> Result
         2. Internal state (HashMap + DLL) is not corrupted\n\
         3. New operations work normally"
     )
+    .with_crash_hint(CRASH_HINT)
+}
+
+/// Test real concurrent clients (server mode)
+/// 
+/// The tester opens its own connections instead of trusting CONCURRENT.
+/// The keyspace fits in the cache, so every written key must survive.
+pub fn test_multi_client_read_heavy(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    MultiClientTestCase::new(
+        "Testing 8 concurrent clients (READ_HEAVY, no evictions)",
+        50,
+        ConcurrentWorkload::new(8, 200, WorkloadMode::ReadHeavy).with_keyspace(40),
+    )
+    .with_hint(
+        "Concurrent clients saw inconsistent results. Make sure:\n\
+        1. Each connection is served independently (thread per client, async, ...)\n\
+        2. One lock protects the HashMap and the linked list together\n\
+        3. GET never returns a value written to a different key"
+    )
     .run(harness)
 }
+
+/// Test concurrent clients under eviction pressure (server mode)
+/// 
+/// Many more keys than capacity: SIZE must settle exactly at capacity
+pub fn test_multi_client_eviction(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    MultiClientTestCase::new(
+        "Testing 16 concurrent clients (WRITE_HEAVY, eviction pressure)",
+        10,
+        ConcurrentWorkload::new(16, 200, WorkloadMode::WriteHeavy).with_keyspace(100),
+    )
+    .with_hint(
+        "Capacity or SIZE broke under concurrent writes. Make sure:\n\
+        1. The capacity check and the eviction happen under the same lock as the insert\n\
+        2. SIZE never exceeds capacity, even while other clients are writing\n\
+        3. Evicted nodes are removed from both the HashMap and the linked list"
+    )
+    .run(harness)
+}
+
+/// Test a seeded random workload
+pub fn test_random_workload(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    random::run_random_workload(harness, 5)
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试手写的期望与参考模型一致
+    #[test]
+    fn test_expectations_match_model() {
+        for test_case in [
+            thread_safe_basic_case(),
+            concurrent_read_heavy_case(),
+            concurrent_write_heavy_case(),
+            concurrent_stress_case(),
+            concurrent_sequential_case(),
+            concurrent_lru_preserved_case().unwrap(),
+            size_consistency_case(),
+            concurrent_capacity_one_case(),
+            operations_after_concurrent_case(),
+        ] {
+            let conflicts = test_case.model_conflicts(EvictionPolicy::Lru).unwrap();
+            assert!(conflicts.is_empty(), "{}:\n{}", test_case.description, conflicts.join("\n"));
+        }
+    }
+}
@@ -1,5 +1,10 @@
+use std::time::{Duration, Instant};
 use tester_utils::{TestCaseHarness, TesterError};
+use crate::clock;
+use crate::helpers::{InteractiveCommandRunner, ProgramSpec, Timeouts};
+use crate::model::EvictionPolicy;
 use crate::test_case::CacheTestCase;
+use crate::random;
 
 /// Stage 6: TTL Expiration
 /// 
@@ -10,11 +15,28 @@ use crate::test_case::CacheTestCase;
 /// 3. TTL interacts correctly with LRU eviction
 /// 4. GET performs lazy deletion of expired entries
 /// 5. Entries without TTL never expire
+/// 6. SLEEP blocks for the requested time and replies OK
+///
+/// The TTL tests perform SLEEP on the tester side (the program never sees it),
+/// so SLEEP itself is checked by its own test.
+///
+/// With SYSTEMQUEST_VIRTUAL_CLOCK set, the TTL tests run on the optional clock extension
+/// (`CLOCK FREEZE`, `CLOCK ADVANCE <n>ms`, `CLOCK NOW`) instead: they finish in milliseconds,
+/// check expiry to the exact millisecond, and fail when the program does not support it.
+
+/// 程序崩溃时的提示
+const CRASH_HINT: &str = "A crash in Stage 6 usually comes from parsing the optional TTL (PUT key value [ttl]) \
+    or from deleting expired entries while iterating over the cache. \
+    A missing TTL means the entry never expires; remove expired entries from both the map and the list.";
 
 /// Test basic TTL expiration
 /// 
 /// Verifies that an entry expires after its TTL and is removed from cache
 pub fn test_ttl_basic(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    ttl_basic_case().run(harness)
+}
+
+fn ttl_basic_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing basic TTL expiration",
         vec![
@@ -34,13 +56,18 @@ pub fn test_ttl_basic(harness: &mut TestCaseHarness) -> Result<(), TesterError>
         3. Expired entries are removed and NULL is returned\n\
         4. SIZE reflects removal of expired entries"
     )
-    .run(harness)
+    .with_tester_delays()
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test immediate access (entry not expired yet)
 /// 
 /// Verifies that entry is accessible before TTL expires
 pub fn test_ttl_immediate_access(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    ttl_immediate_access_case().run(harness)
+}
+
+fn ttl_immediate_access_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing immediate access before TTL expiration",
         vec![
@@ -57,13 +84,17 @@ pub fn test_ttl_immediate_access(harness: &mut TestCaseHarness) -> Result<(), Te
         "Entries should be accessible immediately after PUT. \
         Check that expiration check doesn't trigger false positives."
     )
-    .run(harness)
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test multiple entries with different TTLs
 /// 
 /// Verifies that entries expire independently based on their individual TTLs
 pub fn test_ttl_multiple_different(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    ttl_multiple_different_case().run(harness)
+}
+
+fn ttl_multiple_different_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing multiple entries with different TTLs",
         vec![
@@ -85,13 +116,18 @@ pub fn test_ttl_multiple_different(harness: &mut TestCaseHarness) -> Result<(),
         - 'short' (TTL=1) should be expired\n\
         - 'medium' (TTL=3) and 'long' (TTL=10) should still be valid"
     )
-    .run(harness)
+    .with_tester_delays()
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test TTL with LRU eviction
 /// 
 /// Verifies that LRU eviction works correctly alongside TTL
 pub fn test_ttl_with_eviction(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    ttl_with_eviction_case().run(harness)
+}
+
+fn ttl_with_eviction_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing TTL with LRU eviction",
         vec![
@@ -109,13 +145,17 @@ pub fn test_ttl_with_eviction(harness: &mut TestCaseHarness) -> Result<(), Teste
         "LRU eviction should work normally with TTL entries. \
         'a' is evicted because cache is full, not because TTL expired."
     )
-    .run(harness)
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test no TTL (entries never expire)
 /// 
 /// Verifies that entries without TTL persist indefinitely
 pub fn test_ttl_no_expiration(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    ttl_no_expiration_case().run(harness)
+}
+
+fn ttl_no_expiration_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing entries without TTL (never expire)",
         vec![
@@ -133,13 +173,18 @@ pub fn test_ttl_no_expiration(harness: &mut TestCaseHarness) -> Result<(), Teste
         "Entries without TTL should never expire. \
         They can only be removed by LRU eviction or explicit deletion."
     )
-    .run(harness)
+    .with_tester_delays()
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test mixed TTL and no-TTL entries
 /// 
 /// Verifies that entries with and without TTL coexist correctly
 pub fn test_ttl_mixed(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    ttl_mixed_case().run(harness)
+}
+
+fn ttl_mixed_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing mixed TTL and no-TTL entries",
         vec![
@@ -157,13 +202,18 @@ pub fn test_ttl_mixed(harness: &mut TestCaseHarness) -> Result<(), TesterError>
         "Entries with TTL should expire, while entries without TTL persist. \
         Check that expire_at is None for entries without TTL."
     )
-    .run(harness)
+    .with_tester_delays()
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test PUT update resets TTL
 /// 
 /// Verifies that updating an existing key also updates its TTL
 pub fn test_ttl_update(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    ttl_update_case().run(harness)
+}
+
+fn ttl_update_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing PUT update resets TTL",
         vec![
@@ -184,13 +234,18 @@ pub fn test_ttl_update(harness: &mut TestCaseHarness) -> Result<(), TesterError>
         - At T=1s: Update to a=2 with TTL=5s (expires at T=6s)\n\
         - At T=3.5s: GET should succeed (not expired yet)"
     )
-    .run(harness)
+    .with_tester_delays()
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test TTL with SIZE consistency
 /// 
 /// Verifies that SIZE is consistent after lazy deletion
 pub fn test_ttl_size_consistency(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    ttl_size_consistency_case().run(harness)
+}
+
+fn ttl_size_consistency_case() -> CacheTestCase {
     CacheTestCase::new(
         "Testing SIZE consistency with TTL expiration",
         vec![
@@ -215,32 +270,224 @@ pub fn test_ttl_size_consistency(harness: &mut TestCaseHarness) -> Result<(), Te
         With lazy deletion, expired entries remain in cache until accessed. \
         SIZE decreases as expired entries are removed via GET."
     )
-    .run(harness)
+    .with_tester_delays()
+    .with_crash_hint(CRASH_HINT)
 }
 
 /// Test TTL with concurrent operations
 /// 
 /// Verifies that TTL works correctly under concurrent access
 pub fn test_ttl_concurrent(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
-    CacheTestCase::new(
+    ttl_concurrent_case()?.run(harness)
+}
+
+fn ttl_concurrent_case() -> Result<CacheTestCase, TesterError> {
+    // Expectations come from the reference model: CONCURRENT fills the cache and may evict a/b/c
+    Ok(CacheTestCase::from_model(
         "Testing TTL with concurrent operations",
+        EvictionPolicy::Lru,
         vec![
             "INIT 10",
             "PUT a 1 5",        // TTL = 5 seconds
             "PUT b 2 5",        // TTL = 5 seconds
             "PUT c 3 5",        // TTL = 5 seconds
-            "CONCURRENT 10 READ_HEAVY",  // Concurrent reads/writes
-            "SIZE",             // Should be <= 10 (capacity enforced)
-            "GET a",            // May or may not exist (depends on CONCURRENT)
-            "GET b",            // May or may not exist (depends on CONCURRENT)
-            "GET c",            // May or may not exist (depends on CONCURRENT)
+            "CONCURRENT 10 READ_HEAVY",  // Concurrent reads/writes on key_0..key_99
+            "SIZE",             // 10: the cache is full, nothing has expired
+            "GET a",            // 1 or NULL (may have been evicted, not expired)
+            "GET b",            // 2 or NULL
+            "GET c",            // 3 or NULL
         ],
-        vec!["OK", "OK", "OK", "OK", "OK", "10", "NULL", "NULL", "NULL"],
-    )
+    )?
     .with_hint(
         "TTL should work correctly with concurrent operations. \
-        CONCURRENT command may overwrite a/b/c with random keys. \
+        CONCURRENT command may evict a/b/c to make room for random keys, \
+        but none of them expires within 5 seconds. \
         Check that expired entries are removed safely under concurrent access."
     )
-    .run(harness)
+    .with_crash_hint(CRASH_HINT))
+}
+
+/// Test the SLEEP command
+///
+/// Verifies that SLEEP blocks for the requested (fractional) number of seconds,
+/// replies OK, and leaves the cache usable
+pub fn test_sleep_command(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    harness.logger.infof("Testing the SLEEP command", &[]);
+    let spec = ProgramSpec::from_env()?.with_crash_hint(CRASH_HINT);
+    let mut runner = InteractiveCommandRunner::start(&spec, Timeouts::default())?;
+
+    expect(harness, &mut runner, "INIT 3", "OK")?;
+    expect(harness, &mut runner, "PUT a 1", "OK")?;
+    for seconds in [0.5, 1.0] {
+        let command = format!("SLEEP {}", seconds);
+        let started = Instant::now();
+        let response = runner.send_command(&command)?;
+        let elapsed = started.elapsed();
+        check_sleep(&command, &response, Duration::from_secs_f64(seconds), elapsed).map_err(|message| {
+            harness.logger.errorf(&format!("𐄂 {} → {} (after {:.2}s)", command, response, elapsed.as_secs_f64()), &[]);
+            TesterError::User(format!(
+                "{}\n\n\
+                SLEEP <seconds> should block for that many seconds (fractions allowed, e.g. SLEEP 0.5) \
+                and then print OK. Parse the argument as a float, not an integer.",
+                message
+            ).into())
+        })?;
+        harness.logger.successf(&format!("✓ {} → OK (after {:.2}s)", command, elapsed.as_secs_f64()), &[]);
+    }
+    expect(harness, &mut runner, "GET a", "1")?;
+    expect(harness, &mut runner, "SIZE", "1")?;
+
+    runner.finish()?;
+    harness.logger.successf("✓ Testing the SLEEP command", &[]);
+    Ok(())
+}
+
+/// 发送一条命令并要求响应完全一致
+fn expect(
+    harness: &TestCaseHarness,
+    runner: &mut InteractiveCommandRunner,
+    command: &str,
+    expected: &str,
+) -> Result<(), TesterError> {
+    let response = runner.send_command(command)?;
+    if response == expected {
+        harness.logger.successf(&format!("✓ {} → {}", command, response), &[]);
+        return Ok(());
+    }
+    harness.logger.errorf(&format!("𐄂 {} → {}", command, response), &[]);
+    Err(TesterError::User(format!(
+        "Unexpected response to {}\nExpected: {}\nActual:   {}",
+        command, expected, response
+    ).into()))
+}
+
+/// 检查 SLEEP 的响应和耗时（允许提前 50ms 的计时误差，最多晚 1 秒）
+fn check_sleep(command: &str, response: &str, requested: Duration, elapsed: Duration) -> Result<(), String> {
+    if response != "OK" {
+        return Err(format!("Unexpected response to {}\nExpected: OK\nActual:   {}", command, response));
+    }
+    if elapsed + Duration::from_millis(50) < requested {
+        return Err(format!(
+            "{} replied after only {:.2}s; it should block for {:.2}s",
+            command,
+            elapsed.as_secs_f64(),
+            requested.as_secs_f64()
+        ));
+    }
+    if elapsed > requested + Duration::from_secs(1) {
+        return Err(format!(
+            "{} took {:.2}s; it should block for {:.2}s",
+            command,
+            elapsed.as_secs_f64(),
+            requested.as_secs_f64()
+        ));
+    }
+    Ok(())
+}
+
+/// Test a seeded random workload
+pub fn test_random_workload(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    random::run_random_workload(harness, 6)
+}
+
+/// Test exact expiry boundaries on a virtual clock
+/// 
+/// Verifies that an entry is still valid 1ms before its TTL and expired exactly at its TTL
+pub fn test_ttl_expiry_boundary_virtual(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    ttl_expiry_boundary_case().run(harness)
+}
+
+fn ttl_expiry_boundary_case() -> CacheTestCase {
+    CacheTestCase::new(
+        "Testing exact TTL expiry boundaries",
+        vec![
+            "INIT 5",
+            clock::FREEZE,
+            "PUT a 1 1",              // Expires at 1000ms
+            "PUT b 2 2",              // Expires at 2000ms
+            "CLOCK ADVANCE 999ms",
+            "GET a",                  // 1ms before expiry: still valid
+            "CLOCK ADVANCE 1ms",
+            "GET a",                  // Exactly at expiry: expired
+            "GET b",
+            "PUT b 3 2",              // Update at 1000ms: expires at 3000ms
+            "CLOCK ADVANCE 1999ms",
+            "GET b",                  // 1ms before the new expiry
+            "CLOCK ADVANCE 1ms",
+            "GET b",                  // Exactly at the new expiry
+            clock::NOW,
+            "SIZE",
+        ],
+        vec!["OK", "OK", "OK", "OK", "OK", "1", "OK", "NULL", "2", "OK", "OK", "3", "OK", "NULL", "3000", "0"],
+    )
+    .with_hint(
+        "An entry written at time T with TTL t expires at exactly T + t: \
+        it is still valid 1ms earlier and expired from that moment on (now >= expire_at). \
+        On a frozen clock, read the virtual time everywhere you would read the real clock."
+    )
+    .with_crash_hint(CRASH_HINT)
+    .with_virtual_clock()
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试手写的期望与参考模型一致
+    #[test]
+    fn test_expectations_match_model() {
+        for test_case in [
+            ttl_basic_case(),
+            ttl_immediate_access_case(),
+            ttl_multiple_different_case(),
+            ttl_with_eviction_case(),
+            ttl_no_expiration_case(),
+            ttl_mixed_case(),
+            ttl_update_case(),
+            ttl_size_consistency_case(),
+            ttl_concurrent_case().unwrap(),
+        ] {
+            let conflicts = test_case.model_conflicts(EvictionPolicy::Lru).unwrap();
+            assert!(conflicts.is_empty(), "{}:\n{}", test_case.description, conflicts.join("\n"));
+        }
+    }
+
+    /// 测试虚拟时钟变体（包括精确边界）的期望与参考模型一致
+    #[test]
+    fn test_virtual_clock_expectations_match_model() {
+        for test_case in [
+            ttl_basic_case(),
+            ttl_immediate_access_case(),
+            ttl_multiple_different_case(),
+            ttl_with_eviction_case(),
+            ttl_no_expiration_case(),
+            ttl_mixed_case(),
+            ttl_update_case(),
+            ttl_size_consistency_case(),
+        ] {
+            let test_case = test_case.with_virtual_clock();
+            assert!(!test_case.commands.iter().any(|command| command.starts_with("SLEEP")));
+            let conflicts = test_case.model_conflicts(EvictionPolicy::Lru).unwrap();
+            assert!(conflicts.is_empty(), "{}:\n{}", test_case.description, conflicts.join("\n"));
+        }
+
+        let boundary = ttl_expiry_boundary_case();
+        assert_eq!(boundary.commands.iter().filter(|command| *command == clock::FREEZE).count(), 1);
+        assert!(boundary.model_conflicts(EvictionPolicy::Lru).unwrap().is_empty());
+    }
+
+    /// 测试 SLEEP 的耗时检查: 太短、太长和错误的响应都会失败
+    #[test]
+    fn test_check_sleep() {
+        let half = Duration::from_millis(500);
+        assert!(check_sleep("SLEEP 0.5", "OK", half, Duration::from_millis(510)).is_ok());
+        assert!(check_sleep("SLEEP 0.5", "OK", half, Duration::from_millis(470)).is_ok());
+        assert!(check_sleep("SLEEP 0.5", "OK", half, Duration::from_millis(5)).is_err());
+        assert!(check_sleep("SLEEP 0.5", "OK", half, Duration::from_millis(1600)).is_err());
+        assert!(check_sleep("SLEEP 0.5", "NULL", half, half).is_err());
+    }
 }
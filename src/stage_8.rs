@@ -0,0 +1,210 @@
+//! Stage 8: RESP Protocol (Extension)
+//!
+//! Stage 8 verifies that the cache server speaks RESP (Redis serialization protocol)
+//! on the port passed via SYSTEMQUEST_CACHE_PORT.
+//! Tests verify that:
+//! 1. Commands arrive as RESP arrays of bulk strings, possibly pipelined or split across packets
+//! 2. OK is a simple string, values are bulk strings, misses are null bulk strings
+//! 3. Bulk values are binary-safe (spaces, CRLF, NUL bytes round-trip exactly)
+//! 4. Bad commands get `-ERR ...` replies and the connection stays usable
+//!
+//! These tests always run in RESP mode, regardless of SYSTEMQUEST_TRANSPORT.
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+use tester_utils::{TestCaseHarness, TesterError};
+use crate::helpers::{output_block, ProgramSpec, ServerProcess, Timeouts};
+use crate::resp::{RespConnection, RespValue};
+use crate::transport::Transport;
+
+/// 等待单个回复的最长时间
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Test RESP framing
+///
+/// Verifies reply types for pipelined commands and a command split across writes
+pub fn test_resp_framing(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    harness.logger.infof("Testing RESP framing (pipelined and split commands)", &[]);
+    let mut session = RespSession::start()?;
+
+    // 一次写入多条命令（pipelining）
+    let pipeline: Vec<u8> = ["INIT 3", "PUT a 1", "GET a", "GET missing", "SIZE"]
+        .iter()
+        .flat_map(|line| RespValue::from_command_line(line).encode())
+        .collect();
+    session.send_raw(&pipeline)?;
+
+    session.expect(harness, "INIT 3", &RespValue::SimpleString("OK".into()))?;
+    session.expect(harness, "PUT a 1", &RespValue::SimpleString("OK".into()))?;
+    session.expect(harness, "GET a", &RespValue::bulk("1"))?;
+    session.expect(harness, "GET missing", &RespValue::NullBulkString)?;
+    session.expect(harness, "SIZE", &RespValue::Integer(1))?;
+
+    // 一条命令分两次写入，服务器必须等待完整的帧
+    let split = RespValue::from_command_line("PUT b 2").encode();
+    let (head, tail) = split.split_at(split.len() - 5);
+    session.send_raw(head)?;
+    thread::sleep(Duration::from_millis(100));
+    session.send_raw(tail)?;
+    session.expect(harness, "PUT b 2 (split across two writes)", &RespValue::SimpleString("OK".into()))?;
+
+    session.send(&RespValue::from_command_line("GET b"))?;
+    session.expect(harness, "GET b", &RespValue::bulk("2"))?;
+
+    harness.logger.successf("✓ Testing RESP framing (pipelined and split commands)", &[]);
+    Ok(())
+}
+
+/// Test binary-safe bulk values
+///
+/// Verifies that values containing spaces, CRLF and non-UTF-8 bytes round-trip exactly
+pub fn test_resp_binary_values(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    harness.logger.infof("Testing binary-safe bulk values", &[]);
+    let mut session = RespSession::start()?;
+
+    let values: [(&str, &[u8]); 3] = [
+        ("spaces", b"hello brave new world"),
+        ("crlf", b"line1\r\nline2\r\n"),
+        ("bytes", b"\x00\xff\x01binary\x00"),
+    ];
+
+    session.send(&RespValue::from_command_line("INIT 10"))?;
+    session.expect(harness, "INIT 10", &RespValue::SimpleString("OK".into()))?;
+
+    for (key, value) in values {
+        let put = RespValue::command(&[b"PUT".as_slice(), key.as_bytes(), value]);
+        session.send(&put)?;
+        session.expect(harness, &put.describe(), &RespValue::SimpleString("OK".into()))?;
+    }
+
+    for (key, value) in values {
+        let get = format!("GET {}", key);
+        session.send(&RespValue::from_command_line(&get))?;
+        session.expect(harness, &get, &RespValue::bulk(value))?;
+    }
+
+    harness.logger.successf("✓ Testing binary-safe bulk values", &[]);
+    Ok(())
+}
+
+/// Test error replies
+///
+/// Verifies that invalid commands get `-ERR` replies without closing the connection
+pub fn test_resp_error_replies(harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+    harness.logger.infof("Testing RESP error replies", &[]);
+    let mut session = RespSession::start()?;
+
+    session.send(&RespValue::from_command_line("INIT 2"))?;
+    session.expect(harness, "INIT 2", &RespValue::SimpleString("OK".into()))?;
+
+    for command in ["FLY me to the moon", "PUT lonely"] {
+        session.send(&RespValue::from_command_line(command))?;
+        session.expect_error(harness, command)?;
+    }
+
+    // 错误之后连接仍然可用
+    session.send(&RespValue::from_command_line("PUT a 1"))?;
+    session.expect(harness, "PUT a 1", &RespValue::SimpleString("OK".into()))?;
+    session.send(&RespValue::from_command_line("GET a"))?;
+    session.expect(harness, "GET a", &RespValue::bulk("1"))?;
+
+    harness.logger.successf("✓ Testing RESP error replies", &[]);
+    Ok(())
+}
+
+/// 一个 RESP 服务器及其连接
+///
+/// 直接操作 RespConnection（而不是经过 CommandRunner 的文本规范化），
+/// 这样可以检查回复的具体类型和原始字节。
+struct RespSession {
+    server: ServerProcess,
+    connection: RespConnection,
+}
+
+impl RespSession {
+    fn start() -> Result<Self, TesterError> {
+        let spec = ProgramSpec::from_env()?.with_transport(Transport::Resp);
+        let (server, stream) = ServerProcess::start(&spec, &Timeouts::default())?;
+        let connection = RespConnection::new(stream, REPLY_TIMEOUT).map_err(|e| {
+            TesterError::Configuration(format!("Failed to configure RESP connection: {}", e))
+        })?;
+        Ok(Self { server, connection })
+    }
+
+    fn send(&mut self, value: &RespValue) -> Result<(), TesterError> {
+        self.send_raw(&value.encode())
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) -> Result<(), TesterError> {
+        self.connection
+            .send_raw(bytes)
+            .map_err(|e| self.failure(&format!("Failed to send command: {}", e)))
+    }
+
+    /// 读取下一个回复并要求与 expected 完全一致（类型和字节）
+    fn expect(&mut self, harness: &TestCaseHarness, command: &str, expected: &RespValue) -> Result<(), TesterError> {
+        let actual = self.receive(harness, command)?;
+        if &actual == expected {
+            harness.logger.successf(&format!("✓ {} → {}", command, actual.describe()), &[]);
+            return Ok(());
+        }
+
+        harness.logger.errorf(&format!("𐄂 {} → {}", command, actual.describe()), &[]);
+        Err(self.failure(&format!(
+            "Unexpected reply to {}\nExpected: {}\nActual:   {}",
+            command,
+            expected.describe(),
+            actual.describe()
+        )))
+    }
+
+    /// 读取下一个回复并要求是以 "ERR" 开头的错误回复
+    fn expect_error(&mut self, harness: &TestCaseHarness, command: &str) -> Result<(), TesterError> {
+        let actual = self.receive(harness, command)?;
+        if matches!(&actual, RespValue::Error(message) if message.starts_with("ERR")) {
+            harness.logger.successf(&format!("✓ {} → {}", command, actual.describe()), &[]);
+            return Ok(());
+        }
+
+        harness.logger.errorf(&format!("𐄂 {} → {}", command, actual.describe()), &[]);
+        Err(self.failure(&format!(
+            "Unexpected reply to {}\nExpected: an error reply like -ERR unknown command\nActual:   {}",
+            command,
+            actual.describe()
+        )))
+    }
+
+    fn receive(&mut self, harness: &TestCaseHarness, command: &str) -> Result<RespValue, TesterError> {
+        self.connection.receive().map_err(|e| {
+            harness.logger.errorf(&format!("𐄂 {} → (no valid reply)", command), &[]);
+            let reason = match e.kind() {
+                io::ErrorKind::UnexpectedEof => "server closed the connection".to_string(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                    format!("no reply within {:.1}s", REPLY_TIMEOUT.as_secs_f64())
+                }
+                _ => e.to_string(),
+            };
+            self.failure(&format!("Invalid reply to {}: {}", command, reason))
+        })
+    }
+
+    /// 构造失败信息，附带服务器日志
+    fn failure(&mut self, message: &str) -> TesterError {
+        let exited = match self.server.try_exit_status() {
+            Some(status) => format!("\nServer exited ({})", status),
+            None => String::new(),
+        };
+        TesterError::User(format!(
+            "{}{}\n\n{}\n\n\
+            Make sure your server:\n\
+            1. Parses RESP arrays of bulk strings (*<n>\\r\\n${{len}}\\r\\n<bytes>\\r\\n...)\n\
+            2. Replies +OK, $<len> bulk values, $-1 for misses and :<n> for SIZE\n\
+            3. Treats values as raw bytes (no splitting on spaces or newlines)\n\
+            4. Replies -ERR <message> to invalid commands and keeps the connection open",
+            message,
+            exited,
+            output_block("stderr", &self.server.stderr())
+        ).into())
+    }
+}
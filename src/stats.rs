@@ -0,0 +1,179 @@
+use std::fmt;
+
+/// STATS 回复中各字段的顺序: "hits:X misses:Y hit_rate:Z.ZZ evictions:E expirations:X size:S capacity:C"
+pub const STATS_FIELDS: [&str; 7] = ["hits", "misses", "hit_rate", "evictions", "expirations", "size", "capacity"];
+
+/// Stats - 解析后的 STATS 回复
+///
+/// 解析时检查字段顺序和格式（hit_rate 必须恰好两位小数），
+/// `invariant_violations` 检查字段之间的关系，不需要知道精确值。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+
+    /// 百分比，例如 66.67
+    pub hit_rate: f64,
+
+    pub evictions: u64,
+    pub expirations: u64,
+    pub size: u64,
+    pub capacity: u64,
+}
+
+impl Stats {
+    /// 解析一行 STATS 回复；格式错误时返回说明
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != STATS_FIELDS.len() {
+            return Err(format!(
+                "expected {} fields ({}), got {}",
+                STATS_FIELDS.len(),
+                STATS_FIELDS.join(" "),
+                parts.len()
+            ));
+        }
+
+        let mut values = [0u64; 7];
+        let mut hit_rate = 0.0;
+        for (i, (part, name)) in parts.iter().zip(STATS_FIELDS).enumerate() {
+            let value = match part.split_once(':') {
+                Some((field, value)) if field == name => value,
+                _ => return Err(format!("field #{} should be '{}:...', got '{}'", i + 1, name, part)),
+            };
+
+            if name == "hit_rate" {
+                hit_rate = parse_hit_rate(value)
+                    .ok_or_else(|| format!("hit_rate must have exactly 2 decimals (e.g. 66.67), got '{}'", value))?;
+            } else {
+                values[i] = value
+                    .parse()
+                    .map_err(|_| format!("{} must be a non-negative integer, got '{}'", name, value))?;
+            }
+        }
+
+        Ok(Self {
+            hits: values[0],
+            misses: values[1],
+            hit_rate,
+            evictions: values[3],
+            expirations: values[4],
+            size: values[5],
+            capacity: values[6],
+        })
+    }
+
+    /// 字段的文本形式（hit_rate 保留两位小数）
+    pub fn field(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "hits" => self.hits.to_string(),
+            "misses" => self.misses.to_string(),
+            "hit_rate" => format!("{:.2}", self.hit_rate),
+            "evictions" => self.evictions.to_string(),
+            "expirations" => self.expirations.to_string(),
+            "size" => self.size.to_string(),
+            "capacity" => self.capacity.to_string(),
+            _ => return None,
+        })
+    }
+
+    /// 期望的命中率: hits / (hits + misses) * 100，没有 GET 时为 0
+    pub fn expected_hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / total as f64 * 100.0
+    }
+
+    /// 不依赖精确值的一致性检查
+    ///
+    /// - hit_rate 等于 hits/(hits+misses) 保留两位小数（允许 .005 的舍入差异）
+    /// - size 不超过 capacity
+    pub fn invariant_violations(&self) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        let expected = self.expected_hit_rate();
+        if (self.hit_rate - expected).abs() > 0.005 + 1e-9 {
+            violations.push(format!(
+                "hit_rate: {:.2} does not equal hits/(hits+misses) = {}/{} = {:.2}",
+                self.hit_rate,
+                self.hits,
+                self.hits + self.misses,
+                expected
+            ));
+        }
+
+        if self.size > self.capacity {
+            violations.push(format!("size: {} exceeds capacity {}", self.size, self.capacity));
+        }
+
+        violations
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = STATS_FIELDS
+            .iter()
+            .map(|name| format!("{}:{}", name, self.field(name).unwrap_or_default()))
+            .collect();
+        write!(f, "{}", fields.join(" "))
+    }
+}
+
+/// 解析 "66.67" 形式的命中率（整数部分加恰好两位小数）
+fn parse_hit_rate(value: &str) -> Option<f64> {
+    let (whole, decimals) = value.split_once('.')?;
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !digits(whole) || decimals.len() != 2 || !digits(decimals) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试解析和文本形式
+    #[test]
+    fn test_parse_stats() {
+        let line = "hits:2 misses:1 hit_rate:66.67 evictions:0 expirations:0 size:2 capacity:5";
+        let stats = Stats::parse(line).unwrap();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.capacity, 5);
+        assert_eq!(stats.to_string(), line);
+        assert!(stats.invariant_violations().is_empty());
+    }
+
+    /// 测试字段顺序和格式错误
+    #[test]
+    fn test_parse_stats_format_errors() {
+        let swapped = Stats::parse("misses:1 hits:2 hit_rate:66.67 evictions:0 expirations:0 size:2 capacity:5");
+        assert_eq!(swapped.unwrap_err(), "field #1 should be 'hits:...', got 'misses:1'");
+
+        let rate = Stats::parse("hits:2 misses:1 hit_rate:66.7 evictions:0 expirations:0 size:2 capacity:5");
+        assert!(rate.unwrap_err().contains("exactly 2 decimals"));
+
+        assert!(Stats::parse("hits:2 misses:1").unwrap_err().contains("expected 7 fields"));
+        assert!(Stats::parse("hits:-1 misses:1 hit_rate:0.00 evictions:0 expirations:0 size:0 capacity:5").is_err());
+    }
+
+    /// 测试字段之间的不变式
+    #[test]
+    fn test_stats_invariants() {
+        let stats = Stats::parse("hits:2 misses:1 hit_rate:50.00 evictions:0 expirations:0 size:6 capacity:5").unwrap();
+        let violations = stats.invariant_violations();
+        assert_eq!(violations.len(), 2);
+        assert!(violations[0].contains("2/3 = 66.67"));
+        assert!(violations[1].contains("size: 6 exceeds capacity 5"));
+
+        let empty = Stats::parse("hits:0 misses:0 hit_rate:0.00 evictions:0 expirations:0 size:0 capacity:5").unwrap();
+        assert!(empty.invariant_violations().is_empty());
+    }
+}
@@ -1,6 +1,25 @@
-use tester_utils::{TestCaseHarness, TesterError};
-use crate::helpers::CommandRunner;
-use crate::assertions::{Assertion, ExactMatchAssertion};
+use std::ops::RangeInclusive;
+use tester_utils::{Logger, TestCaseHarness, TesterError};
+use crate::helpers::{CommandRunner, InteractiveCommandRunner, ProgramSpec, RunOutput, Timeouts};
+use crate::assertions::{Assertion, ExactMatchAssertion, ExpectedLine, RegexAssertion, StatsAssertion, Transcript};
+use crate::clock;
+use crate::differential::{self, Reference, REFERENCE_DIR_ENV};
+use crate::model::{self, CacheModel, EvictionPolicy};
+use crate::normalize::{self, Normalization};
+use crate::response::{FramingRules, Response};
+use crate::shrink::Shrinker;
+use crate::usage;
+
+/// 命令执行模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunMode {
+    /// 一次性写入所有命令、关闭 stdin、等待退出后解析输出（默认）
+    #[default]
+    Batch,
+    
+    /// 程序保持运行，每发送一条命令立即读取一行响应
+    Interactive,
+}
 
 /// CacheTestCase - 测试用例抽象
 /// 
@@ -17,45 +36,139 @@ use crate::assertions::{Assertion, ExactMatchAssertion};
 /// - 可扩展支持不同验证策略
 pub struct CacheTestCase {
     /// 测试描述（用于日志）
-    pub description: &'static str,
+    pub description: String,
     
     /// 要发送的命令列表
-    pub commands: Vec<&'static str>,
+    pub commands: Vec<String>,
     
     /// 期望的响应列表（必须与 commands 长度相同）
-    pub expected_responses: Vec<&'static str>,
+    pub expected_responses: Vec<String>,
     
     /// 失败时的提示信息（可选，用于教学性错误提示）
-    pub hint: Option<&'static str>,
+    pub hint: Option<String>,
+    
+    /// 程序崩溃（被信号终止或非零退出）时的提示信息（可选，通常按 Stage 设置）
+    pub crash_hint: Option<&'static str>,
     
     /// 是否显示详细的命令执行日志（默认 false）
     /// 注意: 启用 verbose 会禁用 Assertion 的友好输出
     pub verbose: bool,
     
-    /// 是否使用正则表达式匹配响应（用于并发测试等不确定输出）
+    /// 失败时报告所有不匹配的响应（带上下文的对照表），而不是只报告第一个
+    pub all_mismatches: bool,
+    
+    /// 是否使用正则表达式匹配所有响应（用于并发测试等不确定输出）
     pub regex_match: bool,
+    
+    /// 按正则表达式匹配的期望响应下标（从 0 开始），其余行仍精确匹配
+    /// 正则匹配整条响应（自动加上 ^...$）
+    pub regex_lines: Vec<usize>,
+    
+    /// 按下标覆盖的期望（范围、候选集合、数值容差等，用于结果不确定的测试）
+    /// 这些行以此为准，expected_responses 中对应的字符串只作说明
+    pub expectations: Vec<(usize, ExpectedLine)>,
+    
+    /// 逐行匹配通过后再检查的断言（组合断言、基于完整命令/响应记录的规则等）
+    pub assertions: Vec<Box<dyn Assertion>>,
+    
+    /// 命令执行模式（默认 Batch）
+    pub run_mode: RunMode,
+    
+    /// 单条命令 / 整个测试的超时（默认 Timeouts::default()）
+    pub timeouts: Timeouts,
+    
+    /// 多行响应的帧格式（默认每条命令一行）
+    /// 多行响应的期望值用 "\n" 连接各行，例如 "c\nb\na"
+    pub framing: FramingRules,
+    
+    /// 比较前的规范化策略（默认只去掉 "\r"）
+    pub normalization: Normalization,
+    
+    /// 失败时用参考模型把命令序列缩减到最短（Batch 模式或测试器等待 SLEEP 时，见 `shrink::Shrinker`）
+    pub shrink: Option<EvictionPolicy>,
+    
+    /// 期望来自参考实现的输出（SYSTEMQUEST_REFERENCE_DIR），expected_responses 为空
+    pub from_reference: bool,
+    
+    /// SLEEP 由测试器自己等待，不发送给程序（交互模式，见 `InteractiveCommandRunner::run_with_tester_delays`）
+    pub tester_delays: bool,
+    
+    /// 使用虚拟时钟（`clock` 协议）；程序不支持时测试失败
+    pub virtual_clock: bool,
 }
 
 impl CacheTestCase {
     /// 创建一个新的测试用例
+    /// 
+    /// 命令和期望响应可以是字面量（`vec!["INIT 10"]`），也可以是运行时生成的
+    /// `Vec<String>`（例如在循环中用 `format!` 构造的长命令序列）。
     pub fn new(
-        description: &'static str,
-        commands: Vec<&'static str>,
-        expected_responses: Vec<&'static str>,
+        description: impl Into<String>,
+        commands: Vec<impl Into<String>>,
+        expected_responses: Vec<impl Into<String>>,
     ) -> Self {
         Self {
-            description,
-            commands,
-            expected_responses,
+            description: description.into(),
+            commands: commands.into_iter().map(Into::into).collect(),
+            expected_responses: expected_responses.into_iter().map(Into::into).collect(),
             hint: None,
+            crash_hint: None,
             verbose: false,
+            all_mismatches: false,
             regex_match: false,
+            regex_lines: Vec::new(),
+            expectations: Vec::new(),
+            assertions: Vec::new(),
+            run_mode: RunMode::Batch,
+            timeouts: Timeouts::default(),
+            framing: FramingRules::new(),
+            normalization: Normalization::default(),
+            shrink: None,
+            from_reference: false,
+            tester_delays: false,
+            virtual_clock: false,
+        }
+    }
+    
+    /// 只给出命令，期望响应是参考实现（`SYSTEMQUEST_REFERENCE_DIR`）对同一组命令的输出
+    /// 
+    /// 用于手写期望不现实的长序列；未设置参考实现时运行会返回配置错误。
+    pub fn from_reference(description: impl Into<String>, commands: Vec<impl Into<String>>) -> Self {
+        let mut test_case = Self::new(description, commands, Vec::<String>::new());
+        test_case.from_reference = true;
+        test_case
+    }
+    
+    /// 只给出命令，期望响应由参考模型计算（见 `model::CacheModel`）
+    /// 
+    /// 不确定的响应（例如 CONCURRENT 之后的 GET）使用候选集合、范围等期望。
+    pub fn from_model(
+        description: impl Into<String>,
+        policy: EvictionPolicy,
+        commands: Vec<impl Into<String>>,
+    ) -> Result<Self, TesterError> {
+        let commands: Vec<String> = commands.into_iter().map(Into::into).collect();
+        let expected = CacheModel::expectations(policy, &commands)
+            .map_err(|e| TesterError::Configuration(format!("Cannot compute expected responses: {}", e)))?;
+        
+        let mut test_case = Self::new(description, commands, expected.iter().map(|line| line.to_string()).collect());
+        for (i, line) in expected.into_iter().enumerate() {
+            if !matches!(line, ExpectedLine::Exact(_)) {
+                test_case = test_case.with_expectation(i, line);
+            }
         }
+        Ok(test_case)
     }
     
     /// 添加提示信息（用于失败时的教学性提示）
-    pub fn with_hint(mut self, hint: &'static str) -> Self {
-        self.hint = Some(hint);
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+    
+    /// 添加崩溃提示（例如 Stage 4: 双向链表 unlink 时的指针错误）
+    pub fn with_crash_hint(mut self, hint: &'static str) -> Self {
+        self.crash_hint = Some(hint);
         self
     }
     
@@ -65,25 +178,129 @@ impl CacheTestCase {
         self
     }
     
+    /// 报告所有不匹配的响应（用于长命令序列，区分单个错误和系统性偏移）
+    pub fn with_all_mismatches(mut self) -> Self {
+        self.all_mismatches = true;
+        self
+    }
+    
     /// 启用正则表达式匹配（用于并发测试等输出不确定的场景）
     pub fn with_regex_match(mut self, enabled: bool) -> Self {
         self.regex_match = enabled;
         self
     }
     
+    /// 指定部分期望响应按正则表达式匹配（与精确匹配的行混用）
+    pub fn with_regex_lines(mut self, lines: &[usize]) -> Self {
+        self.regex_lines = lines.to_vec();
+        self
+    }
+    
+    /// 为第 index 条响应（从 0 开始）指定期望，例如 `ExpectedLine::range(0..=10)`
+    pub fn with_expectation(mut self, index: usize, expected: ExpectedLine) -> Self {
+        self.expectations.retain(|(i, _)| *i != index);
+        self.expectations.push((index, expected));
+        self
+    }
+    
+    /// 追加一条在逐行匹配之后检查的断言，例如 `PredicateAssertion::get_returns_last_put(policy)`
+    pub fn with_assertion(mut self, assertion: impl Assertion + 'static) -> Self {
+        self.assertions.push(Box::new(assertion));
+        self
+    }
+    
+    /// 设置命令执行模式（Batch 或 Interactive）
+    pub fn with_run_mode(mut self, run_mode: RunMode) -> Self {
+        self.run_mode = run_mode;
+        self
+    }
+    
+    /// 设置超时（例如为长时间运行的 CONCURRENT 测试放宽限制）
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+    
+    /// 设置多行响应的帧格式（例如 KEYS 使用 `*<n>` 计数头）
+    pub fn with_framing(mut self, framing: FramingRules) -> Self {
+        self.framing = framing;
+        self
+    }
+    
+    /// 设置规范化策略，例如 `Normalization::strict()` 要求输出逐字节一致
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+    
+    /// SLEEP 由测试器在命令之间等待，并检查实际经过的时间（改用交互模式）
+    /// 
+    /// TTL 测试因此不依赖学生的 SLEEP 实现（睡 0 秒、持锁睡眠等）；
+    /// SLEEP 本身由单独的测试检查。设置了 SYSTEMQUEST_VIRTUAL_CLOCK 时改用虚拟时钟
+    /// （见 `with_virtual_clock`），因此要放在按下标设置期望的 builder 之后。
+    pub fn with_tester_delays(mut self) -> Self {
+        self.tester_delays = true;
+        self.run_mode = RunMode::Interactive;
+        if clock::enabled() {
+            return self.with_virtual_clock();
+        }
+        self
+    }
+    
+    /// 改用虚拟时钟: INIT 之后冻结时钟，SLEEP 换成等长的 CLOCK ADVANCE（见 `clock`）
+    /// 
+    /// 测试只需几毫秒，过期时间精确到毫秒，不受机器负载影响；按批量模式运行。
+    /// 程序不支持 CLOCK FREEZE（可选扩展）时测试失败。
+    pub fn with_virtual_clock(mut self) -> Self {
+        for command in &mut self.commands {
+            if let Some(advance) = clock::replace_sleep(command) {
+                *command = advance;
+            }
+        }
+        
+        if !self.commands.iter().any(|command| command == clock::FREEZE) {
+            let at = self.commands.iter().position(|command| command.starts_with("INIT")).map_or(0, |i| i + 1);
+            self.commands.insert(at, clock::FREEZE.to_string());
+            if !self.from_reference {
+                self.expected_responses.insert(at, "OK".to_string());
+            }
+            // 插入位置之后的下标后移一位
+            for index in self.expectations.iter_mut().map(|(i, _)| i).chain(self.regex_lines.iter_mut()) {
+                if *index >= at {
+                    *index += 1;
+                }
+            }
+        }
+        
+        self.description = format!("{} (virtual clock)", self.description);
+        self.virtual_clock = true;
+        self.tester_delays = false;
+        self.run_mode = RunMode::Batch;
+        self
+    }
+    
+    /// 失败时缩减命令序列，在错误信息中附上最短的失败序列（用于长的生成序列）
+    /// 
+    /// 期望必须与参考模型一致（见 `model_conflicts`），缩减时按模型重新计算期望。
+    pub fn with_shrinking(mut self, policy: EvictionPolicy) -> Self {
+        self.shrink = Some(policy);
+        self
+    }
+    
     /// 运行测试用例
     /// 
     /// 工作流程:
-    /// 1. 创建 CommandRunner
+    /// 1. 根据 run_mode 创建 CommandRunner 或 InteractiveCommandRunner
     /// 2. 发送所有命令
     /// 3. 使用 Assertion 验证响应（提供友好的逐行输出）
-    /// 4. 返回结果
+    /// 4. 失败时（如果启用）缩减命令序列
+    /// 5. 返回结果
     pub fn run(&self, harness: &mut TestCaseHarness) -> Result<(), TesterError> {
         // 1. 日志: 开始测试
-        harness.logger.infof(self.description, &[]);
+        harness.logger.infof(&self.description, &[]);
         
         // 2. 验证测试用例有效性
-        if self.commands.len() != self.expected_responses.len() {
+        if !self.from_reference && self.commands.len() != self.expected_responses.len() {
             return Err(TesterError::Configuration(format!(
                 "Test case configuration error: commands count ({}) != expected responses count ({})",
                 self.commands.len(),
@@ -91,60 +308,281 @@ impl CacheTestCase {
             )));
         }
         
-        // 3. 创建 CommandRunner 并发送命令
-        let mut runner = CommandRunner::new(harness.executable.clone_executable());
-        let responses = runner.send_commands(&self.commands)?;
+        let spec = self.program_spec()?;
         
-        // 4. 使用 Assertion 验证响应
-        if self.regex_match {
-            // Regex 模式: 使用正则表达式匹配（用于并发测试等输出不确定的场景）
-            use regex::Regex;
-            
-            for (i, (actual, expected_pattern)) in responses.iter().zip(self.expected_responses.iter()).enumerate() {
-                let re = Regex::new(expected_pattern).map_err(|e| {
-                    TesterError::Configuration(format!(
-                        "Invalid regex pattern '{}': {}",
-                        expected_pattern, e
-                    ))
-                })?;
-                
-                if !re.is_match(actual) {
-                    let mut error_msg = format!(
-                        "Command {} failed: response '{}' does not match pattern '{}'\n\
-                        Command: {}",
-                        i + 1, actual, expected_pattern, self.commands[i]
-                    );
-                    
-                    if let Some(hint) = self.hint {
-                        error_msg.push_str(&format!("\n\nHint: {}", hint));
-                    }
-                    
-                    return Err(TesterError::User(error_msg.into()));
+        // 正则在启动程序之前编译（只编译一次，无效的模式立即报告）；
+        // 设置了参考实现时，期望来自它的输出或者必须接受它的输出
+        let expected = self.resolve_expectations(harness)?;
+        
+        if self.tester_delays {
+            harness.logger.infof("SLEEP lines are performed by the tester (not sent to your program)", &[]);
+        }
+        
+        // 3. 创建 Runner 并发送命令（stderr 和退出状态总是保留）
+        let output = match self.execute(&spec) {
+            Ok(output) => output,
+            Err(err) => {
+                usage::record_result(&self.description, false, None);
+                return Err(self.shrink_failure(err, &spec, harness));
+            }
+        };
+        
+        if let Some(usage) = &output.usage {
+            harness.logger.infof(&format!("Resource usage: {}", usage), &[]);
+        }
+        
+        // 程序不支持虚拟时钟时，后续响应都没有意义（也不需要缩减）
+        if self.virtual_clock {
+            if let Err(err) = clock::check_supported(&self.commands, &output.responses) {
+                usage::record_result(&self.description, false, output.usage.as_ref());
+                return Err(err);
+            }
+        }
+        
+        let expected = self.allow_for_delays(expected, &output, &harness.logger);
+        
+        // 4. 验证响应，并把结果（含资源使用情况）写入结果文件
+        let result = self.verify(&expected, &output, harness);
+        usage::record_result(&self.description, result.is_ok(), output.usage.as_ref());
+        result.map_err(|err| self.shrink_failure(err, &spec, harness))
+    }
+    
+    /// 缩减失败的命令序列，把最短的失败序列附加在原来的错误之后
+    /// 
+    /// 缩减本身出错或没有得到更短的序列时，原样返回错误。
+    fn shrink_failure(&self, err: TesterError, spec: &ProgramSpec, harness: &mut TestCaseHarness) -> TesterError {
+        let Some(policy) = self.shrink else {
+            return err;
+        };
+        let replayable = self.run_mode == RunMode::Batch || self.tester_delays;
+        if !replayable || !matches!(err, TesterError::User(_)) {
+            return err;
+        }
+        
+        harness.logger.infof(&format!("Shrinking the failing sequence of {} commands...", self.commands.len()), &[]);
+        let shrunk = Shrinker::new(spec.clone(), policy)
+            .with_tester_delays(self.tester_delays)
+            .with_timeouts(self.timeouts)
+            .with_normalization(self.normalization)
+            .shrink(&self.commands);
+        match shrunk {
+            Ok(Some(shrunk)) if shrunk.commands.len() < self.commands.len() => {
+                for line in shrunk.to_string().lines() {
+                    harness.logger.infof(line, &[]);
                 }
-                
-                harness.logger.debugf(&format!(
-                    "✓ Command {}: {} → {} (matched pattern)",
-                    i + 1,
-                    self.commands[i],
-                    actual
-                ), &[]);
+                TesterError::User(format!("{}\n\n{}", err, shrunk).into())
             }
-            
-            harness.logger.successf(&format!("✓ {}", self.description), &[]);
-        } else if self.verbose {
+            Ok(_) => err,
+            Err(shrink_err) => {
+                harness.logger.infof(&format!("Could not shrink the failing sequence: {}", shrink_err), &[]);
+                err
+            }
+        }
+    }
+    
+    /// 测试器侧等待比 SLEEP 长时（机器过载），多出的时间计入依赖时间的期望
+    /// 
+    /// 模型按请求的时间和实际经过的时间（`RunOutput::timed_commands`）各算一遍期望，
+    /// 两者不同的行改用按实际时间算出的期望。TTL 测试从 Stage 6 开始，淘汰策略总是 LRU。
+    fn allow_for_delays(
+        &self,
+        mut expected: Vec<ExpectedLine>,
+        output: &RunOutput,
+        logger: &Logger,
+    ) -> Vec<ExpectedLine> {
+        if output.delays.is_empty() {
+            return expected;
+        }
+        let timed_commands = output.timed_commands(&self.commands);
+        let (Ok(requested), Ok(timed)) = (
+            CacheModel::expectations(EvictionPolicy::Lru, &self.commands),
+            CacheModel::expectations(EvictionPolicy::Lru, &timed_commands),
+        ) else {
+            return expected;
+        };
+        
+        let mut adjusted = 0;
+        for (i, (requested, timed)) in requested.iter().zip(timed).enumerate() {
+            if i < expected.len() && requested.to_string() != timed.to_string() {
+                expected[i] = timed;
+                adjusted += 1;
+            }
+        }
+        if adjusted > 0 {
+            logger.infof(&format!(
+                "Note: the tester's pauses took longer than requested ({}); adjusted {} timing-dependent expected responses",
+                output.delays.iter()
+                    .map(|(i, elapsed)| format!("{} took {:.2}s", self.commands[*i], elapsed.as_secs_f64()))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                adjusted
+            ), &[]);
+        }
+        expected
+    }
+    
+    /// 启动程序并发送全部命令
+    fn execute(&self, spec: &ProgramSpec) -> Result<RunOutput, TesterError> {
+        let commands: Vec<&str> = self.commands.iter().map(|s| s.as_str()).collect();
+        match self.run_mode {
+            RunMode::Batch => {
+                let mut runner = CommandRunner::new(spec.clone())
+                    .with_timeouts(self.timeouts);
+                runner.run(&commands)
+            }
+            RunMode::Interactive if self.tester_delays => {
+                InteractiveCommandRunner::run_with_tester_delays(spec, self.timeouts, &commands)
+            }
+            RunMode::Interactive => {
+                let mut runner = InteractiveCommandRunner::start(spec, self.timeouts)?;
+                runner.send_commands(&commands)?;
+                runner.finish()
+            }
+        }
+    }
+    
+    /// 学生程序的启动描述（帧格式和崩溃提示来自测试用例）
+    fn program_spec(&self) -> Result<ProgramSpec, TesterError> {
+        let mut spec = ProgramSpec::from_env()?.with_framing(self.framing.clone());
+        if let Some(hint) = self.crash_hint {
+            spec = spec.with_crash_hint(hint);
+        }
+        Ok(spec)
+    }
+    
+    /// 每条响应的期望，并与参考实现（如果设置了 SYSTEMQUEST_REFERENCE_DIR）对照
+    /// 
+    /// - from_reference: 参考实现的输出就是期望（精确匹配）
+    /// - 其他: 手写的期望必须接受参考实现的输出，否则是测试本身的错误（配置错误）
+    fn resolve_expectations(&self, harness: &mut TestCaseHarness) -> Result<Vec<ExpectedLine>, TesterError> {
+        let reference = Reference::from_env()?.map(|reference| {
+            reference
+                .with_framing(self.framing.clone())
+                .with_timeouts(self.timeouts)
+                .with_normalization(self.normalization)
+        });
+        
+        if self.from_reference {
+            let Some(reference) = reference else {
+                return Err(TesterError::Configuration(format!(
+                    "'{}' takes its expected responses from a reference implementation, but {} is not set",
+                    self.description, REFERENCE_DIR_ENV
+                )));
+            };
+            let responses = reference.responses(&self.commands)?;
+            return Ok(responses.into_iter().map(ExpectedLine::exact).collect());
+        }
+        
+        let expected = self.expected_lines()?;
+        if let Some(reference) = reference {
+            let responses = reference.responses(&self.commands)?;
+            let disagreements = differential::disagreements(&self.commands, &expected, &responses);
+            if !disagreements.is_empty() {
+                return Err(TesterError::Configuration(format!(
+                    "The expected responses disagree with the reference implementation:\n  {}",
+                    disagreements.join("\n  ")
+                )));
+            }
+            harness.logger.debugf("✓ Expected responses agree with the reference implementation", &[]);
+        }
+        Ok(expected)
+    }
+    
+    /// 每条期望响应的匹配方式
+    /// 
+    /// - expectations 中的行: 使用指定的期望
+    /// - 正则行: 按正则表达式匹配
+    /// - STATS 命令: 按字段比较并检查不变式（期望值可以用 `*` / `>=N`，见 StatsAssertion）
+    /// - 其他: 精确匹配
+    fn expected_lines(&self) -> Result<Vec<ExpectedLine>, TesterError> {
+        self.expected_responses
+            .iter()
+            .enumerate()
+            .map(|(i, expected)| {
+                let is_stats = self.commands.get(i)
+                    .is_some_and(|command| command.split_whitespace().next() == Some("STATS"));
+                if let Some((_, expected)) = self.expectations.iter().find(|(index, _)| *index == i) {
+                    Ok(expected.clone())
+                } else if self.regex_match || self.regex_lines.contains(&i) {
+                    ExpectedLine::pattern(expected)
+                } else if is_stats {
+                    StatsAssertion::from_expected(expected).map(ExpectedLine::Stats)
+                } else {
+                    Ok(ExpectedLine::exact(expected.as_str()))
+                }
+            })
+            .collect()
+    }
+    
+    /// 与参考模型比较手写的期望，返回每处不一致的说明（用于 cargo test）
+    pub fn model_conflicts(&self, policy: EvictionPolicy) -> Result<Vec<String>, TesterError> {
+        let expected = self.expected_lines()?;
+        model::conflicts(policy, &self.commands, &expected)
+            .map_err(|e| TesterError::Configuration(format!("Cannot compute expected responses: {}", e)))
+    }
+    
+    /// 按规范化策略处理响应后验证
+    /// 
+    /// 规范化能消除的差异（"\r"、首尾空白、关键字大小写）总会被明确指出:
+    /// 失败时附加在错误信息中，通过时以提示的形式记录。
+    fn verify(
+        &self,
+        expected: &[ExpectedLine],
+        output: &RunOutput,
+        harness: &mut TestCaseHarness,
+    ) -> Result<(), TesterError> {
+        let responses: Vec<Response> = output.responses.iter()
+            .map(|response| self.normalization.apply_response(response))
+            .collect();
+        let notes = normalization_notes(expected, &output.responses);
+        
+        match self.verify_responses(expected, &responses, output, harness) {
+            Ok(()) => {
+                for note in &notes {
+                    harness.logger.infof(&format!("Note: {} (ignored by this test)", note), &[]);
+                }
+                Ok(())
+            }
+            Err(err) if notes.is_empty() => Err(self.add_hint(err)),
+            Err(err) => {
+                let mut message = format!("{}\n\nSome responses differ only in formatting:", err);
+                for note in &notes {
+                    message.push_str(&format!("\n  - {}", note));
+                }
+                Err(self.add_hint(TesterError::User(message.into())))
+            }
+        }
+    }
+    
+    /// 使用 Assertion 验证（已规范化的）响应
+    fn verify_responses(
+        &self,
+        expected: &[ExpectedLine],
+        responses: &[Response],
+        output: &RunOutput,
+        harness: &mut TestCaseHarness,
+    ) -> Result<(), TesterError> {
+        if self.verbose {
             // Verbose 模式: 使用旧的验证逻辑（保留向后兼容）
-            for (i, (actual, expected)) in responses.iter().zip(self.expected_responses.iter()).enumerate() {
-                if actual != expected {
-                    let mut error_msg = format!(
+            if responses.len() != self.commands.len() {
+                return Err(TesterError::User(format!(
+                    "Expected {} responses, got {}. Output: {:?}\n\n{}",
+                    self.commands.len(),
+                    responses.len(),
+                    responses,
+                    output.stderr_block()
+                ).into()));
+            }
+            
+            for (i, (actual, expected_line)) in responses.iter().zip(expected.iter()).enumerate() {
+                let actual = actual.text();
+                if !expected_line.matches(&actual) {
+                    let error_msg = format!(
                         "Command {} failed: expected '{}', got '{}'\n\
-                        Command: {}",
-                        i + 1, expected, actual, self.commands[i]
+                        Command: {}\n\n{}",
+                        i + 1, expected_line, actual, self.commands[i], output.stderr_block()
                     );
                     
-                    if let Some(hint) = self.hint {
-                        error_msg.push_str(&format!("\n\nHint: {}", hint));
-                    }
-                    
                     return Err(TesterError::User(error_msg.into()));
                 }
                 
@@ -156,32 +594,83 @@ impl CacheTestCase {
                 ), &[]);
             }
             
+            self.verify_assertions(responses, harness)?;
             harness.logger.successf(&format!("✓ {}", self.description), &[]);
+            return Ok(());
+        }
+        
+        // 默认模式: 使用 Assertion 抽象（友好的逐行输出）
+        // 有正则 / STATS 行时使用 RegexAssertion（各种行可以混用），否则使用 ExactMatchAssertion
+        let commands: Vec<String> = self.commands.iter()
+            .map(|s| s.to_string())
+            .collect();
+        
+        let assertion: Box<dyn Assertion> = if expected.iter().any(|line| !matches!(line, ExpectedLine::Exact(_))) {
+            let assertion = RegexAssertion::new(expected.to_vec())
+                .with_commands(commands)
+                .with_stderr(output.stderr_block());
+            if self.all_mismatches {
+                Box::new(assertion.with_all_mismatches())
+            } else {
+                Box::new(assertion)
+            }
         } else {
-            // 默认模式: 使用 Assertion 抽象（友好的逐行输出）
-            let expected: Vec<String> = self.expected_responses.iter()
-                .map(|s| s.to_string())
-                .collect();
-            
-            let commands: Vec<String> = self.commands.iter()
-                .map(|s| s.to_string())
+            // 全部是精确匹配的行（from_reference 的期望只在这里）
+            let expected: Vec<String> = expected.iter()
+                .map(|line| line.to_string())
                 .collect();
-            
             let assertion = ExactMatchAssertion::new(expected)
-                .with_commands(commands);
-            
-            // 使用 Assertion 验证，如果失败会添加 Hint
-            assertion.verify(&responses, &harness.logger).map_err(|err| {
-                if let Some(hint) = self.hint {
-                    TesterError::User(format!("{}\n\nHint: {}", err, hint).into())
-                } else {
-                    err
-                }
-            })?;
+                .with_commands(commands)
+                .with_stderr(output.stderr_block());
+            if self.all_mismatches {
+                Box::new(assertion.with_all_mismatches())
+            } else {
+                Box::new(assertion)
+            }
+        };
+        
+        assertion.verify(responses, &harness.logger)?;
+        self.verify_assertions(responses, harness)
+    }
+    
+    /// 用完整的命令/响应记录检查额外的断言
+    fn verify_assertions(&self, responses: &[Response], harness: &mut TestCaseHarness) -> Result<(), TesterError> {
+        if self.assertions.is_empty() {
+            return Ok(());
         }
         
+        let transcript = Transcript::new(
+            self.commands.iter().map(|s| s.to_string()).collect(),
+            responses.to_vec(),
+        );
+        for assertion in &self.assertions {
+            assertion.verify_transcript(&transcript, &harness.logger)?;
+        }
         Ok(())
     }
+    
+    /// 失败时附加 Hint
+    fn add_hint(&self, err: TesterError) -> TesterError {
+        match &self.hint {
+            Some(hint) => TesterError::User(format!("{}\n\nHint: {}", err, hint).into()),
+            None => err,
+        }
+    }
+}
+
+/// 规范化能消除的差异，每种问题只报告一次（附带第一次出现的响应编号）
+fn normalization_notes(expected: &[ExpectedLine], raw: &[Response]) -> Vec<String> {
+    let mut issues: Vec<(usize, String)> = Vec::new();
+    for (i, (expected_line, response)) in expected.iter().zip(raw).enumerate() {
+        for issue in normalize::diagnose(&response.text(), |line| expected_line.matches(line)) {
+            if !issues.iter().any(|(_, seen)| *seen == issue) {
+                issues.push((i, issue));
+            }
+        }
+    }
+    issues.into_iter()
+        .map(|(i, issue)| format!("response #{}: {}", i + 1, issue))
+        .collect()
 }
 
 /// CacheTestCaseBuilder - 构建器模式 (可选，提供更流畅的 API)
@@ -202,36 +691,115 @@ impl CacheTestCase {
 /// # }
 /// ```
 pub struct CacheTestCaseBuilder {
-    description: Option<&'static str>,
-    commands: Option<Vec<&'static str>>,
-    expected_responses: Option<Vec<&'static str>>,
-    hint: Option<&'static str>,
+    description: Option<String>,
+    commands: Option<Vec<String>>,
+    expected_responses: Option<Vec<String>>,
+    regex_lines: Vec<usize>,
+    expectations: Vec<(usize, ExpectedLine)>,
+    assertions: Vec<Box<dyn Assertion>>,
+    hint: Option<String>,
+    crash_hint: Option<&'static str>,
     verbose: bool,
+    all_mismatches: bool,
+    run_mode: RunMode,
+    timeouts: Timeouts,
+    framing: FramingRules,
+    normalization: Normalization,
 }
 
 impl CacheTestCaseBuilder {
-    pub fn new(description: &'static str) -> Self {
+    pub fn new(description: impl Into<String>) -> Self {
         Self {
-            description: Some(description),
+            description: Some(description.into()),
             commands: None,
             expected_responses: None,
+            regex_lines: Vec::new(),
+            expectations: Vec::new(),
+            assertions: Vec::new(),
             hint: None,
+            crash_hint: None,
             verbose: false,
+            all_mismatches: false,
+            run_mode: RunMode::Batch,
+            timeouts: Timeouts::default(),
+            framing: FramingRules::new(),
+            normalization: Normalization::default(),
         }
     }
     
-    pub fn commands(mut self, commands: Vec<&'static str>) -> Self {
-        self.commands = Some(commands);
+    pub fn commands(mut self, commands: Vec<impl Into<String>>) -> Self {
+        self.commands = Some(commands.into_iter().map(Into::into).collect());
+        self
+    }
+    
+    /// 追加一条命令（用于在循环中生成命令序列）
+    pub fn command(mut self, command: impl Into<String>) -> Self {
+        self.commands.get_or_insert_with(Vec::new).push(command.into());
+        self
+    }
+    
+    pub fn expect(mut self, expected_responses: Vec<impl Into<String>>) -> Self {
+        self.expected_responses = Some(expected_responses.into_iter().map(Into::into).collect());
+        self
+    }
+    
+    /// 所有期望响应都是正则表达式
+    pub fn expect_regex(mut self, patterns: Vec<impl Into<String>>) -> Self {
+        self.regex_lines = (0..patterns.len()).collect();
+        self.expected_responses = Some(patterns.into_iter().map(Into::into).collect());
+        self
+    }
+    
+    /// 追加一条精确匹配的期望响应
+    pub fn expect_line(mut self, line: impl Into<String>) -> Self {
+        self.expected_responses.get_or_insert_with(Vec::new).push(line.into());
+        self
+    }
+    
+    /// 追加一条按正则表达式匹配的期望响应
+    pub fn expect_pattern(mut self, pattern: impl Into<String>) -> Self {
+        let expected = self.expected_responses.get_or_insert_with(Vec::new);
+        self.regex_lines.push(expected.len());
+        expected.push(pattern.into());
+        self
+    }
+    
+    /// 追加一条任意形式的期望响应（expected_responses 中只留空占位）
+    pub fn expect_matching(mut self, line: ExpectedLine) -> Self {
+        let expected = self.expected_responses.get_or_insert_with(Vec::new);
+        self.expectations.push((expected.len(), line));
+        expected.push(String::new());
+        self
+    }
+    
+    /// 追加一条整数范围期望，例如 SIZE 在 `0..=capacity` 之间
+    pub fn expect_range(self, range: RangeInclusive<i64>) -> Self {
+        self.expect_matching(ExpectedLine::range(range))
+    }
+    
+    /// 追加一条候选集合期望，例如 `&["1", "NULL"]`
+    pub fn expect_one_of(self, responses: &[&str]) -> Self {
+        self.expect_matching(ExpectedLine::one_of(responses))
+    }
+    
+    /// 追加一条数值容差期望
+    pub fn expect_approx(self, value: f64, tolerance: f64) -> Self {
+        self.expect_matching(ExpectedLine::approx(value, tolerance))
+    }
+    
+    /// 追加一条在逐行匹配之后检查的断言
+    pub fn assertion(mut self, assertion: impl Assertion + 'static) -> Self {
+        self.assertions.push(Box::new(assertion));
         self
     }
     
-    pub fn expect(mut self, expected_responses: Vec<&'static str>) -> Self {
-        self.expected_responses = Some(expected_responses);
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
         self
     }
     
-    pub fn hint(mut self, hint: &'static str) -> Self {
-        self.hint = Some(hint);
+    pub fn crash_hint(mut self, hint: &'static str) -> Self {
+        self.crash_hint = Some(hint);
         self
     }
     
@@ -240,14 +808,52 @@ impl CacheTestCaseBuilder {
         self
     }
     
+    pub fn all_mismatches(mut self) -> Self {
+        self.all_mismatches = true;
+        self
+    }
+    
+    pub fn interactive(mut self) -> Self {
+        self.run_mode = RunMode::Interactive;
+        self
+    }
+    
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+    
+    pub fn framing(mut self, framing: FramingRules) -> Self {
+        self.framing = framing;
+        self
+    }
+    
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+    
     pub fn build(self) -> CacheTestCase {
         CacheTestCase {
             description: self.description.expect("description is required"),
             commands: self.commands.expect("commands are required"),
             expected_responses: self.expected_responses.expect("expected_responses are required"),
             hint: self.hint,
+            crash_hint: self.crash_hint,
             verbose: self.verbose,
+            all_mismatches: self.all_mismatches,
             regex_match: false,
+            regex_lines: self.regex_lines,
+            expectations: self.expectations,
+            assertions: self.assertions,
+            run_mode: self.run_mode,
+            timeouts: self.timeouts,
+            framing: self.framing,
+            normalization: self.normalization,
+            shrink: None,
+            from_reference: false,
+            tester_delays: false,
+            virtual_clock: false,
         }
     }
 }
@@ -325,6 +931,8 @@ impl MultiCacheTestCase {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::assertions::PredicateAssertion;
+    use std::time::Duration;
     
     #[test]
     fn test_cache_test_case_creation() {
@@ -339,6 +947,7 @@ mod tests {
         assert_eq!(test_case.expected_responses.len(), 2);
         assert_eq!(test_case.hint, None);
         assert_eq!(test_case.verbose, false);
+        assert_eq!(test_case.run_mode, RunMode::Batch);
     }
     
     #[test]
@@ -349,7 +958,7 @@ mod tests {
             vec!["OK"],
         ).with_hint("This is a hint");
         
-        assert_eq!(test_case.hint, Some("This is a hint"));
+        assert_eq!(test_case.hint.as_deref(), Some("This is a hint"));
     }
     
     #[test]
@@ -365,6 +974,93 @@ mod tests {
         assert_eq!(test_case.verbose, true);
     }
     
+    #[test]
+    fn test_cache_test_case_builder_interactive() {
+        let test_case = CacheTestCaseBuilder::new("Test")
+            .commands(vec!["INIT 10"])
+            .expect(vec!["OK"])
+            .interactive()
+            .build();
+        
+        assert_eq!(test_case.run_mode, RunMode::Interactive);
+    }
+    
+    #[test]
+    fn test_cache_test_case_builder_mixed_regex() {
+        let test_case = CacheTestCaseBuilder::new("Test")
+            .commands(vec!["INIT 10", "CONCURRENT 5 MIXED", "SIZE"])
+            .expect_line("OK")
+            .expect_line("OK")
+            .expect_pattern(r"[0-9]|10")
+            .build();
+        
+        assert_eq!(test_case.expected_responses, vec!["OK", "OK", r"[0-9]|10"]);
+        assert_eq!(test_case.regex_lines, vec![2]);
+        
+        let expected = test_case.expected_lines().unwrap();
+        assert!(expected[0].matches("OK") && !expected[0].matches("OK!"));
+        assert!(expected[2].matches("10") && !expected[2].matches("11"));
+    }
+    
+    #[test]
+    fn test_cache_test_case_nondeterministic_expectations() {
+        let test_case = CacheTestCaseBuilder::new("Test")
+            .commands(vec!["INIT 2", "CONCURRENT 5 MIXED", "GET a", "SIZE"])
+            .expect_line("OK")
+            .expect_line("OK")
+            .expect_one_of(&["1", "NULL"])
+            .expect_range(0..=2)
+            .build();
+        assert_eq!(test_case.expected_responses.len(), 4);
+        
+        let expected = test_case.expected_lines().unwrap();
+        assert!(expected[2].matches("NULL") && !expected[2].matches("2"));
+        assert!(expected[3].matches("2") && !expected[3].matches("3"));
+        
+        let test_case = CacheTestCase::new("Test", vec!["SIZE"], vec!["0..=5"])
+            .with_expectation(0, ExpectedLine::range(0..=5));
+        assert!(test_case.expected_lines().unwrap()[0].matches("4"));
+    }
+    
+    #[test]
+    fn test_cache_test_case_builder_assertions() {
+        let test_case = CacheTestCaseBuilder::new("Test")
+            .commands(vec!["INIT 2", "PUT a 1", "GET a"])
+            .expect(vec!["OK", "OK", "1"])
+            .assertion(PredicateAssertion::get_returns_last_put(EvictionPolicy::Lru))
+            .build();
+        assert_eq!(test_case.assertions.len(), 1);
+        
+        let test_case = CacheTestCase::new("Test", vec!["INIT 2"], vec!["OK"])
+            .with_assertion(PredicateAssertion::new("always holds", |_| Ok(())));
+        assert_eq!(test_case.assertions.len(), 1);
+    }
+    
+    #[test]
+    fn test_normalization_notes() {
+        let expected = vec![ExpectedLine::exact("OK"), ExpectedLine::exact("NULL"), ExpectedLine::exact("OK")];
+        let raw = vec![Response::from("OK\r"), Response::from("null\r"), Response::from("OK\r")];
+        
+        let notes = normalization_notes(&expected, &raw);
+        assert_eq!(notes.len(), 2);
+        assert!(notes[0].starts_with("response #1: your output has a trailing \\r"));
+        assert_eq!(notes[1], "response #2: keywords are case-sensitive: print 'NULL', not 'null'");
+        
+        let test_case = CacheTestCaseBuilder::new("Test")
+            .commands(vec!["GET a"])
+            .expect(vec!["NULL"])
+            .normalization(Normalization::lenient())
+            .build();
+        assert_eq!(test_case.normalization, Normalization::lenient());
+        assert_eq!(CacheTestCase::new("Test", vec!["INIT 1"], vec!["OK"]).normalization, Normalization::default());
+    }
+    
+    #[test]
+    fn test_cache_test_case_invalid_regex() {
+        let test_case = CacheTestCase::new("Test", vec!["SIZE"], vec!["(\\d"]).with_regex_match(true);
+        assert!(matches!(test_case.expected_lines(), Err(TesterError::Configuration(_))));
+    }
+    
     #[test]
     fn test_multi_cache_test_case_creation() {
         let multi_test = MultiCacheTestCase::new(vec![
@@ -374,4 +1070,115 @@ mod tests {
         
         assert_eq!(multi_test.test_cases.len(), 2);
     }
+    
+    #[test]
+    fn test_generated_test_cases() {
+        let commands: Vec<String> = (0..100).map(|i| format!("PUT k{} {}", i, i)).collect();
+        let expected = vec!["OK"; 100];
+        let generated = CacheTestCase::new(format!("Generated {} PUTs", commands.len()), commands, expected);
+        assert_eq!(generated.commands[99], "PUT k99 99");
+        assert_eq!(generated.description, "Generated 100 PUTs");
+        
+        let mut builder = CacheTestCaseBuilder::new("Generated GETs");
+        for i in 0..3 {
+            builder = builder.command(format!("GET k{}", i)).expect_line(i.to_string());
+        }
+        let built = builder.expect_pattern(String::from("\\d+")).build();
+        assert_eq!(built.commands, vec!["GET k0", "GET k1", "GET k2"]);
+        assert_eq!(built.expected_responses.len(), 4);
+        assert_eq!(built.regex_lines, vec![3]);
+        
+        let multi_test = MultiCacheTestCase::new(vec![
+            CacheTestCase::new("Static", vec!["INIT 5"], vec!["OK"]),
+            generated,
+            built,
+        ]);
+        assert_eq!(multi_test.test_cases.len(), 3);
+    }
+    
+    #[test]
+    fn test_cache_test_case_from_model() {
+        let test_case = CacheTestCase::from_model(
+            "Model",
+            EvictionPolicy::Lru,
+            vec!["INIT 2", "PUT a 1", "CONCURRENT 5 MIXED", "GET a", "SIZE"],
+        ).unwrap();
+        assert_eq!(test_case.expected_responses, vec!["OK", "OK", "OK", "1|NULL", "2"]);
+        assert_eq!(test_case.expectations.len(), 1);
+        assert!(test_case.model_conflicts(EvictionPolicy::Lru).unwrap().is_empty());
+        
+        let wrong = CacheTestCase::new("Wrong", vec!["INIT 2", "PUT a 1", "GET a"], vec!["OK", "OK", "NULL"]);
+        assert_eq!(wrong.model_conflicts(EvictionPolicy::Lru).unwrap().len(), 1);
+        
+        assert!(matches!(
+            CacheTestCase::from_model("Unknown", EvictionPolicy::Lru, vec!["FLY"]),
+            Err(TesterError::Configuration(_))
+        ));
+    }
+    
+    #[test]
+    fn test_cache_test_case_from_reference() {
+        let test_case = CacheTestCase::from_reference("Reference", vec!["INIT 2", "PUT a 1", "GET a"]);
+        assert!(test_case.from_reference);
+        assert_eq!(test_case.commands.len(), 3);
+        assert!(test_case.expected_responses.is_empty());
+        assert!(!CacheTestCase::new("Manual", vec!["INIT 1"], vec!["OK"]).from_reference);
+    }
+    
+    #[test]
+    fn test_cache_test_case_virtual_clock() {
+        let test_case = CacheTestCase::new(
+            "TTL",
+            vec!["INIT 1", "PUT a 1 1", "SLEEP 1.5", "GET a"],
+            vec!["OK", "OK", "OK", "NULL"],
+        )
+        .with_expectation(3, ExpectedLine::one_of(&["NULL"]))
+        .with_tester_delays()
+        .with_virtual_clock();
+        
+        assert_eq!(test_case.commands, vec!["INIT 1", "CLOCK FREEZE", "PUT a 1 1", "CLOCK ADVANCE 1500ms", "GET a"]);
+        assert_eq!(test_case.expected_responses, vec!["OK", "OK", "OK", "OK", "NULL"]);
+        assert_eq!(test_case.expectations[0].0, 4);
+        assert_eq!(test_case.run_mode, RunMode::Batch);
+        assert!(test_case.virtual_clock && !test_case.tester_delays);
+        assert!(test_case.model_conflicts(EvictionPolicy::Lru).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_cache_test_case_tester_delays() {
+        let test_case = CacheTestCase::new("TTL", vec!["INIT 1", "SLEEP 1"], vec!["OK", "OK"]);
+        assert!(!test_case.tester_delays);
+        
+        let test_case = test_case.with_tester_delays();
+        assert!(test_case.tester_delays);
+        assert_eq!(test_case.run_mode, RunMode::Interactive);
+    }
+    
+    #[test]
+    fn test_allow_for_delays() {
+        let test_case = CacheTestCase::new(
+            "TTL",
+            vec!["INIT 1", "PUT a 1 2", "SLEEP 1", "GET a"],
+            vec!["OK", "OK", "OK", "1"],
+        )
+        .with_tester_delays();
+        let mut output = RunOutput {
+            responses: Vec::new(),
+            stderr: String::new(),
+            exit_status: None,
+            usage: None,
+            delays: vec![(2, Duration::from_millis(1010))],
+        };
+        let quiet = Logger::get_quiet_logger("");
+        
+        // 多出的时间不影响期望
+        let expected = test_case.allow_for_delays(test_case.expected_lines().unwrap(), &output, &quiet);
+        assert!(!expected[3].matches("NULL"));
+        
+        // 等待了 1.6s: 条目可能已经过期
+        output.delays = vec![(2, Duration::from_millis(1600))];
+        let expected = test_case.allow_for_delays(test_case.expected_lines().unwrap(), &output, &quiet);
+        assert!(expected[3].matches("1") && expected[3].matches("NULL"));
+        assert!(expected[0].matches("OK"));
+    }
 }
@@ -0,0 +1,152 @@
+use std::env;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::process::ExitStatus;
+use std::thread;
+use std::time::{Duration, Instant};
+use tester_utils::TesterError;
+
+/// 选择传输方式的环境变量（stdin / tcp / resp / http）
+pub const TRANSPORT_ENV: &str = "SYSTEMQUEST_TRANSPORT";
+
+/// 测试器分配给服务器的端口，通过该环境变量传给 your_program.sh
+pub const PORT_ENV: &str = "SYSTEMQUEST_CACHE_PORT";
+
+/// 服务器启动（开始监听端口）的最长等待时间
+pub const SERVER_STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Transport - 测试器与学生程序之间交换命令的方式
+///
+/// 所有 Stage 的命令和期望响应都与传输方式无关:
+/// - Stdin: 命令写入 stdin，响应从 stdout 按行读取（默认）
+/// - Tcp: 程序作为服务器监听 `SYSTEMQUEST_CACHE_PORT`，
+///   同样的 INIT/PUT/GET/SIZE/STATS 行协议通过本地 TCP 连接交换
+/// - Resp: 同样监听端口，但命令编码为 RESP 数组，回复按 RESP 解码后
+///   规范化为 "OK" / 值 / "NULL" 等文本形式（见 `resp::RespValue::to_response_line`）
+/// - Http: 程序作为 REST 服务监听端口，命令映射为 HTTP 请求，
+///   响应同样规范化为文本形式（见 `http::HttpCommand`）
+///
+/// 通过 `SYSTEMQUEST_TRANSPORT=tcp` / `resp` / `http` 让所有 Stage 以服务器模式运行。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transport {
+    #[default]
+    Stdin,
+    Tcp,
+    Resp,
+    Http,
+}
+
+impl Transport {
+    /// 从 SYSTEMQUEST_TRANSPORT 读取传输方式（未设置时为 Stdin）
+    pub fn from_env() -> Result<Self, TesterError> {
+        match env::var(TRANSPORT_ENV) {
+            Ok(value) => Self::parse(&value),
+            Err(_) => Ok(Self::Stdin),
+        }
+    }
+
+    /// 解析传输方式名称
+    pub fn parse(value: &str) -> Result<Self, TesterError> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "stdin" => Ok(Self::Stdin),
+            "tcp" => Ok(Self::Tcp),
+            "resp" => Ok(Self::Resp),
+            "http" => Ok(Self::Http),
+            other => Err(TesterError::Configuration(format!(
+                "Unknown {} '{}' (expected one of: stdin, tcp, resp, http)",
+                TRANSPORT_ENV, other
+            ))),
+        }
+    }
+
+    /// 是否以服务器模式运行（程序监听端口，而不是读 stdin）
+    pub fn is_server(self) -> bool {
+        !matches!(self, Self::Stdin)
+    }
+}
+
+/// 分配一个当前空闲的本地端口
+pub(crate) fn free_port() -> Result<u16, TesterError> {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| TesterError::Configuration(format!("Failed to allocate a port: {}", e)))
+}
+
+/// 等待程序开始监听端口并建立连接
+///
+/// `exited` 检查程序是否已经退出（返回退出状态）。
+/// 程序在监听之前退出、或超过 deadline 仍未监听时返回错误。
+pub(crate) fn connect(
+    port: u16,
+    mut exited: impl FnMut() -> Option<ExitStatus>,
+    deadline: Instant,
+) -> Result<TcpStream, TesterError> {
+    loop {
+        match TcpStream::connect((Ipv4Addr::LOCALHOST, port)) {
+            Ok(stream) => {
+                let _ = stream.set_nodelay(true);
+                return Ok(stream);
+            }
+            Err(e) if e.kind() != ErrorKind::ConnectionRefused => {
+                return Err(TesterError::Configuration(format!(
+                    "Failed to connect to 127.0.0.1:{}: {}",
+                    port, e
+                )));
+            }
+            Err(_) => {}
+        }
+
+        if let Some(status) = exited() {
+            return Err(TesterError::User(format!(
+                "Program exited ({}) before listening on port {} (passed via {})",
+                status, port, PORT_ENV
+            ).into()));
+        }
+
+        if Instant::now() >= deadline {
+            return Err(TesterError::User(format!(
+                "Program did not start listening on port {} (passed via {}) in time. \
+                Make sure your server reads the port from {} and binds to 127.0.0.1.",
+                port, PORT_ENV, PORT_ENV
+            ).into()));
+        }
+
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试传输方式解析
+    #[test]
+    fn test_parse_transport() {
+        assert_eq!(Transport::parse("stdin").unwrap(), Transport::Stdin);
+        assert_eq!(Transport::parse("TCP").unwrap(), Transport::Tcp);
+        assert_eq!(Transport::parse("resp").unwrap(), Transport::Resp);
+        assert_eq!(Transport::parse("http").unwrap(), Transport::Http);
+        assert_eq!(Transport::parse("").unwrap(), Transport::Stdin);
+        assert!(Transport::parse("carrier-pigeon").is_err());
+    }
+
+    /// 测试服务器模式判断
+    #[test]
+    fn test_is_server() {
+        assert!(!Transport::Stdin.is_server());
+        assert!(Transport::Tcp.is_server());
+        assert!(Transport::Resp.is_server());
+        assert!(Transport::Http.is_server());
+    }
+
+    /// 测试空闲端口分配
+    #[test]
+    fn test_free_port() {
+        assert!(free_port().unwrap() > 0);
+    }
+}
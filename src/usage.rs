@@ -0,0 +1,165 @@
+use std::env;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::Duration;
+use serde_json::json;
+
+/// 机器可读结果文件（JSON Lines，每个测试用例追加一行）
+pub const RESULTS_FILE_ENV: &str = "SYSTEMQUEST_RESULTS_FILE";
+
+/// ResourceUsage - 学生程序一次运行的资源使用情况
+///
+/// 通过 wait4(2) 在回收子进程时获得（包括它等待过的子进程，
+/// 例如 your_program.sh 启动的 python 进程）。
+/// - 峰值 RSS 持续增长可以暴露手写双向链表的内存泄漏（Stage 4）
+/// - 大量非自愿上下文切换可以暴露锁竞争 / lock convoy（Stage 5）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResourceUsage {
+    /// 从启动到被回收的墙钟时间
+    pub wall_time: Duration,
+    pub user_time: Duration,
+    pub system_time: Duration,
+
+    /// 峰值常驻内存（KB，Linux 上 ru_maxrss 的单位）
+    pub max_rss_kb: u64,
+
+    pub voluntary_context_switches: u64,
+    pub involuntary_context_switches: u64,
+}
+
+impl ResourceUsage {
+    fn from_rusage(rusage: &libc::rusage, wall_time: Duration) -> Self {
+        Self {
+            wall_time,
+            user_time: timeval_to_duration(rusage.ru_utime),
+            system_time: timeval_to_duration(rusage.ru_stime),
+            max_rss_kb: rusage.ru_maxrss.max(0) as u64,
+            voluntary_context_switches: rusage.ru_nvcsw.max(0) as u64,
+            involuntary_context_switches: rusage.ru_nivcsw.max(0) as u64,
+        }
+    }
+
+    /// 机器可读形式
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "wall_time_ms": self.wall_time.as_micros() as f64 / 1000.0,
+            "user_time_ms": self.user_time.as_micros() as f64 / 1000.0,
+            "system_time_ms": self.system_time.as_micros() as f64 / 1000.0,
+            "max_rss_kb": self.max_rss_kb,
+            "voluntary_context_switches": self.voluntary_context_switches,
+            "involuntary_context_switches": self.involuntary_context_switches,
+        })
+    }
+}
+
+impl fmt::Display for ResourceUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "wall {:.3}s, user {:.3}s, sys {:.3}s, max RSS {:.1} MB, context switches {} voluntary / {} involuntary",
+            self.wall_time.as_secs_f64(),
+            self.user_time.as_secs_f64(),
+            self.system_time.as_secs_f64(),
+            self.max_rss_kb as f64 / 1024.0,
+            self.voluntary_context_switches,
+            self.involuntary_context_switches
+        )
+    }
+}
+
+/// 用 wait4 回收子进程并取得资源使用情况
+///
+/// `block` 为 false 时使用 WNOHANG: 进程仍在运行则返回 Ok(None)。
+/// 回收之后不能再对同一个 `Child` 调用 `try_wait` / `wait`。
+pub(crate) fn wait4(pid: u32, block: bool, wall_time: Duration) -> io::Result<Option<(ExitStatus, ResourceUsage)>> {
+    let mut status: libc::c_int = 0;
+    // SAFETY: rusage 是纯数据结构，全零是合法值
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    let options = if block { 0 } else { libc::WNOHANG };
+
+    loop {
+        let result = unsafe { libc::wait4(pid as libc::pid_t, &mut status, options, &mut rusage) };
+        match result {
+            0 => return Ok(None),
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            _ => {
+                return Ok(Some((
+                    ExitStatus::from_raw(status),
+                    ResourceUsage::from_rusage(&rusage, wall_time),
+                )));
+            }
+        }
+    }
+}
+
+/// 把一个测试用例的结果追加到 SYSTEMQUEST_RESULTS_FILE（未设置时不做任何事）
+pub fn record_result(description: &str, passed: bool, usage: Option<&ResourceUsage>) {
+    let Ok(path) = env::var(RESULTS_FILE_ENV) else {
+        return;
+    };
+
+    let line = json!({
+        "test": description,
+        "passed": passed,
+        "resource_usage": usage.map(ResourceUsage::to_json),
+    });
+
+    // 结果文件只是附加信息，写入失败不影响测试结果
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+fn timeval_to_duration(tv: libc::timeval) -> Duration {
+    Duration::from_secs(tv.tv_sec.max(0) as u64) + Duration::from_micros(tv.tv_usec.max(0) as u64)
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    /// 测试 wait4 回收子进程并返回资源使用情况
+    #[test]
+    fn test_wait4_reports_usage() {
+        // 只取 pid: 子进程由 wait4 回收，不再通过 Child::wait
+        let pid = Command::new("sh")
+            .args(["-c", "i=0; while [ $i -lt 20000 ]; do i=$((i+1)); done; exit 3"])
+            .spawn()
+            .unwrap()
+            .id();
+
+        let (status, usage) = wait4(pid, true, Duration::from_millis(5)).unwrap().unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert!(usage.max_rss_kb > 0);
+        assert!(usage.user_time + usage.system_time > Duration::ZERO);
+        assert_eq!(usage.wall_time, Duration::from_millis(5));
+    }
+
+    /// 测试机器可读形式
+    #[test]
+    fn test_usage_json() {
+        let usage = ResourceUsage {
+            wall_time: Duration::from_millis(1500),
+            max_rss_kb: 2048,
+            ..Default::default()
+        };
+        let value = usage.to_json();
+        assert_eq!(value["wall_time_ms"], 1500.0);
+        assert_eq!(value["max_rss_kb"], 2048);
+        assert!(usage.to_string().contains("max RSS 2.0 MB"));
+    }
+}